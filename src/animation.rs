@@ -0,0 +1,1144 @@
+//! Playback of SMIL animations, i.e. `<animate>`, `<animateTransform>`,
+//! `<animateMotion>` and `<set>` elements.
+//!
+//! `usvg` drops animation elements, so they are parsed in a separate pass over
+//! the raw XML document and stored as [`SvgAnimation`]s on the [`Svg`]. Insert
+//! a [`SvgAnimationPlayer`] on a SVG entity to play them back.
+//!
+//! Animations target elements by `id`, either the parent of the animation
+//! element or the element referenced with `href`. Animations of elements
+//! without an `id` and animations that begin on an event instead of a clock
+//! value are ignored. Supported attributes are `fill`, `stroke`, `opacity`,
+//! `fill-opacity` and `stroke-opacity`.
+//!
+//! Like in SMIL, animations replace the underlying value unless they have
+//! `additive="sum"`, `to` animations start from the underlying value and
+//! `by` animations without `from` add to it.
+
+use std::collections::HashMap;
+
+use bevy::{
+    asset::{Assets, Handle},
+    core::Time,
+    ecs::{entity::Entity, system::{Commands, Query, Res, ResMut}},
+    render::{color::Color, mesh::Mesh},
+};
+use lyon_svg::path::PathEvent;
+use lyon_tessellation::{
+    FillTessellator, StrokeTessellator,
+    math::{Angle, Point, Transform, point, vector},
+};
+use usvg::NodeExt;
+
+use crate::{
+    Convert,
+    geometry::{edge_at_length, length},
+    plugin::{TessellationOptions, mesh_order, tessellate_path_with},
+    metadata::element_id,
+    svg::{DrawType, PathDescriptor, Svg, parse_path_data},
+    vertex_buffer::{VertexBuffers, append},
+};
+
+const SVG_NS: &str = "http://www.w3.org/2000/svg";
+const XLINK_NS: &str = "http://www.w3.org/1999/xlink";
+
+/// A single SMIL animation of a SVG element.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgAnimation {
+    /// The `id` of the animated element.
+    pub target: String,
+    /// What gets animated.
+    pub effect: AnimationEffect,
+    /// When it gets animated.
+    pub timing: AnimationTiming,
+    /// Whether the animated value is added to the underlying value
+    /// (`additive="sum"`) instead of replacing it.
+    pub additive: bool,
+    /// Whether the animation starts from the underlying value, for `to`
+    /// animations of colors and opacities. Their `values` only hold the `to`
+    /// value.
+    pub from_underlying: bool,
+}
+
+impl SvgAnimation {
+    /// The two values to interpolate between at `progress` and the
+    /// interpolation factor.
+    fn values_at<'a, T>(&self, values: &'a [T], underlying: &'a T, progress: f32) -> Option<(&'a T, &'a T, f32)> {
+        if self.from_underlying {
+            let (_, _, t) = self.timing.interval(progress, 2);
+            return Some((underlying, values.first()?, t));
+        }
+        let (i, j, t) = self.timing.interval(progress, values.len());
+        Some((values.get(i)?, values.get(j)?, t))
+    }
+}
+
+/// What a [`SvgAnimation`] animates.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AnimationEffect {
+    /// The color of the `fill` or `stroke`.
+    Paint {
+        /// Whether the fill or the stroke is animated.
+        target: PaintTarget,
+        /// The animated values.
+        values: Vec<Color>,
+    },
+    /// The `opacity`, `fill-opacity` or `stroke-opacity`.
+    Opacity {
+        /// Whether the fill, the stroke or both are animated.
+        target: PaintTarget,
+        /// The animated values.
+        values: Vec<f32>,
+    },
+    /// A transform, from `<animateTransform>`.
+    Transform {
+        /// The kind of transform.
+        kind: TransformKind,
+        /// The parameters of the transform for each value, as in the
+        /// corresponding SVG transform function.
+        values: Vec<Vec<f32>>,
+    },
+    /// Movement along a path, from `<animateMotion>`.
+    Motion {
+        /// The motion path.
+        path: Vec<PathEvent>,
+        /// How the element is rotated along the path.
+        rotate: MotionRotate,
+    },
+}
+
+/// The painted part of an element that is affected by an animation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PaintTarget {
+    /// Only the fill.
+    Fill,
+    /// Only the stroke.
+    Stroke,
+    /// Fill and stroke.
+    Both,
+}
+
+/// Kind of transform animated by `<animateTransform>`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransformKind {
+    /// `translate(x [y])`
+    Translate,
+    /// `scale(x [y])`
+    Scale,
+    /// `rotate(angle [cx cy])`, angle in degrees.
+    Rotate,
+    /// `skewX(angle)`, angle in degrees.
+    SkewX,
+    /// `skewY(angle)`, angle in degrees.
+    SkewY,
+}
+
+/// Rotation of an element moving along a path.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MotionRotate {
+    /// A fixed angle in degrees.
+    Angle(f32),
+    /// Follow the direction of the path.
+    Auto,
+    /// Follow the opposite direction of the path.
+    AutoReverse,
+}
+
+/// Timing of a [`SvgAnimation`], all times are in seconds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationTiming {
+    /// Start of the animation.
+    pub begin: f32,
+    /// Duration of one iteration, `None` if indefinite.
+    pub duration: Option<f32>,
+    /// Number of iterations, `None` if indefinite.
+    pub repeat_count: Option<f32>,
+    /// Keep the last value after the animation ended.
+    pub freeze: bool,
+    /// Progress of each value in the iteration, evenly spaced if empty.
+    pub key_times: Vec<f32>,
+    /// Jump between values instead of interpolating them.
+    pub discrete: bool,
+}
+
+impl AnimationTiming {
+    /// Time at which the animation ends, `None` if it never does.
+    pub fn end(&self) -> Option<f32> {
+        let duration = self.duration?;
+        self.repeat_count.map(|count| self.begin + duration * count)
+    }
+
+    /// Progress through the current iteration at `time`, between `0.0` and
+    /// `1.0`. `None` if the animation isn't active and not frozen.
+    fn progress(&self, time: f32) -> Option<f32> {
+        let local = time - self.begin;
+        if local < 0.0 {
+            return None;
+        }
+        let duration = match self.duration {
+            Some(duration) if duration > 0.0 => duration,
+            _ => return Some(0.0),
+        };
+
+        match self.repeat_count {
+            Some(count) if local >= duration * count => {
+                if !self.freeze {
+                    return None;
+                }
+                let rest = count.fract();
+                Some(if rest > 0.0 { rest } else { 1.0 })
+            },
+            _ => Some((local % duration) / duration),
+        }
+    }
+
+    /// The indices of the two values to interpolate between at `progress` and
+    /// the interpolation factor.
+    fn interval(&self, progress: f32, len: usize) -> (usize, usize, f32) {
+        if len < 2 {
+            return (0, 0, 0.0);
+        }
+
+        let steps = if self.discrete { len } else { len - 1 };
+        let key_time = |i: usize| -> f32 {
+            if self.key_times.len() == len {
+                self.key_times[i]
+            } else {
+                i as f32 / steps as f32
+            }
+        };
+
+        let mut i = 0;
+        while i + 1 < len && key_time(i + 1) <= progress {
+            i += 1;
+        }
+        if self.discrete || i + 1 >= len {
+            return (i, i, 0.0);
+        }
+
+        let (t0, t1) = (key_time(i), key_time(i + 1));
+        let t = if t1 > t0 { (progress - t0) / (t1 - t0) } else { 0.0 };
+        (i, i + 1, t)
+    }
+}
+
+/// Component which plays back the [`SvgAnimation`]s of a [`Svg`].
+///
+/// Animations of elements inside a filtered group or painted with a pattern
+/// don't play, those are drawn by child entities that aren't re-tessellated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SvgAnimationPlayer {
+    /// Current time of the animation timeline, in seconds.
+    pub time: f32,
+    /// Factor by which the time advances compared to real time.
+    pub speed: f32,
+    /// Whether the time advances.
+    pub playing: bool,
+    /// Whether the timeline starts over after the last animation ended.
+    pub looping: bool,
+}
+
+impl Default for SvgAnimationPlayer {
+    fn default() -> Self {
+        SvgAnimationPlayer {
+            time: 0.0,
+            speed: 1.0,
+            playing: true,
+            looping: true,
+        }
+    }
+}
+
+impl SvgAnimationPlayer {
+    /// Resume playing.
+    pub fn play(&mut self) {
+        self.playing = true;
+    }
+
+    /// Pause playing, the animations keep their current state.
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    /// Jump to the given time, in seconds.
+    pub fn seek(&mut self, time: f32) {
+        self.time = time.max(0.0);
+    }
+}
+
+/// Static transforms of an animated element.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct ElementTransform {
+    /// The element's own `transform` attribute.
+    pub(crate) local: Transform,
+    /// Transform from the element to the coordinate system of the SVG,
+    /// including `local`.
+    pub(crate) abs: Transform,
+}
+
+/// Animated state of a single element.
+struct ElementState {
+    /// The animated `transform` attribute, `None` if it isn't animated.
+    transform: Option<Transform>,
+    motion: Option<Transform>,
+}
+
+impl ElementState {
+    /// The animated transform of the element, which takes the place of its
+    /// static `local` transform.
+    fn local(&self, local: Transform) -> Transform {
+        let transform = self.transform.unwrap_or(local);
+        match self.motion {
+            Some(ref motion) => transform.then(motion),
+            None => transform,
+        }
+    }
+}
+
+/// Animated state of a single path.
+struct PathState {
+    color: Color,
+    opacity: f32,
+}
+
+/// Geometry of the paths of an animated [`Svg`] that no animation targets, so
+/// that only the targeted paths need to be tessellated again.
+pub(crate) struct AnimationCache {
+    /// Tessellated untargeted paths or index of a targeted path, in the order
+    /// the paths get drawn.
+    parts: Vec<AnimationPart>,
+}
+
+enum AnimationPart {
+    Static(VertexBuffers),
+    Animated(usize),
+}
+
+/// Bevy system which advances the [`SvgAnimationPlayer`]s and re-tessellates
+/// the animated paths of their [`Svg`] with the animated values.
+pub(crate) fn svg_animation_player(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut fill_tess: ResMut<FillTessellator>,
+    mut stroke_tess: ResMut<StrokeTessellator>,
+    mut query: Query<(Entity, &Svg, &mut SvgAnimationPlayer, &Handle<Mesh>, Option<&AnimationCache>)>,
+) {
+    for (entity, svg, mut player, mesh, cache) in query.iter_mut() {
+        if svg.animations.is_empty() {
+            continue;
+        }
+        if player.playing {
+            player.time += time.delta_seconds() * player.speed;
+            let end = svg.animations.iter()
+                .filter_map(|animation| animation.timing.end())
+                .fold(0.0_f32, f32::max);
+            if player.looping && end > 0.0 && player.time > end {
+                player.time %= end;
+            }
+        }
+        if !player.is_changed() {
+            continue;
+        }
+
+        let new_cache = match cache {
+            Some(_) => None,
+            None => Some(animation_cache(&mut fill_tess, &mut stroke_tess, svg)),
+        };
+        let cache = match cache.or_else(|| new_cache.as_ref()) {
+            Some(cache) => cache,
+            None => continue,
+        };
+
+        let elements = animate_elements(&svg.animations, &svg.element_transforms, player.time);
+
+        let mut buffers = VertexBuffers::new();
+        for part in cache.parts.iter() {
+            let path = match *part {
+                AnimationPart::Static(ref geometry) => {
+                    append(&mut buffers, geometry);
+                    continue;
+                },
+                AnimationPart::Animated(index) => &svg.paths[index],
+            };
+
+            let mut state = PathState { color: path.color, opacity: 1.0 };
+            for animation in svg.animations.iter().filter(|animation| targets(animation, path)) {
+                if let Some(progress) = animation.timing.progress(player.time) {
+                    animate_path(&mut state, matches!(path.draw_type, DrawType::Fill), animation, progress);
+                }
+            }
+
+            let transform = path_transform(path, &svg.element_transforms, &elements);
+            let mut animated = path.clone();
+            animated.color = state.color;
+            animated.color.set_a(state.color.a() * state.opacity);
+
+            let segments = path.segments.iter().map(|event| event.transformed(&transform));
+            tessellate_path_with(
+                &mut fill_tess, &mut stroke_tess, &animated, segments, TessellationOptions::of(svg), &mut buffers,
+            );
+        }
+        if let Some(ref nine_slice) = svg.nine_slice {
+            nine_slice.apply(&svg.view_box, &mut buffers);
+        }
+
+        if let Some(mesh) = meshes.get_mut(mesh) {
+            *mesh = buffers.convert();
+        }
+
+        if let Some(new_cache) = new_cache {
+            commands.entity(entity).insert(new_cache);
+        }
+    }
+}
+
+/// The transform from the static to the animated segments of a path. Going
+/// from the outermost group inwards, each animated element is placed with its
+/// animated transform into the animated coordinate system of its parent.
+fn path_transform(
+    path: &PathDescriptor,
+    transforms: &HashMap<String, ElementTransform>,
+    elements: &HashMap<&str, ElementState>,
+) -> Transform {
+    let inverse = |transform: &Transform| transform.inverse().unwrap_or_else(Transform::identity);
+
+    // Static and animated transform of the innermost animated element so far.
+    let mut animated: Option<(Transform, Transform)> = None;
+    for id in path.group_ids.iter().rev().chain(std::iter::once(&path.id)) {
+        let (static_transform, element) = match (transforms.get(id), elements.get(id.as_str())) {
+            (Some(static_transform), Some(element)) => (static_transform, element),
+            _ => continue,
+        };
+        // From the parent of the element to the SVG, through the animated
+        // outer element if there is one.
+        let mut parent = inverse(&static_transform.local).then(&static_transform.abs);
+        if let Some((outer, outer_animated)) = animated {
+            parent = parent.then(&inverse(&outer)).then(&outer_animated);
+        }
+        animated = Some((static_transform.abs, element.local(static_transform.local).then(&parent)));
+    }
+
+    match animated {
+        Some((abs, animated)) => inverse(&abs).then(&animated),
+        None => Transform::identity(),
+    }
+}
+
+/// Whether the animation targets the path or one of its ancestor groups.
+fn targets(animation: &SvgAnimation, path: &PathDescriptor) -> bool {
+    animation.target == path.id || path.group_ids.contains(&animation.target)
+}
+
+/// Tessellate the paths of a [`Svg`] that no animation targets.
+fn animation_cache(
+    fill_tess: &mut FillTessellator,
+    stroke_tess: &mut StrokeTessellator,
+    svg: &Svg,
+) -> AnimationCache {
+    let mut parts: Vec<AnimationPart> = Vec::new();
+    for index in mesh_order(&svg.paths, svg.anti_alias.is_some()) {
+        let path = &svg.paths[index];
+        if svg.animations.iter().any(|animation| targets(animation, path)) {
+            parts.push(AnimationPart::Animated(index));
+            continue;
+        }
+        // Consecutive untargeted paths share their buffers.
+        if !matches!(parts.last(), Some(AnimationPart::Static(_))) {
+            parts.push(AnimationPart::Static(VertexBuffers::new()));
+        }
+        if let Some(AnimationPart::Static(ref mut buffers)) = parts.last_mut() {
+            tessellate_path_with(
+                fill_tess, stroke_tess, path, path.segments.iter().cloned(),
+                TessellationOptions::of(svg), buffers,
+            );
+        }
+    }
+
+    AnimationCache { parts }
+}
+
+/// Evaluate the transform and motion animations of all elements at `time`.
+fn animate_elements<'a>(
+    animations: &'a [SvgAnimation],
+    transforms: &HashMap<String, ElementTransform>,
+    time: f32,
+) -> HashMap<&'a str, ElementState> {
+    let mut elements: HashMap<&str, ElementState> = HashMap::new();
+    for animation in animations.iter() {
+        let progress = match animation.timing.progress(time) {
+            Some(progress) => progress,
+            None => continue,
+        };
+        let transform = match animation.effect {
+            AnimationEffect::Transform { kind, ref values } => {
+                let (i, j, t) = animation.timing.interval(progress, values.len());
+                match (values.get(i), values.get(j)) {
+                    (Some(a), Some(b)) => transform(kind, &lerp_values(a, b, t)),
+                    _ => continue,
+                }
+            },
+            AnimationEffect::Motion { ref path, rotate } => {
+                if let Some(motion) = motion(path, rotate, progress) {
+                    elements.entry(animation.target.as_str())
+                        .or_insert(ElementState { transform: None, motion: None })
+                        .motion = Some(motion);
+                }
+                continue;
+            },
+            _ => continue,
+        };
+
+        let element = elements.entry(animation.target.as_str())
+            .or_insert(ElementState { transform: None, motion: None });
+        element.transform = Some(if animation.additive {
+            // Added animations apply before the underlying transform, i.e. on
+            // the right of the `transform` attribute.
+            let local = transforms.get(&animation.target).map_or_else(Transform::identity, |t| t.local);
+            transform.then(&element.transform.unwrap_or(local))
+        } else {
+            transform
+        });
+    }
+
+    elements
+}
+
+/// Apply a color or opacity animation to the state of a path.
+fn animate_path(state: &mut PathState, is_fill: bool, animation: &SvgAnimation, progress: f32) {
+    let affects = |target: PaintTarget| match target {
+        PaintTarget::Both => true,
+        PaintTarget::Fill => is_fill,
+        PaintTarget::Stroke => !is_fill,
+    };
+
+    match animation.effect {
+        AnimationEffect::Paint { target, ref values } if affects(target) => {
+            let underlying = state.color;
+            if let Some((a, b, t)) = animation.values_at(values, &underlying, progress) {
+                let mut value = Color::rgba(
+                    lerp(a.r(), b.r(), t),
+                    lerp(a.g(), b.g(), t),
+                    lerp(a.b(), b.b(), t),
+                    underlying.a(),
+                );
+                if animation.additive {
+                    value = add_colors(&value, &underlying);
+                }
+                state.color = value;
+            }
+        },
+        AnimationEffect::Opacity { target, ref values } if affects(target) => {
+            let underlying = if target == PaintTarget::Both { state.opacity } else { state.color.a() };
+            if let Some((a, b, t)) = animation.values_at(values, &underlying, progress) {
+                let mut value = lerp(*a, *b, t);
+                if animation.additive {
+                    value += underlying;
+                }
+                if target == PaintTarget::Both {
+                    state.opacity = value;
+                } else {
+                    state.color.set_a(value);
+                }
+            }
+        },
+        _ => {},
+    }
+}
+
+fn transform(kind: TransformKind, values: &[f32]) -> Transform {
+    let value = |i: usize| values.get(i).copied();
+    match kind {
+        TransformKind::Translate =>
+            Transform::translation(value(0).unwrap_or(0.0), value(1).unwrap_or(0.0)),
+        TransformKind::Scale => {
+            let x = value(0).unwrap_or(1.0);
+            Transform::scale(x, value(1).unwrap_or(x))
+        },
+        TransformKind::Rotate => {
+            let (cx, cy) = (value(1).unwrap_or(0.0), value(2).unwrap_or(0.0));
+            Transform::translation(-cx, -cy)
+                .then_rotate(Angle::degrees(value(0).unwrap_or(0.0)))
+                .then_translate(vector(cx, cy))
+        },
+        TransformKind::SkewX =>
+            Transform::new(1.0, 0.0, value(0).unwrap_or(0.0).to_radians().tan(), 1.0, 0.0, 0.0),
+        TransformKind::SkewY =>
+            Transform::new(1.0, value(0).unwrap_or(0.0).to_radians().tan(), 0.0, 1.0, 0.0, 0.0),
+    }
+}
+
+fn motion(path: &[PathEvent], rotate: MotionRotate, progress: f32) -> Option<Transform> {
+    let (edge, t) = edge_at_length(path, length(path) * progress)?;
+    let position = edge.sample(t);
+    let angle = match rotate {
+        MotionRotate::Angle(degrees) => Angle::degrees(degrees),
+        MotionRotate::Auto => edge.tangent(t).angle_from_x_axis(),
+        MotionRotate::AutoReverse => edge.tangent(t).angle_from_x_axis() + Angle::pi(),
+    };
+
+    Some(Transform::rotation(angle).then_translate(position.to_vector()))
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn lerp_values(a: &[f32], b: &[f32], t: f32) -> Vec<f32> {
+    a.iter().zip(b.iter()).map(|(a, b)| lerp(*a, *b, t)).collect()
+}
+
+/// Sum of the channels of two colors, keeping the alpha of `a`.
+fn add_colors(a: &Color, b: &Color) -> Color {
+    Color::rgba((a.r() + b.r()).min(1.0), (a.g() + b.g()).min(1.0), (a.b() + b.b()).min(1.0), a.a())
+}
+
+/// Sum of two parameter lists, the shorter one padded with zeros.
+fn add_values(a: &[f32], b: &[f32]) -> Vec<f32> {
+    (0..a.len().max(b.len()))
+        .map(|i| a.get(i).unwrap_or(&0.0) + b.get(i).unwrap_or(&0.0))
+        .collect()
+}
+
+/// The parameters of a transform function that leave points where they are.
+fn neutral_values(kind: TransformKind) -> Vec<f32> {
+    match kind {
+        TransformKind::Scale => vec![1.0, 1.0],
+        TransformKind::Translate => vec![0.0, 0.0],
+        TransformKind::Rotate => vec![0.0, 0.0, 0.0],
+        TransformKind::SkewX | TransformKind::SkewY => vec![0.0],
+    }
+}
+
+/// Complete the values of a `by` animation: `from` and `from + by`, or zero
+/// and `by` if there is no `from`.
+fn with_by<T: Clone>(mut values: Vec<T>, by: Option<T>, zero: T, add: impl Fn(&T, &T) -> T) -> Vec<T> {
+    if let Some(by) = by {
+        let from = values.pop().unwrap_or(zero);
+        let to = add(&from, &by);
+        values = vec![from, to];
+    }
+    values
+}
+
+/// The `transform` attributes of all animated elements, by `id`.
+pub(crate) fn local_transforms(doc: &roxmltree::Document<'_>, animations: &[SvgAnimation]) -> HashMap<String, Transform> {
+    doc.descendants()
+        .filter_map(|node| Some((node.attribute("id")?, node)))
+        .filter(|(id, _)| animations.iter().any(|animation| animation.target == *id))
+        .map(|(id, node)| (id.to_string(), node.attribute("transform").map_or_else(Transform::identity, parse_transform)))
+        .collect()
+}
+
+/// Pair the `transform` attributes of the animated elements with their
+/// absolute transforms in the usvg tree.
+pub(crate) fn element_transforms(
+    tree: &usvg::Tree,
+    locals: &HashMap<String, Transform>,
+) -> HashMap<String, ElementTransform> {
+    tree.root().descendants()
+        .filter_map(|node| {
+            let id = element_id(node.borrow().id()).to_string();
+            let local = *locals.get(&id)?;
+            let t = node.abs_transform();
+            let abs = Transform::new(t.a as f32, t.b as f32, t.c as f32, t.d as f32, t.e as f32, t.f as f32);
+            Some((id, ElementTransform { local, abs }))
+        })
+        .collect()
+}
+
+/// Parse all supported animation elements of a SVG document.
+pub(crate) fn parse_animations(doc: &roxmltree::Document<'_>) -> Vec<SvgAnimation> {
+    doc.descendants()
+        .filter(|node| node.is_element() && node.tag_name().namespace() == Some(SVG_NS))
+        .filter_map(|node| parse_animation(doc, node))
+        .collect()
+}
+
+fn parse_animation(doc: &roxmltree::Document<'_>, node: roxmltree::Node<'_, '_>) -> Option<SvgAnimation> {
+    let tag = node.tag_name().name();
+    if !["animate", "animateTransform", "animateMotion", "set"].contains(&tag) {
+        return None;
+    }
+
+    let target_node = match href(node) {
+        Some(id) => doc.descendants().find(|n| n.attribute("id") == Some(id))?,
+        None => node.parent_element()?,
+    };
+    let target = target_node.attribute("id")?.to_string();
+
+    let is_set = tag == "set";
+    let timing = AnimationTiming {
+        begin: match node.attribute("begin") {
+            Some(begin) => parse_clock(begin.split(';').next()?)?,
+            None => 0.0,
+        },
+        duration: node.attribute("dur").and_then(parse_clock),
+        repeat_count: match node.attribute("repeatCount") {
+            Some("indefinite") => None,
+            Some(count) => Some(count.trim().parse().ok()?),
+            None => Some(1.0),
+        },
+        freeze: node.attribute("fill") == Some("freeze"),
+        key_times: node.attribute("keyTimes")
+            .map(|times| times.split(';').filter_map(|t| t.trim().parse().ok()).collect())
+            .unwrap_or_default(),
+        discrete: is_set || node.attribute("calcMode") == Some("discrete"),
+    };
+
+    let values: Vec<&str> = if is_set {
+        vec![node.attribute("to")?]
+    } else if let Some(values) = node.attribute("values") {
+        values.split(';').map(str::trim).filter(|v| !v.is_empty()).collect()
+    } else {
+        node.attribute("from").into_iter().chain(node.attribute("to")).collect()
+    };
+    let is_simple = !is_set && node.attribute("values").is_none();
+    // `to` takes precedence over `by`.
+    let by = node.attribute("by").filter(|_| is_simple && node.attribute("to").is_none());
+    // A `to` animation starts from the underlying value and is never
+    // additive, a `by` animation without `from` always is.
+    let to_only = is_simple && node.attribute("from").is_none() && node.attribute("to").is_some();
+    let by_only = by.is_some() && node.attribute("from").is_none();
+    let additive = !to_only && (by_only || node.attribute("additive") == Some("sum"));
+    let from_underlying = to_only && tag == "animate";
+
+    let effect = match tag {
+        "animateMotion" => {
+            let path = match node.attribute("path") {
+                Some(d) => parse_path_data(d)?,
+                None => match node.children().find(|n| n.has_tag_name((SVG_NS, "mpath"))) {
+                    Some(mpath) => {
+                        let id = href(mpath)?;
+                        let path = doc.descendants().find(|n| n.attribute("id") == Some(id))?;
+                        parse_path_data(path.attribute("d")?)?
+                    },
+                    None => motion_path_from_points(&values)?,
+                },
+            };
+            let rotate = match node.attribute("rotate") {
+                Some("auto") => MotionRotate::Auto,
+                Some("auto-reverse") => MotionRotate::AutoReverse,
+                Some(angle) => MotionRotate::Angle(angle.trim().parse().ok()?),
+                None => MotionRotate::Angle(0.0),
+            };
+            AnimationEffect::Motion { path, rotate }
+        },
+        "animateTransform" => {
+            let kind = transform_kind(node.attribute("type")?)?;
+            let mut values: Vec<Vec<f32>> = values.iter().map(|v| parse_numbers(v)).collect();
+            if to_only {
+                // Transform lists can't be interpolated, so the animation
+                // starts from the `transform` attribute only if that is a
+                // single function of the same kind.
+                let functions: Vec<_> = target_node.attribute("transform")
+                    .map(|transform| transform_functions(transform).collect())
+                    .unwrap_or_default();
+                let from = match functions[..] {
+                    [(name, ref from)] if transform_kind(name) == Some(kind) => from.clone(),
+                    _ => neutral_values(kind),
+                };
+                values.insert(0, from);
+            }
+            let values = with_by(values, by.map(parse_numbers), neutral_values(kind), |a, b| add_values(a, b));
+            AnimationEffect::Transform { kind, values }
+        },
+        _ => match node.attribute("attributeName")? {
+            name @ "fill" | name @ "stroke" => {
+                let colors = values.iter().map(|v| parse_color(v)).collect::<Option<_>>()?;
+                let by = match by {
+                    Some(by) => Some(parse_color(by)?),
+                    None => None,
+                };
+                AnimationEffect::Paint {
+                    target: if name == "fill" { PaintTarget::Fill } else { PaintTarget::Stroke },
+                    values: with_by(colors, by, Color::BLACK, add_colors),
+                }
+            },
+            name @ "opacity" | name @ "fill-opacity" | name @ "stroke-opacity" => {
+                let opacities = values.iter().map(|v| v.parse().ok()).collect::<Option<_>>()?;
+                let by = match by {
+                    Some(by) => Some(by.trim().parse().ok()?),
+                    None => None,
+                };
+                AnimationEffect::Opacity {
+                    target: match name {
+                        "fill-opacity" => PaintTarget::Fill,
+                        "stroke-opacity" => PaintTarget::Stroke,
+                        _ => PaintTarget::Both,
+                    },
+                    values: with_by(opacities, by, 0.0, |a, b| a + b),
+                }
+            },
+            _ => return None,
+        },
+    };
+
+    Some(SvgAnimation { target, effect, timing, additive, from_underlying })
+}
+
+/// The id referenced by the `href` or `xlink:href` attribute of a node.
+fn href<'a>(node: roxmltree::Node<'a, '_>) -> Option<&'a str> {
+    node.attribute((XLINK_NS, "href"))
+        .or_else(|| node.attribute("href"))
+        .and_then(|href| href.strip_prefix('#'))
+}
+
+/// Parse a SMIL clock value into seconds, e.g. `2s`, `500ms`, `1.5` or `00:01:30`.
+fn parse_clock(value: &str) -> Option<f32> {
+    let value = value.trim();
+    if value.contains(':') {
+        return value.split(':').try_fold(0.0, |total, part| {
+            part.parse::<f32>().ok().map(|part| total * 60.0 + part)
+        });
+    }
+
+    let units = [("ms", 0.001), ("min", 60.0), ("h", 3600.0), ("s", 1.0)];
+    for (unit, factor) in units.iter() {
+        if let Some(number) = value.strip_suffix(unit) {
+            return number.trim().parse::<f32>().ok().map(|n| n * factor);
+        }
+    }
+
+    value.parse().ok()
+}
+
+fn transform_kind(name: &str) -> Option<TransformKind> {
+    match name {
+        "translate" => Some(TransformKind::Translate),
+        "scale" => Some(TransformKind::Scale),
+        "rotate" => Some(TransformKind::Rotate),
+        "skewX" => Some(TransformKind::SkewX),
+        "skewY" => Some(TransformKind::SkewY),
+        _ => None,
+    }
+}
+
+/// The functions of a `transform` attribute with their parameters.
+fn transform_functions(value: &str) -> impl Iterator<Item = (&str, Vec<f32>)> {
+    value.split(')').filter_map(|function| {
+        let mut parts = function.splitn(2, '(');
+        let name = parts.next()?.trim_matches(|c: char| c == ',' || c.is_whitespace());
+        Some((name, parse_numbers(parts.next()?)))
+    })
+}
+
+/// Parse a `transform` attribute, unknown functions are skipped.
+fn parse_transform(value: &str) -> Transform {
+    transform_functions(value).fold(Transform::identity(), |parsed, (name, values)| {
+        let function = match (name, &values[..]) {
+            ("matrix", &[a, b, c, d, e, f]) => Transform::new(a, b, c, d, e, f),
+            _ => match transform_kind(name) {
+                Some(kind) => transform(kind, &values),
+                None => return parsed,
+            },
+        };
+        // The functions apply from right to left.
+        function.then(&parsed)
+    })
+}
+
+fn parse_numbers(value: &str) -> Vec<f32> {
+    value.split(|c: char| c == ',' || c.is_whitespace())
+        .filter_map(|n| n.parse().ok())
+        .collect()
+}
+
+/// Parse a color in hex, `rgb()` or one of the basic color keyword notations.
+fn parse_color(value: &str) -> Option<Color> {
+    let value = value.trim();
+    if let Some(hex) = value.strip_prefix('#') {
+        return Color::hex(hex).ok();
+    }
+    if let Some(rgb) = value.strip_prefix("rgb(").and_then(|v| v.strip_suffix(')')) {
+        let channels: Vec<f32> = rgb.split(',')
+            .map(|c| {
+                let c = c.trim();
+                match c.strip_suffix('%') {
+                    Some(percent) => percent.parse::<f32>().ok().map(|p| p / 100.0),
+                    None => c.parse::<f32>().ok().map(|c| c / 255.0),
+                }
+            })
+            .collect::<Option<_>>()?;
+        return match channels[..] {
+            [r, g, b] => Some(Color::rgb(r, g, b)),
+            _ => None,
+        };
+    }
+
+    match value {
+        "black" => Some(Color::BLACK),
+        "white" => Some(Color::WHITE),
+        "red" => Some(Color::RED),
+        "green" => Some(Color::rgb_u8(0, 128, 0)),
+        "lime" => Some(Color::GREEN),
+        "blue" => Some(Color::BLUE),
+        "yellow" => Some(Color::YELLOW),
+        "cyan" | "aqua" => Some(Color::CYAN),
+        "magenta" | "fuchsia" => Some(Color::FUCHSIA),
+        "gray" | "grey" => Some(Color::GRAY),
+        "orange" => Some(Color::ORANGE),
+        "purple" => Some(Color::PURPLE),
+        _ => None,
+    }
+}
+
+/// Build a polyline motion path from `x,y` values.
+fn motion_path_from_points(values: &[&str]) -> Option<Vec<PathEvent>> {
+    let points: Vec<Point> = values.iter()
+        .map(|v| match parse_numbers(v)[..] {
+            [x, y] => Some(point(x, y)),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+
+    let (first, last) = (*points.first()?, *points.last()?);
+    let mut path = vec![PathEvent::Begin { at: first }];
+    path.extend(points.windows(2).map(|w| PathEvent::Line { from: w[0], to: w[1] }));
+    path.push(PathEvent::End { last, first, close: false });
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{metadata::parse_xml, test_util::assert_near};
+
+    fn animations(body: &str) -> Vec<SvgAnimation> {
+        let text = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">{}</svg>"#,
+            body,
+        );
+        let doc = parse_xml(&text).unwrap();
+        parse_animations(&doc)
+    }
+
+    #[test]
+    fn clock_values() {
+        assert_eq!(parse_clock("2s"), Some(2.0));
+        assert_eq!(parse_clock("500ms"), Some(0.5));
+        assert_eq!(parse_clock(" 1.5 "), Some(1.5));
+        assert_eq!(parse_clock("2min"), Some(120.0));
+        assert_eq!(parse_clock("00:01:30"), Some(90.0));
+        assert_eq!(parse_clock("soon"), None);
+    }
+
+    #[test]
+    fn colors() {
+        let red = parse_color("#ff0000").unwrap();
+        assert_eq!((red.r(), red.g(), red.b()), (1.0, 0.0, 0.0));
+        let blue = parse_color("rgb(0, 0, 100%)").unwrap();
+        assert_eq!((blue.r(), blue.g(), blue.b()), (0.0, 0.0, 1.0));
+        assert_eq!(parse_color("white"), Some(Color::WHITE));
+        assert_eq!(parse_color("rgb(1, 2)"), None);
+        assert_eq!(parse_color("chartreuse"), None);
+    }
+
+    #[test]
+    fn animate_targets_parent_or_href() {
+        let parsed = animations(r##"
+            <rect id="box" width="10" height="10">
+                <animate attributeName="opacity" from="1" to="0" dur="2s" begin="1s" repeatCount="3" fill="freeze"/>
+            </rect>
+            <animateTransform xlink:href="#box" attributeName="transform" type="rotate" values="0 5 5;90 5 5" dur="1s"/>
+        "##);
+        assert_eq!(parsed.len(), 2);
+
+        assert_eq!(parsed[0].target, "box");
+        assert_eq!(parsed[0].effect, AnimationEffect::Opacity { target: PaintTarget::Both, values: vec![1.0, 0.0] });
+        assert_eq!(parsed[0].timing.begin, 1.0);
+        assert_eq!(parsed[0].timing.duration, Some(2.0));
+        assert_eq!(parsed[0].timing.end(), Some(7.0));
+        assert!(parsed[0].timing.freeze);
+
+        assert_eq!(parsed[1].target, "box");
+        assert_eq!(parsed[1].effect, AnimationEffect::Transform {
+            kind: TransformKind::Rotate,
+            values: vec![vec![0.0, 5.0, 5.0], vec![90.0, 5.0, 5.0]],
+        });
+        assert!(!parsed[1].timing.freeze);
+    }
+
+    #[test]
+    fn unsupported_attributes_are_skipped() {
+        let parsed = animations(r#"
+            <rect id="box" width="10" height="10">
+                <animate attributeName="width" from="10" to="20" dur="1s"/>
+            </rect>
+        "#);
+        assert!(parsed.is_empty());
+    }
+
+    #[test]
+    fn set_freezes_only_with_fill_freeze() {
+        let parsed = animations(r#"
+            <rect id="box" width="10" height="10">
+                <set attributeName="fill" to="red" dur="1s"/>
+                <set attributeName="fill" to="blue" dur="1s" fill="freeze"/>
+            </rect>
+        "#);
+        assert_eq!(parsed.len(), 2);
+        assert!(parsed[0].timing.discrete);
+
+        assert_eq!(parsed[0].timing.progress(0.5), Some(0.5));
+        assert_eq!(parsed[0].timing.progress(1.5), None);
+        assert_eq!(parsed[1].timing.progress(1.5), Some(1.0));
+    }
+
+    #[test]
+    fn progress_of_repeated_animation() {
+        let timing = AnimationTiming {
+            begin: 1.0,
+            duration: Some(2.0),
+            repeat_count: Some(1.5),
+            freeze: true,
+            key_times: Vec::new(),
+            discrete: false,
+        };
+        assert_eq!(timing.progress(0.5), None);
+        assert_near(timing.progress(2.0).unwrap(), 0.5, 1e-6);
+        assert_near(timing.progress(3.5).unwrap(), 0.25, 1e-6);
+        assert_near(timing.progress(10.0).unwrap(), 0.5, 1e-6);
+
+        let indefinite = AnimationTiming { repeat_count: None, freeze: false, ..timing };
+        assert_near(indefinite.progress(100.0).unwrap(), 0.5, 1e-6);
+    }
+
+    #[test]
+    fn intervals_follow_key_times() {
+        let even = AnimationTiming {
+            begin: 0.0,
+            duration: Some(1.0),
+            repeat_count: Some(1.0),
+            freeze: false,
+            key_times: Vec::new(),
+            discrete: false,
+        };
+        let (from, to, t) = even.interval(0.75, 3);
+        assert_eq!((from, to), (1, 2));
+        assert_near(t, 0.5, 1e-6);
+
+        let keyed = AnimationTiming { key_times: vec![0.0, 0.8, 1.0], ..even.clone() };
+        let (from, to, t) = keyed.interval(0.4, 3);
+        assert_eq!((from, to), (0, 1));
+        assert_near(t, 0.5, 1e-6);
+
+        let discrete = AnimationTiming { discrete: true, ..even };
+        assert_eq!(discrete.interval(0.4, 2), (0, 0, 0.0));
+        assert_eq!(discrete.interval(0.6, 2), (1, 1, 0.0));
+    }
+
+    #[test]
+    fn motion_path_from_values() {
+        let path = motion_path_from_points(&["0,0", "10 0", "10,10"]).unwrap();
+        assert_eq!(path.len(), 4);
+        assert_near(length(&path), 20.0, 1e-4);
+        assert_eq!(motion_path_from_points(&["0,0", "10"]), None);
+    }
+
+    #[test]
+    fn transforms_apply_in_the_element_coordinate_system() {
+        let svg = crate::test_util::load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="100">
+            <g transform="translate(100 0)">
+                <rect id="square" width="10" height="10">
+                    <animateTransform attributeName="transform" type="rotate" from="0 5 5" to="180 5 5" dur="1s"/>
+                </rect>
+            </g>
+        </svg>"#);
+        let elements = animate_elements(&svg.animations, &svg.element_transforms, 0.5);
+        // A quarter turn around the center of the square, not around the origin of the SVG.
+        let corner = path_transform(&svg.paths[0], &svg.element_transforms, &elements)
+            .transform_point(point(100.0, 0.0));
+        assert_near(corner.x, 110.0, 1e-3);
+        assert_near(corner.y, 0.0, 1e-3);
+    }
+
+    #[test]
+    fn group_transforms_apply_in_the_group_coordinate_system() {
+        let svg = crate::test_util::load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="100">
+            <g id="arm" transform="translate(100 0)">
+                <animateTransform attributeName="transform" type="rotate" from="0" to="180" dur="1s" additive="sum"/>
+                <g transform="translate(10 0)">
+                    <rect id="hand" width="10" height="10" transform="scale(2)"/>
+                </g>
+            </g>
+        </svg>"#);
+        let elements = animate_elements(&svg.animations, &svg.element_transforms, 0.5);
+        // The corner at (10, 0) of the rectangle is at (30, 0) in the group,
+        // which turns a quarter around the group's origin.
+        let corner = path_transform(&svg.paths[0], &svg.element_transforms, &elements)
+            .transform_point(point(130.0, 0.0));
+        assert_near(corner.x, 100.0, 1e-3);
+        assert_near(corner.y, 30.0, 1e-3);
+    }
+
+    #[test]
+    fn transforms_replace_the_transform_attribute_unless_additive() {
+        let corner = |additive: &str| {
+            let svg = crate::test_util::load(&format!(r#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="100">
+                <rect id="square" width="10" height="10" transform="translate(100 0)">
+                    <animateTransform attributeName="transform" type="translate" from="0 0" to="20 0" dur="1s" {}/>
+                </rect>
+            </svg>"#, additive));
+            let elements = animate_elements(&svg.animations, &svg.element_transforms, 0.5);
+            path_transform(&svg.paths[0], &svg.element_transforms, &elements).transform_point(point(100.0, 0.0))
+        };
+        assert_near(corner("").x, 10.0, 1e-3);
+        assert_near(corner(r#"additive="replace""#).x, 10.0, 1e-3);
+        assert_near(corner(r#"additive="sum""#).x, 110.0, 1e-3);
+    }
+
+    #[test]
+    fn to_animations_start_from_the_underlying_value() {
+        let parsed = animations(r#"
+            <rect id="box" width="10" height="10" transform="translate(100 0)">
+                <animate attributeName="fill-opacity" to="0" dur="1s" additive="sum"/>
+                <animateTransform attributeName="transform" type="translate" to="200 0" dur="1s"/>
+                <animateTransform attributeName="transform" type="rotate" to="90" dur="1s"/>
+            </rect>
+        "#);
+        assert!(parsed[0].from_underlying);
+        assert!(!parsed[0].additive);
+        let mut state = PathState { color: Color::rgba(1.0, 0.0, 0.0, 0.8), opacity: 1.0 };
+        animate_path(&mut state, true, &parsed[0], 0.5);
+        assert_near(state.color.a(), 0.4, 1e-5);
+
+        assert_eq!(parsed[1].effect, AnimationEffect::Transform {
+            kind: TransformKind::Translate,
+            values: vec![vec![100.0, 0.0], vec![200.0, 0.0]],
+        });
+        assert_eq!(parsed[2].effect, AnimationEffect::Transform {
+            kind: TransformKind::Rotate,
+            values: vec![vec![0.0, 0.0, 0.0], vec![90.0]],
+        });
+    }
+
+    #[test]
+    fn by_animations_add_to_from_or_the_underlying_value() {
+        let parsed = animations(r#"
+            <rect id="box" width="10" height="10">
+                <animate attributeName="fill-opacity" by="-0.5" dur="1s"/>
+                <animateTransform attributeName="transform" type="rotate" from="10 5 5" by="80" dur="1s"/>
+            </rect>
+        "#);
+        assert!(parsed[0].additive);
+        assert_eq!(parsed[0].effect, AnimationEffect::Opacity { target: PaintTarget::Fill, values: vec![0.0, -0.5] });
+        let mut state = PathState { color: Color::BLACK, opacity: 1.0 };
+        animate_path(&mut state, true, &parsed[0], 1.0);
+        assert_near(state.color.a(), 0.5, 1e-5);
+
+        assert!(!parsed[1].additive);
+        assert_eq!(parsed[1].effect, AnimationEffect::Transform {
+            kind: TransformKind::Rotate,
+            values: vec![vec![10.0, 5.0, 5.0], vec![90.0, 5.0, 5.0]],
+        });
+    }
+
+    #[test]
+    fn transform_attributes_apply_from_right_to_left() {
+        let p = parse_transform("translate(10, 0) scale(2)").transform_point(point(1.0, 1.0));
+        assert_eq!((p.x, p.y), (12.0, 2.0));
+        let p = parse_transform("matrix(1 0 0 1 5 6) skewY(0)").transform_point(point(1.0, 1.0));
+        assert_eq!((p.x, p.y), (6.0, 7.0));
+    }
+}
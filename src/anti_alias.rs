@@ -0,0 +1,191 @@
+//! Anti-aliasing of SVG edges without multisampling.
+//!
+//! Every outline gets a thin band of extra triangles around it, the fringe.
+//! Its inner vertices have a coverage of `1` and its outer vertices a coverage
+//! of `0`, so the fragment shader fades the shape out across the fringe. This
+//! works on platforms without MSAA, like WebGL, but slightly widens the shape.
+//!
+//! Anti-aliased SVGs are drawn without depth writes, otherwise the transparent
+//! outer part of the fringe would hide shapes drawn behind it later on.
+
+use bevy::{log::error, render::color::Color};
+use lyon_svg::path::PathEvent;
+use lyon_tessellation::{
+    BuffersBuilder, Side, StrokeOptions, StrokeTessellator, StrokeVertex, StrokeVertexConstructor,
+};
+
+use crate::{
+    geometry::{Contour, contours},
+    vertex_buffer::{IndexType, Vertex, VertexBuffers},
+};
+
+/// Maximum length of the fringe at sharp corners, relative to its width.
+const MITER_LIMIT: f32 = 4.0;
+
+/// Append the fringe around the outline of a filled path to `buffers`. Every
+/// sub-path is treated as closed, like the fill tessellator does, and the
+/// fringe of every edge lies outside of the area filled with the even-odd
+/// rule, so holes get their fringe on the inside.
+pub(crate) fn fill_fringe(
+    segments: &[PathEvent],
+    color: Color,
+    width: f32,
+    tolerance: f32,
+    buffers: &mut VertexBuffers,
+) {
+    let color = [color.r(), color.g(), color.b(), color.a()];
+    for contour in contours(segments, tolerance) {
+        contour_fringe(&contour, color, width, buffers);
+    }
+}
+
+/// Append the fringe around a closed contour to `buffers`.
+fn contour_fringe(contour: &Contour, color: [f32; 4], width: f32, buffers: &mut VertexBuffers) {
+    let Contour { ref points, ref normals } = *contour;
+    let n = points.len();
+
+    let offset = buffers.vertices.len() as IndexType;
+    for i in 0..n {
+        let point = points[i];
+        let prev = normals[(i + n - 1) % n];
+        let next = normals[i];
+        let sum = prev + next;
+        let normal = if sum.square_length() > f32::EPSILON {
+            let normal = sum.normalize();
+            normal * (1.0 / normal.dot(prev).max(1.0 / MITER_LIMIT))
+        } else {
+            prev
+        };
+        let outer = point + normal * width;
+
+        buffers.vertices.push(Vertex { position: [point.x, point.y, 0.0], color, coverage: 1.0, uv: [0.0, 0.0] });
+        buffers.vertices.push(Vertex { position: [outer.x, outer.y, 0.0], color, coverage: 0.0, uv: [0.0, 0.0] });
+    }
+    for i in 0..n {
+        let j = (i + 1) % n;
+        let (inner_i, outer_i) = (offset + 2 * i as IndexType, offset + 2 * i as IndexType + 1);
+        let (inner_j, outer_j) = (offset + 2 * j as IndexType, offset + 2 * j as IndexType + 1);
+        buffers.indices.extend_from_slice(&[inner_i, outer_i, outer_j, inner_i, outer_j, inner_j]);
+    }
+}
+
+/// Append the fringes on both sides of a stroked path to `buffers`.
+///
+/// The stroke gets tessellated once per side, with the vertices on that side
+/// pushed out by the fringe width and the vertices on the other side pulled
+/// onto the edge of the stroke.
+pub(crate) fn stroke_fringe(
+    stroke_tess: &mut StrokeTessellator,
+    segments: &[PathEvent],
+    opts: &StrokeOptions,
+    color: Color,
+    width: f32,
+    buffers: &mut VertexBuffers,
+) {
+    for &side in [Side::Left, Side::Right].iter() {
+        let constructor = FringeConstructor { color, half_width: opts.line_width / 2.0, width, side };
+        if let Err(e) = stroke_tess.tessellate(
+            segments.iter().cloned(),
+            opts,
+            &mut BuffersBuilder::new(buffers, constructor),
+        ) {
+            error!("StrokeTessellator error: {:?}", e)
+        }
+    }
+}
+
+/// Builds the vertices of the fringe on one side of a stroke.
+struct FringeConstructor {
+    color: Color,
+    half_width: f32,
+    width: f32,
+    side: Side,
+}
+
+impl StrokeVertexConstructor<Vertex> for FringeConstructor {
+    fn new_vertex(&mut self, vertex: StrokeVertex) -> Vertex {
+        let (position, coverage) = if vertex.side() == self.side {
+            (vertex.position_on_path() + vertex.normal() * (self.half_width + self.width), 0.0)
+        } else {
+            (vertex.position_on_path() - vertex.normal() * self.half_width, 1.0)
+        };
+        Vertex {
+            position: [position.x, position.y, 0.0],
+            color: [self.color.r(), self.color.g(), self.color.b(), self.color.a()],
+            coverage,
+            uv: [0.0, 0.0],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lyon_tessellation::math::point;
+
+    use super::*;
+    use crate::test_util::{assert_near, polygon, rect};
+
+    fn outer_vertices(buffers: &VertexBuffers) -> Vec<[f32; 2]> {
+        buffers.vertices.iter()
+            .filter(|v| v.coverage == 0.0)
+            .map(|v| [v.position[0], v.position[1]])
+            .collect()
+    }
+
+    #[test]
+    fn square_fringe_is_outside() {
+        let mut buffers = VertexBuffers::new();
+        fill_fringe(&rect(0.0, 0.0, 10.0, 10.0), Color::WHITE, 1.0, 0.01, &mut buffers);
+
+        assert_eq!(buffers.vertices.len(), 8);
+        assert_eq!(buffers.indices.len(), 24);
+        for [x, y] in outer_vertices(&buffers) {
+            assert_near(x.abs().min((x - 10.0).abs()), 1.0, 1e-4);
+            assert_near(y.abs().min((y - 10.0).abs()), 1.0, 1e-4);
+            assert!(!(0.0..=10.0).contains(&x) && !(0.0..=10.0).contains(&y));
+        }
+    }
+
+    #[test]
+    fn winding_does_not_matter() {
+        let reversed = polygon(&[(0.0, 0.0), (0.0, 10.0), (10.0, 10.0), (10.0, 0.0)]);
+
+        let mut buffers = VertexBuffers::new();
+        fill_fringe(&reversed, Color::WHITE, 1.0, 0.01, &mut buffers);
+        for [x, y] in outer_vertices(&buffers) {
+            assert!(!(0.0..=10.0).contains(&x) && !(0.0..=10.0).contains(&y));
+        }
+    }
+
+    #[test]
+    fn hole_fringe_is_inside_the_hole() {
+        // Both contours wind the same way, the even-odd rule makes the inner
+        // one a hole.
+        let ring = [rect(0.0, 0.0, 30.0, 30.0), rect(10.0, 10.0, 10.0, 10.0)].concat();
+        let mut buffers = VertexBuffers::new();
+        fill_fringe(&ring, Color::WHITE, 1.0, 0.01, &mut buffers);
+
+        let outer = outer_vertices(&buffers);
+        assert_eq!(outer.len(), 8);
+        let in_hole = outer.iter().filter(|[x, y]| *x > 10.0 && *x < 20.0 && *y > 10.0 && *y < 20.0).count();
+        assert_eq!(in_hole, 4);
+    }
+
+    #[test]
+    fn stroke_fringe_on_both_sides() {
+        let line = vec![
+            PathEvent::Begin { at: point(0.0, 0.0) },
+            PathEvent::Line { from: point(0.0, 0.0), to: point(10.0, 0.0) },
+            PathEvent::End { last: point(10.0, 0.0), first: point(0.0, 0.0), close: false },
+        ];
+        let mut buffers = VertexBuffers::new();
+        let opts = StrokeOptions::default().with_line_width(2.0);
+        stroke_fringe(&mut StrokeTessellator::new(), &line, &opts, Color::WHITE, 0.5, &mut buffers);
+
+        assert!(!buffers.indices.is_empty());
+        for vertex in buffers.vertices.iter() {
+            let expected = if vertex.coverage == 0.0 { 1.5 } else { 1.0 };
+            assert_near(vertex.position[1].abs(), expected, 1e-4);
+        }
+    }
+}
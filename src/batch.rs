@@ -0,0 +1,303 @@
+//! Batching of many SVG entities into few draw calls.
+//!
+//! Bevy draws every mesh entity with its own draw call. Entities marked with
+//! [`SvgBatched`] are instead merged on the CPU into one mesh per z-layer,
+//! i.e. per distinct z translation, which keeps the order of transparent
+//! entities intact. Every batched entity keeps its own transform and an
+//! optional [`SvgTint`], while its own mesh is hidden.
+//!
+//! Entities drawn with the default or the anti-aliasing pipeline are batched
+//! separately per pipeline. Entities with a custom pipeline, e.g. for a
+//! [`SvgMaterial`](crate::material::SvgMaterial), keep drawing themselves.
+//!
+//! The batch of a layer is rebuilt whenever one of its entities moves, changes
+//! its tint or mesh, or stops being batched, so batching pays off most for
+//! many small SVGs like bullets or tiles.
+
+use std::collections::{HashMap, HashSet};
+
+use bevy::{
+    asset::{AssetEvent, Assets, Handle},
+    ecs::{
+        entity::Entity,
+        event::EventReader,
+        query::{Added, Changed, Or, With},
+        system::{Commands, Query, RemovedComponents, ResMut},
+    },
+    math::Vec3,
+    render::{
+        color::Color,
+        draw::{Draw, Visible},
+        mesh::{Indices, Mesh, VertexAttributeValues},
+        pipeline::{PipelineDescriptor, RenderPipeline, RenderPipelines},
+        render_graph::base::MainPass,
+    },
+    transform::components::{GlobalTransform, Transform},
+};
+
+use crate::{
+    Convert,
+    plugin::{SvgMaterial, SVG_ANTI_ALIAS_PIPELINE_HANDLE, SVG_PIPELINE_HANDLE},
+    svg::Svg,
+    vertex_buffer::{ATTRIBUTE_COVERAGE, IndexType, Vertex, VertexBuffers},
+};
+
+/// Precision with which z translations are grouped into layers.
+const LAYER_PRECISION: f32 = 1000.0;
+
+/// Marker component for SVG entities that are drawn as part of a batch.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SvgBatched;
+
+/// Color multiplied with the vertex colors of a [`SvgBatched`] entity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SvgTint(pub Color);
+
+impl Default for SvgTint {
+    fn default() -> Self {
+        SvgTint(Color::WHITE)
+    }
+}
+
+/// Marker component for the entities drawing a batch.
+pub(crate) struct SvgBatch;
+
+/// A z-layer and the pipeline its entities are drawn with.
+type BatchKey = (i64, Handle<PipelineDescriptor>);
+
+/// The batch entity and mesh of every z-layer and pipeline, and the batch of
+/// every batched entity.
+#[derive(Default)]
+pub(crate) struct SvgBatches {
+    layers: HashMap<BatchKey, (Entity, Handle<Mesh>)>,
+    members: HashMap<Entity, BatchKey>,
+}
+
+/// The z-layer of a translation.
+fn layer_of(z: f32) -> i64 {
+    (z * LAYER_PRECISION).round() as i64
+}
+
+/// The batch of an entity at `z`, `None` if its pipeline can't be batched.
+fn batch_of(z: f32, pipelines: Option<&RenderPipelines>) -> Option<BatchKey> {
+    let pipeline = match pipelines {
+        Some(pipelines) => match pipelines.pipelines[..] {
+            [ref pipeline] => pipeline.pipeline.clone_weak(),
+            _ => return None,
+        },
+        None => SVG_PIPELINE_HANDLE.typed(),
+    };
+    let batchable = [SVG_PIPELINE_HANDLE, SVG_ANTI_ALIAS_PIPELINE_HANDLE].iter().any(|handle| handle.id == pipeline.id);
+    if batchable { Some((layer_of(z), pipeline)) } else { None }
+}
+
+/// Bevy system which merges the meshes of all [`SvgBatched`] entities into
+/// one mesh per z-layer and pipeline. Only the batches that a changed entity
+/// left or joined are rebuilt.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn svg_batcher(
+    mut commands: Commands,
+    mut batches: ResMut<SvgBatches>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut mesh_events: EventReader<AssetEvent<Mesh>>,
+    removed: RemovedComponents<SvgBatched>,
+    changed: Query<Entity, (
+        With<SvgBatched>,
+        Or<(Added<SvgBatched>, Changed<GlobalTransform>, Changed<SvgTint>, Changed<Handle<Mesh>>)>,
+    )>,
+    members: Query<
+        (Entity, &Handle<Mesh>, &GlobalTransform, Option<&SvgTint>, Option<&RenderPipelines>),
+        With<SvgBatched>
+    >,
+    mut visibles: Query<&mut Visible, With<Svg>>,
+) {
+    let mut dirty: HashSet<BatchKey> = HashSet::new();
+    for entity in removed.iter() {
+        if let Some(batch) = batches.members.remove(&entity) {
+            dirty.insert(batch);
+        }
+        if let Ok(mut visible) = visibles.get_mut(entity) {
+            visible.is_visible = true;
+        }
+    }
+
+    let mut moved: Vec<Entity> = changed.iter().collect();
+    for event in mesh_events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            moved.extend(members.iter().filter(|(_, mesh, _, _, _)| *mesh == handle).map(|(entity, ..)| entity));
+        }
+    }
+    for entity in moved {
+        if let Ok((_, _, transform, _, pipelines)) = members.get(entity) {
+            match batch_of(transform.translation.z, pipelines) {
+                Some(batch) => {
+                    dirty.extend(batches.members.insert(entity, batch.clone()));
+                    dirty.insert(batch);
+                },
+                None => {
+                    dirty.extend(batches.members.remove(&entity));
+                    if let Ok(mut visible) = visibles.get_mut(entity) {
+                        visible.is_visible = true;
+                    }
+                },
+            }
+        }
+    }
+    if dirty.is_empty() {
+        return;
+    }
+
+    let mut layers: HashMap<BatchKey, (f32, VertexBuffers)> = HashMap::new();
+    for (entity, mesh, transform, tint, pipelines) in members.iter() {
+        let z = transform.translation.z;
+        let batch = match batch_of(z, pipelines) {
+            Some(batch) if dirty.contains(&batch) => batch,
+            _ => continue,
+        };
+        let mesh = match meshes.get(mesh) {
+            Some(mesh) => mesh,
+            None => continue,
+        };
+
+        let (_, buffers) = layers.entry(batch).or_insert_with(|| (z, VertexBuffers::new()));
+        let appended = append_transformed(buffers, mesh, transform, z, tint.map_or(Color::WHITE, |tint| tint.0));
+        // Entities whose mesh can't be batched keep drawing themselves.
+        if let Ok(mut visible) = visibles.get_mut(entity) {
+            visible.is_visible = !appended;
+        }
+    }
+
+    for batch in dirty.iter().filter(|batch| !layers.contains_key(batch)) {
+        if let Some((entity, _)) = batches.layers.remove(batch) {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    for (batch, (z, buffers)) in layers {
+        match batches.layers.get(&batch) {
+            Some((_, handle)) => if let Some(mesh) = meshes.get_mut(handle) {
+                *mesh = buffers.convert();
+            },
+            None => {
+                let handle = meshes.add(buffers.convert());
+                let transform = Transform::from_translation(Vec3::new(0.0, 0.0, z));
+                let entity = commands.spawn_bundle((
+                    SvgBatch,
+                    handle.clone(),
+                    Handle::<SvgMaterial>::default(),
+                    MainPass,
+                    Draw::default(),
+                    Visible { is_visible: true, is_transparent: true },
+                    RenderPipelines::from_pipelines(vec![RenderPipeline::new(batch.1.clone_weak())]),
+                    transform,
+                    GlobalTransform::from(transform),
+                )).id();
+                batches.layers.insert(batch, (entity, handle));
+            },
+        }
+    }
+}
+
+/// Append the vertices of `mesh`, moved by `transform` into the layer at `z`
+/// and tinted, to `buffers`. Returns `false` if the mesh lacks the positions,
+/// colors or indices of a SVG mesh and nothing was appended.
+fn append_transformed(buffers: &mut VertexBuffers, mesh: &Mesh, transform: &GlobalTransform, z: f32, tint: Color) -> bool {
+    let (positions, colors, indices) = match (
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION),
+        mesh.attribute(Mesh::ATTRIBUTE_COLOR),
+        mesh.indices(),
+    ) {
+        (
+            Some(VertexAttributeValues::Float3(positions)),
+            Some(VertexAttributeValues::Float4(colors)),
+            Some(Indices::U32(indices)),
+        ) => (positions, colors, indices),
+        _ => return false,
+    };
+    let coverages = match mesh.attribute(ATTRIBUTE_COVERAGE) {
+        Some(VertexAttributeValues::Float(coverages)) => Some(coverages),
+        _ => None,
+    };
+    let uvs = match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+        Some(VertexAttributeValues::Float2(uvs)) => Some(uvs),
+        _ => None,
+    };
+
+    let matrix = transform.compute_matrix();
+    let tint = [tint.r(), tint.g(), tint.b(), tint.a()];
+    let offset = buffers.vertices.len() as IndexType;
+    buffers.vertices.extend(positions.iter().zip(colors.iter()).enumerate().map(|(i, (position, color))| {
+        let position = matrix.transform_point3(Vec3::from(*position)) - Vec3::new(0.0, 0.0, z);
+        Vertex {
+            position: position.into(),
+            color: [color[0] * tint[0], color[1] * tint[1], color[2] * tint[2], color[3] * tint[3]],
+            coverage: coverages.and_then(|c| c.get(i)).copied().unwrap_or(1.0),
+            uv: uvs.and_then(|uvs| uvs.get(i)).copied().unwrap_or_default(),
+        }
+    }));
+    buffers.indices.extend(indices.iter().map(|i| i + offset));
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{asset::HandleUntyped, reflect::TypeUuid, render::pipeline::PrimitiveTopology};
+
+    use super::*;
+
+    fn triangle() -> Mesh {
+        let mut buffers = VertexBuffers::new();
+        for &(x, y) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)].iter() {
+            buffers.vertices.push(Vertex { position: [x, y, 0.0], color: [1.0, 0.5, 1.0, 1.0], coverage: 0.5, uv: [x, y] });
+        }
+        buffers.indices.extend([0, 1, 2].iter());
+        buffers.convert()
+    }
+
+    #[test]
+    fn layers_are_rounded() {
+        assert_eq!(layer_of(1.0), layer_of(1.0001));
+        assert_ne!(layer_of(1.0), layer_of(1.001));
+    }
+
+    #[test]
+    fn batches_are_kept_per_pipeline() {
+        let pipelines = |handle: HandleUntyped| RenderPipelines::from_pipelines(vec![RenderPipeline::new(handle.typed())]);
+        let (layer, pipeline) = batch_of(1.0, None).unwrap();
+        assert_eq!((layer, pipeline.id), (layer_of(1.0), SVG_PIPELINE_HANDLE.id));
+
+        let plain = batch_of(1.0, Some(&pipelines(SVG_PIPELINE_HANDLE))).unwrap();
+        let anti_aliased = batch_of(1.0, Some(&pipelines(SVG_ANTI_ALIAS_PIPELINE_HANDLE))).unwrap();
+        assert_ne!(plain, anti_aliased);
+        assert_eq!(anti_aliased.1.id, SVG_ANTI_ALIAS_PIPELINE_HANDLE.id);
+
+        let custom = HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 42);
+        assert_eq!(batch_of(1.0, Some(&pipelines(custom))), None);
+    }
+
+    #[test]
+    fn appends_transformed_and_tinted() {
+        let mut buffers = VertexBuffers::new();
+        let transform = GlobalTransform::from_translation(Vec3::new(10.0, 20.0, 3.0));
+        assert!(append_transformed(&mut buffers, &triangle(), &transform, 3.0, Color::rgba(0.5, 1.0, 1.0, 1.0)));
+        assert!(append_transformed(&mut buffers, &triangle(), &transform, 3.0, Color::WHITE));
+
+        assert_eq!(buffers.vertices.len(), 6);
+        assert_eq!(buffers.indices, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(buffers.vertices[1].position, [11.0, 20.0, 0.0]);
+        assert_eq!(buffers.vertices[0].color, [0.5, 0.5, 1.0, 1.0]);
+        assert_eq!(buffers.vertices[2].coverage, 0.5);
+        assert_eq!(buffers.vertices[2].uv, [0.0, 1.0]);
+    }
+
+    #[test]
+    fn foreign_meshes_are_not_appended() {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0.0_f32, 0.0, 0.0]; 3]);
+        mesh.set_indices(Some(Indices::U32(vec![0, 1, 2])));
+
+        let mut buffers = VertexBuffers::new();
+        assert!(!append_transformed(&mut buffers, &mesh, &GlobalTransform::identity(), 0.0, Color::WHITE));
+        assert!(buffers.vertices.is_empty());
+    }
+}
@@ -0,0 +1,173 @@
+//! Boolean operations on the areas enclosed by paths.
+//!
+//! Both operands are flattened into polygons, combined and turned back into
+//! segments of straight lines. The result gets tessellated like any other
+//! path once it is put into the [`Svg::paths`](crate::svg::Svg::paths) of a
+//! newly spawned [`SvgBundle`](crate::bundle::SvgBundle).
+
+use geo_booleanop::boolean::BooleanOp;
+use geo_types::{LineString, MultiPolygon, Polygon};
+use lyon_svg::path::{PathEvent, iterator::PathIterator};
+use lyon_tessellation::math::point;
+
+use crate::svg::{DrawType, PathDescriptor};
+
+/// A boolean operation between the areas of two paths.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathOp {
+    /// The area covered by either path.
+    Union,
+    /// The area of the first path not covered by the second one.
+    Difference,
+    /// The area covered by both paths.
+    Intersection,
+    /// The area covered by exactly one of the paths.
+    Xor,
+}
+
+impl PathDescriptor {
+    /// Combine the area of this path with the area of `other`. Curves are
+    /// flattened with the given tolerance and the areas follow the even-odd
+    /// rule, like the fill tessellation.
+    ///
+    /// The result is a [`DrawType::Fill`] path made of straight lines, with
+    /// the color, ids and attributes of this path. Strokes are combined by
+    /// the area their outline encloses, not by the area the stroke covers.
+    pub fn boolean(&self, other: &PathDescriptor, op: PathOp, tolerance: f32) -> PathDescriptor {
+        let segments = boolean(&self.segments, &other.segments, op, tolerance);
+        PathDescriptor {
+            segments,
+            draw_type: DrawType::Fill,
+            ..self.clone()
+        }
+    }
+
+    /// The area covered by either path, see [`PathDescriptor::boolean`].
+    pub fn union(&self, other: &PathDescriptor, tolerance: f32) -> PathDescriptor {
+        self.boolean(other, PathOp::Union, tolerance)
+    }
+
+    /// The area of this path not covered by `other`, see
+    /// [`PathDescriptor::boolean`].
+    pub fn difference(&self, other: &PathDescriptor, tolerance: f32) -> PathDescriptor {
+        self.boolean(other, PathOp::Difference, tolerance)
+    }
+
+    /// The area covered by both paths, see [`PathDescriptor::boolean`].
+    pub fn intersection(&self, other: &PathDescriptor, tolerance: f32) -> PathDescriptor {
+        self.boolean(other, PathOp::Intersection, tolerance)
+    }
+
+    /// The area covered by exactly one of the paths, see
+    /// [`PathDescriptor::boolean`].
+    pub fn xor(&self, other: &PathDescriptor, tolerance: f32) -> PathDescriptor {
+        self.boolean(other, PathOp::Xor, tolerance)
+    }
+}
+
+/// Combine the areas enclosed by two segment lists. Every sub-path is treated
+/// as closed.
+pub(crate) fn boolean(a: &[PathEvent], b: &[PathEvent], op: PathOp, tolerance: f32) -> Vec<PathEvent> {
+    let (a, b) = (to_polygons(a, tolerance), to_polygons(b, tolerance));
+    let result = match op {
+        PathOp::Union => a.union(&b),
+        PathOp::Difference => a.difference(&b),
+        PathOp::Intersection => a.intersection(&b),
+        PathOp::Xor => a.xor(&b),
+    };
+    to_segments(&result)
+}
+
+/// Flatten the sub-paths into one polygon each. Overlapping polygons cancel
+/// out, which gives the even-odd rule.
+pub(crate) fn to_polygons(segments: &[PathEvent], tolerance: f32) -> MultiPolygon<f64> {
+    let mut polygons = Vec::new();
+    let mut ring: Vec<(f64, f64)> = Vec::new();
+    for event in segments.iter().cloned().flattened(tolerance) {
+        match event {
+            PathEvent::Begin { at } => {
+                ring.clear();
+                ring.push((at.x as f64, at.y as f64));
+            },
+            PathEvent::Line { to, .. } => ring.push((to.x as f64, to.y as f64)),
+            PathEvent::End { .. } => {
+                if ring.len() >= 3 {
+                    polygons.push(Polygon::new(LineString::from(std::mem::take(&mut ring)), Vec::new()));
+                }
+            },
+            _ => {},
+        }
+    }
+    MultiPolygon(polygons)
+}
+
+/// Closed sub-paths for the rings of the polygons.
+pub(crate) fn to_segments(polygons: &MultiPolygon<f64>) -> Vec<PathEvent> {
+    let mut segments = Vec::new();
+    for polygon in polygons.0.iter() {
+        for ring in std::iter::once(polygon.exterior()).chain(polygon.interiors().iter()) {
+            let mut points = ring.0.iter().map(|c| point(c.x as f32, c.y as f32));
+            let first = match points.next() {
+                Some(first) => first,
+                None => continue,
+            };
+            segments.push(PathEvent::Begin { at: first });
+            let mut last = first;
+            // The rings repeat their first point at the end.
+            for to in points.take(ring.0.len().saturating_sub(2)) {
+                segments.push(PathEvent::Line { from: last, to });
+                last = to;
+            }
+            segments.push(PathEvent::End { last, first, close: true });
+        }
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_near, fill, fill_area, rect, stroke};
+
+    fn area(op: PathOp, a: &[PathEvent], b: &[PathEvent]) -> f32 {
+        fill_area(&boolean(a, b, op, 0.01))
+    }
+
+    #[test]
+    fn overlapping_squares() {
+        let (a, b) = (rect(0.0, 0.0, 10.0, 10.0), rect(5.0, 5.0, 10.0, 10.0));
+        assert_near(area(PathOp::Union, &a, &b), 175.0, 1e-3);
+        assert_near(area(PathOp::Difference, &a, &b), 75.0, 1e-3);
+        assert_near(area(PathOp::Intersection, &a, &b), 25.0, 1e-3);
+        assert_near(area(PathOp::Xor, &a, &b), 150.0, 1e-3);
+    }
+
+    #[test]
+    fn squares_sharing_an_edge() {
+        let (a, b) = (rect(0.0, 0.0, 10.0, 10.0), rect(10.0, 0.0, 10.0, 10.0));
+        assert_near(area(PathOp::Union, &a, &b), 200.0, 1e-3);
+        assert_near(area(PathOp::Difference, &a, &b), 100.0, 1e-3);
+        assert_near(area(PathOp::Intersection, &a, &b), 0.0, 1e-3);
+        assert_near(area(PathOp::Xor, &a, &b), 200.0, 1e-3);
+    }
+
+    #[test]
+    fn sub_paths_follow_the_even_odd_rule() {
+        let mut ring = rect(0.0, 0.0, 10.0, 10.0);
+        ring.extend(rect(2.0, 2.0, 6.0, 6.0));
+        let dot = rect(4.0, 4.0, 2.0, 2.0);
+        assert_near(fill_area(&boolean(&ring, &[], PathOp::Union, 0.01)), 64.0, 1e-3);
+        assert_near(area(PathOp::Union, &ring, &dot), 68.0, 1e-3);
+        assert_near(area(PathOp::Intersection, &ring, &dot), 0.0, 1e-3);
+    }
+
+    #[test]
+    fn result_is_a_fill_with_the_first_paths_metadata() {
+        let mut a = stroke(rect(0.0, 0.0, 10.0, 10.0), 2.0);
+        a.id = "a".to_string();
+        let result = a.intersection(&fill(rect(5.0, 0.0, 10.0, 10.0)), 0.01);
+        assert!(matches!(result.draw_type, DrawType::Fill));
+        assert_eq!(result.id, "a");
+        assert_near(fill_area(&result.segments), 50.0, 1e-3);
+    }
+}
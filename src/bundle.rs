@@ -1,14 +1,15 @@
 //! Bevy [`Bundle`] representing an SVG entity.
 
-use crate::{plugin::{SvgMaterial, SVG_PIPELINE_HANDLE}, svg::Svg};
+use crate::{geometry::SvgBounds, plugin::{SvgMaterial, SVG_PIPELINE_HANDLE}, svg::Svg, ui::SvgUiFit};
 use bevy::{
-    asset::Handle, ecs::bundle::Bundle, math::{Vec2, Vec3},
+    asset::Handle, ecs::bundle::Bundle, math::{Size, Vec2, Vec3},
     render::{
-        draw::{Draw, Visible}, mesh::Mesh, pipeline::{RenderPipeline, RenderPipelines},
+        draw::{Draw, Visible}, mesh::Mesh, pipeline::{PipelineDescriptor, RenderPipeline, RenderPipelines},
         render_graph::base::MainPass,
     },
     sprite::QUAD_HANDLE,
-    transform::components::{GlobalTransform, Transform}
+    transform::components::{GlobalTransform, Transform},
+    ui::{Node, Style, Val},
 };
 
 
@@ -17,6 +18,7 @@ use bevy::{
 #[derive(Bundle)]
 pub struct SvgBundle {
     pub svg: Svg ,
+    pub bounds: SvgBounds,
     pub mesh: Handle<Mesh>,
     pub material: Handle<SvgMaterial>,
     pub main_pass: MainPass,
@@ -31,6 +33,7 @@ impl SvgBundle {
     /// Create a new [`SvgBundle`] from a [`Svg`].
     pub fn new(svg: Svg) -> SvgBundle {
         Self {
+            bounds: svg.bounds(),
             svg,
             mesh: QUAD_HANDLE.typed(),
             render_pipelines: RenderPipelines::from_pipelines(vec![RenderPipeline::new(
@@ -67,4 +70,147 @@ impl SvgBundle {
         self.transform.scale = Vec3::new(scale.x, -scale.y, 1.0);
         self
     }
+
+    /// Draw the SVG with a custom pipeline instead of the default shaders.
+    /// Insert the handle of its material next to the bundle, see the
+    /// [`material`](crate::material) module.
+    pub fn with_pipeline(mut self, pipeline: Handle<PipelineDescriptor>) -> SvgBundle {
+        self.render_pipelines = RenderPipelines::from_pipelines(vec![RenderPipeline::new(pipeline)]);
+        self
+    }
+}
+
+/// A Bevy [`Bundle`] representing an SVG entity whose [`Svg`] is loaded by the
+/// [`AssetServer`](bevy::asset::AssetServer). The [`Svg`] and [`SvgBounds`]
+/// components get inserted once the asset is loaded.
+///
+/// ```ignore
+/// let svg = asset_server.load("icons/heart.svgz");
+/// commands.spawn_bundle(SvgAssetBundle::new(svg).at_position(Vec3::ZERO));
+/// ```
+#[allow(missing_docs)]
+#[derive(Bundle)]
+pub struct SvgAssetBundle {
+    pub svg: Handle<Svg>,
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<SvgMaterial>,
+    pub main_pass: MainPass,
+    pub draw: Draw,
+    pub visible: Visible,
+    pub render_pipelines: RenderPipelines,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+}
+
+impl SvgAssetBundle {
+    /// Create a new [`SvgAssetBundle`] from a handle to a [`Svg`] asset.
+    pub fn new(svg: Handle<Svg>) -> SvgAssetBundle {
+        Self {
+            svg,
+            mesh: QUAD_HANDLE.typed(),
+            render_pipelines: RenderPipelines::from_pipelines(vec![RenderPipeline::new(
+                SVG_PIPELINE_HANDLE.typed(),
+            )]),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            main_pass: MainPass,
+            draw: Default::default(),
+            material: Default::default(),
+            transform: Transform::from_scale(Vec3::new(1.0, -1.0, 1.0)),
+            global_transform: Default::default(),
+        }
+    }
+
+    /// Specifies the 3D position at which the [`SvgAssetBundle`] will be spawned.
+    pub fn at_position(mut self, translation: Vec3) -> SvgAssetBundle {
+        self.transform.translation = translation;
+        self
+    }
+
+    /// Specifies a Transform.
+    pub fn with_transform(mut self, transform: Transform) -> SvgAssetBundle {
+        self.transform = transform;
+        self
+    }
+
+    /// Scale the SVG.
+    pub fn with_scale(mut self, scale: Vec2) -> SvgAssetBundle {
+        self.transform.scale = Vec3::new(scale.x, -scale.y, 1.0);
+        self
+    }
+
+    /// Draw the SVG with a custom pipeline instead of the default shaders.
+    /// Insert the handle of its material next to the bundle, see the
+    /// [`material`](crate::material) module.
+    pub fn with_pipeline(mut self, pipeline: Handle<PipelineDescriptor>) -> SvgAssetBundle {
+        self.render_pipelines = RenderPipelines::from_pipelines(vec![RenderPipeline::new(pipeline)]);
+        self
+    }
+}
+
+/// A Bevy [`Bundle`] representing an SVG inside a Bevy UI node.
+///
+/// The node is laid out like any other UI node and the SVG is scaled to the
+/// node's size according to its [`SvgUiFit`]. By default the node has the
+/// size of the SVG, change it with [`SvgUiBundle::with_style`].
+///
+/// The scale of the SVG is set on the node's [`Transform`], which also scales
+/// and flips any child nodes. Don't add children to it.
+#[allow(missing_docs)]
+#[derive(Bundle)]
+pub struct SvgUiBundle {
+    pub svg: Svg,
+    pub bounds: SvgBounds,
+    pub fit: SvgUiFit,
+    pub node: Node,
+    pub style: Style,
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<SvgMaterial>,
+    pub draw: Draw,
+    pub visible: Visible,
+    pub render_pipelines: RenderPipelines,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+}
+
+impl SvgUiBundle {
+    /// Create a new [`SvgUiBundle`] from a [`Svg`].
+    pub fn new(svg: Svg) -> SvgUiBundle {
+        Self {
+            bounds: svg.bounds(),
+            style: Style {
+                size: Size::new(Val::Px(svg.width as f32), Val::Px(svg.height as f32)),
+                ..Default::default()
+            },
+            svg,
+            fit: SvgUiFit::default(),
+            node: Default::default(),
+            mesh: QUAD_HANDLE.typed(),
+            render_pipelines: RenderPipelines::from_pipelines(vec![RenderPipeline::new(
+                SVG_PIPELINE_HANDLE.typed(),
+            )]),
+            visible: Visible {
+                is_visible: false,
+                is_transparent: true,
+            },
+            draw: Default::default(),
+            material: Default::default(),
+            transform: Default::default(),
+            global_transform: Default::default(),
+        }
+    }
+
+    /// Specifies the [`Style`] of the UI node.
+    pub fn with_style(mut self, style: Style) -> SvgUiBundle {
+        self.style = style;
+        self
+    }
+
+    /// Specifies how the SVG is scaled to the size of the UI node.
+    pub fn with_fit(mut self, fit: SvgUiFit) -> SvgUiBundle {
+        self.fit = fit;
+        self
+    }
 }
@@ -0,0 +1,477 @@
+//! Generate collider geometry from the paths of a [`Svg`].
+//!
+//! The shapes are engine agnostic: a polyline, a convex polygon or a triangle
+//! mesh, in SVG units with the y-axis pointing up, like in Bevy. Physics
+//! engines ignore the scale of a [`Transform`], so the spawned [`SvgCollider`]s
+//! already have the scale of the SVG entity applied. Convert them into the
+//! components of your physics crate in a system of your own.
+//!
+//! Curves are flattened with the same tolerance that is used for rendering, so
+//! the colliders match what is drawn on screen.
+
+use std::collections::HashMap;
+
+use bevy::{
+    ecs::{entity::Entity, query::Added, system::{Commands, Query}},
+    log::error,
+    math::Vec2,
+    transform::{components::{GlobalTransform, Transform}, hierarchy::BuildChildren},
+};
+use lyon_svg::path::{PathEvent, iterator::PathIterator};
+use lyon_tessellation::{
+    BuffersBuilder, FillOptions, FillTessellator, FillVertex, StrokeTessellator, StrokeVertex,
+    VertexBuffers, math::Point,
+};
+
+use crate::{plugin::FILL_TOLERANCE, svg::{DrawType, PathDescriptor, Svg}};
+
+/// The kind of collider shapes to generate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColliderKind {
+    /// One polyline per sub-path, useful for terrain outlines.
+    Polyline,
+    /// The filled area split into convex polygons.
+    ConvexDecomposition,
+    /// The filled area as a triangle mesh.
+    TriMesh,
+}
+
+/// Where the generated collider components are inserted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColliderTarget {
+    /// A single [`SvgCollider`] with the shapes of all paths on the SVG entity.
+    Entity,
+    /// One child entity with a [`SvgCollider`] per path of the SVG.
+    PerPath,
+}
+
+/// Settings for generating colliders, see [`SvgBuilder::colliders`](crate::svg::SvgBuilder::colliders).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColliderOptions {
+    /// The kind of collider shapes to generate.
+    pub kind: ColliderKind,
+    /// Where the generated collider components are inserted.
+    pub target: ColliderTarget,
+}
+
+/// A single collider shape, in SVG units with the y-axis pointing up.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ColliderShape {
+    /// A polyline, `closed` if the last point connects back to the first one.
+    Polyline {
+        /// Points of the polyline.
+        points: Vec<Vec2>,
+        /// Whether the polyline is closed.
+        closed: bool,
+    },
+    /// A convex polygon, points are in counter-clockwise order.
+    ConvexPolygon {
+        /// Points of the polygon.
+        points: Vec<Vec2>,
+    },
+    /// A triangle mesh.
+    TriMesh {
+        /// Vertices of the mesh.
+        vertices: Vec<Vec2>,
+        /// Indices of the triangles, in counter-clockwise order.
+        indices: Vec<[u32; 3]>,
+    },
+}
+
+/// Component holding the collider shapes generated from a [`Svg`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SvgCollider {
+    /// The generated shapes.
+    pub shapes: Vec<ColliderShape>,
+}
+
+impl Svg {
+    /// Generate collider shapes of the given kind for all drawn paths of this
+    /// SVG, including the ones of filtered groups and pattern-painted ones,
+    /// without any scale.
+    pub fn collider(&self, kind: ColliderKind) -> SvgCollider {
+        SvgCollider {
+            shapes: self.drawn_paths().into_iter().flat_map(|path| path.collider_shapes(kind)).collect(),
+        }
+    }
+}
+
+impl PathDescriptor {
+    /// Generate collider shapes of the given kind for this path, in SVG units
+    /// with the y-axis flipped to point up. Stroked
+    /// paths produce the area covered by the stroke for
+    /// [`ColliderKind::TriMesh`] and [`ColliderKind::ConvexDecomposition`].
+    pub fn collider_shapes(&self, kind: ColliderKind) -> Vec<ColliderShape> {
+        match kind {
+            ColliderKind::Polyline => self.polylines(),
+            ColliderKind::TriMesh => {
+                let (vertices, indices) = self.triangles();
+                if indices.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![ColliderShape::TriMesh { vertices, indices }]
+                }
+            },
+            ColliderKind::ConvexDecomposition => {
+                let (vertices, indices) = self.triangles();
+                convex_decomposition(&vertices, &indices)
+            },
+        }
+    }
+
+    fn tolerance(&self) -> f32 {
+        match self.draw_type {
+            DrawType::Fill => FILL_TOLERANCE,
+            DrawType::Stroke(ref opts) => opts.tolerance,
+        }
+    }
+
+    fn polylines(&self) -> Vec<ColliderShape> {
+        let mut shapes = Vec::new();
+        let mut points = Vec::new();
+        for event in self.segments.iter().cloned().flattened(self.tolerance()) {
+            match event {
+                PathEvent::Begin { at } => {
+                    points.clear();
+                    points.push(to_vec2(at));
+                },
+                PathEvent::Line { to, .. } => points.push(to_vec2(to)),
+                PathEvent::End { close, .. } => {
+                    if points.len() > 1 {
+                        shapes.push(ColliderShape::Polyline {
+                            points: std::mem::take(&mut points),
+                            closed: close,
+                        });
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        shapes
+    }
+
+    /// Tessellate the path into triangles, counter-clockwise with the y-axis
+    /// pointing up.
+    fn triangles(&self) -> (Vec<Vec2>, Vec<[u32; 3]>) {
+        let mut buffers: VertexBuffers<Point, u32> = VertexBuffers::new();
+        let result = match self.draw_type {
+            DrawType::Fill => FillTessellator::new().tessellate(
+                self.segments.iter().cloned(),
+                &FillOptions::tolerance(FILL_TOLERANCE),
+                &mut BuffersBuilder::new(&mut buffers, |v: FillVertex| v.position()),
+            ),
+            DrawType::Stroke(ref opts) => StrokeTessellator::new().tessellate(
+                self.segments.iter().cloned(),
+                opts,
+                &mut BuffersBuilder::new(&mut buffers, |v: StrokeVertex| v.position()),
+            ),
+        };
+        if let Err(e) = result {
+            error!("Tessellator error while generating collider: {:?}", e);
+            return (Vec::new(), Vec::new());
+        }
+
+        let vertices: Vec<Vec2> = buffers.vertices.into_iter().map(to_vec2).collect();
+        let indices = buffers.indices.chunks_exact(3)
+            .map(|t| {
+                let (a, b, c) = (t[0], t[1], t[2]);
+                if cross(vertices[a as usize], vertices[b as usize], vertices[c as usize]) < 0.0 {
+                    [a, c, b]
+                } else {
+                    [a, b, c]
+                }
+            })
+            .collect();
+
+        (vertices, indices)
+    }
+}
+
+/// Bevy system which generates the [`SvgCollider`]s of newly spawned SVGs that
+/// requested them.
+pub(crate) fn svg_collider_maker(
+    mut commands: Commands,
+    query: Query<(Entity, &Svg, &Transform), Added<Svg>>,
+) {
+    for (entity, svg, transform) in query.iter() {
+        let options = match svg.colliders {
+            Some(options) => options,
+            None => continue,
+        };
+        // The mesh is flipped by the negative y scale of the entity, the
+        // shapes are already flipped.
+        let scale = Vec2::new(transform.scale.x, -transform.scale.y);
+
+        match options.target {
+            ColliderTarget::Entity => {
+                commands.entity(entity).insert(svg.collider(options.kind).scaled(scale));
+            },
+            ColliderTarget::PerPath => {
+                commands.entity(entity).with_children(|parent| {
+                    for path in svg.drawn_paths() {
+                        let collider = SvgCollider { shapes: path.collider_shapes(options.kind) }.scaled(scale);
+                        parent.spawn_bundle((collider, Transform::default(), GlobalTransform::default()));
+                    }
+                });
+            },
+        }
+    }
+}
+
+impl SvgCollider {
+    /// Scale all shapes, keeping triangles and convex polygons
+    /// counter-clockwise.
+    pub fn scaled(self, scale: Vec2) -> SvgCollider {
+        if scale == Vec2::new(1.0, 1.0) {
+            return self;
+        }
+        SvgCollider { shapes: self.shapes.into_iter().map(|shape| shape.scaled(scale)).collect() }
+    }
+
+}
+
+impl ColliderShape {
+    /// Scale the shape, keeping triangles and convex polygons
+    /// counter-clockwise.
+    pub fn scaled(self, scale: Vec2) -> ColliderShape {
+        let mirrored = scale.x * scale.y < 0.0;
+        match self {
+            ColliderShape::Polyline { points, closed } => ColliderShape::Polyline {
+                points: points.into_iter().map(|p| p * scale).collect(),
+                closed,
+            },
+            ColliderShape::ConvexPolygon { points } => {
+                let mut points: Vec<Vec2> = points.into_iter().map(|p| p * scale).collect();
+                if mirrored {
+                    points.reverse();
+                }
+                ColliderShape::ConvexPolygon { points }
+            },
+            ColliderShape::TriMesh { vertices, indices } => ColliderShape::TriMesh {
+                vertices: vertices.into_iter().map(|p| p * scale).collect(),
+                indices: if mirrored { indices.into_iter().map(|[a, b, c]| [a, c, b]).collect() } else { indices },
+            },
+        }
+    }
+
+}
+
+/// Merge triangles into convex polygons with the Hertel-Mehlhorn algorithm:
+/// two polygons sharing an edge are merged as long as the result stays convex.
+fn convex_decomposition(vertices: &[Vec2], indices: &[[u32; 3]]) -> Vec<ColliderShape> {
+    let mut polygons: Vec<Option<Vec<u32>>> = indices.iter().map(|t| Some(t.to_vec())).collect();
+
+    // The polygon on the left of each directed edge.
+    let mut owners: HashMap<(u32, u32), usize> = HashMap::new();
+    for (i, triangle) in indices.iter().enumerate() {
+        for k in 0..3 {
+            owners.insert((triangle[k], triangle[(k + 1) % 3]), i);
+        }
+    }
+    // Every inner edge is visited once, polygons grow as edges get removed.
+    let mut worklist: Vec<(u32, u32)> = owners.keys()
+        .filter(|&&(from, to)| from < to && owners.contains_key(&(to, from)))
+        .copied()
+        .collect();
+    worklist.sort_unstable();
+
+    for (from, to) in worklist {
+        let (i, j) = match (owners.get(&(from, to)), owners.get(&(to, from))) {
+            (Some(&i), Some(&j)) if i != j => (i, j),
+            _ => continue,
+        };
+        let (a, b) = match (&polygons[i], &polygons[j]) {
+            (Some(a), Some(b)) => (a, b),
+            _ => continue,
+        };
+        if let Some(polygon) = merge_convex(vertices, a, b) {
+            owners.remove(&(from, to));
+            owners.remove(&(to, from));
+            let n = polygon.len();
+            for k in 0..n {
+                let edge = (polygon[k], polygon[(k + 1) % n]);
+                if let Some(owner) = owners.get_mut(&edge) {
+                    *owner = i;
+                }
+            }
+            polygons[i] = Some(polygon);
+            polygons[j] = None;
+        }
+    }
+
+    polygons.into_iter()
+        .flatten()
+        .map(|polygon| ColliderShape::ConvexPolygon {
+            points: polygon.into_iter().map(|i| vertices[i as usize]).collect(),
+        })
+        .collect()
+}
+
+/// Merge two counter-clockwise polygons along a shared edge, if they have one
+/// and the merged polygon is convex.
+fn merge_convex(vertices: &[Vec2], a: &[u32], b: &[u32]) -> Option<Vec<u32>> {
+    // The shared edge runs `a[i] -> a[i + 1]` in `a` and in the opposite
+    // direction in `b`.
+    let (i, j) = (0..a.len()).find_map(|i| {
+        let (from, to) = (a[i], a[(i + 1) % a.len()]);
+        (0..b.len())
+            .find(|&j| b[j] == to && b[(j + 1) % b.len()] == from)
+            .map(|j| (i, j))
+    })?;
+
+    let mut polygon = Vec::with_capacity(a.len() + b.len() - 2);
+    for k in 0..a.len() {
+        polygon.push(a[(i + 1 + k) % a.len()]);
+    }
+    for k in 2..b.len() {
+        polygon.push(b[(j + k) % b.len()]);
+    }
+
+    let n = polygon.len();
+    let convex = (0..n).all(|k| {
+        let p0 = vertices[polygon[k] as usize];
+        let p1 = vertices[polygon[(k + 1) % n] as usize];
+        let p2 = vertices[polygon[(k + 2) % n] as usize];
+        cross(p0, p1, p2) >= 0.0
+    });
+
+    if convex { Some(polygon) } else { None }
+}
+
+fn cross(a: Vec2, b: Vec2, c: Vec2) -> f32 {
+    (b - a).perp_dot(c - a)
+}
+
+/// Convert a point of the SVG to a point with the y-axis pointing up.
+fn to_vec2(p: Point) -> Vec2 {
+    Vec2::new(p.x, -p.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_near, fill, load, polygon, rect};
+
+    fn area(points: &[Vec2]) -> f32 {
+        let n = points.len();
+        (0..n).map(|i| points[i].perp_dot(points[(i + 1) % n])).sum::<f32>() / 2.0
+    }
+
+    fn shape_area(shapes: &[ColliderShape]) -> f32 {
+        shapes.iter()
+            .map(|shape| match shape {
+                ColliderShape::ConvexPolygon { points } => area(points),
+                ColliderShape::TriMesh { vertices, indices } => indices.iter()
+                    .map(|t| area(&[vertices[t[0] as usize], vertices[t[1] as usize], vertices[t[2] as usize]]))
+                    .sum(),
+                ColliderShape::Polyline { .. } => 0.0,
+            })
+            .sum()
+    }
+
+    fn is_convex(points: &[Vec2]) -> bool {
+        let n = points.len();
+        (0..n).all(|k| cross(points[k], points[(k + 1) % n], points[(k + 2) % n]) >= -1e-4)
+    }
+
+    #[test]
+    fn filtered_groups_get_colliders() {
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <filter id="shift"><feOffset dx="2" dy="0"/></filter>
+            <rect width="10" height="10"/>
+            <g filter="url(#shift)"><rect x="50" width="20" height="5"/></g>
+        </svg>"#);
+        let collider = svg.collider(ColliderKind::TriMesh);
+        assert_eq!(collider.shapes.len(), 2);
+        assert_near(shape_area(&collider.shapes), 200.0, 1e-3);
+    }
+
+    #[test]
+    fn pattern_painted_paths_get_colliders() {
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <pattern id="dots" width="10" height="10" patternUnits="userSpaceOnUse"><circle cx="5" cy="5" r="2"/></pattern>
+            <rect width="10" height="10"/>
+            <rect x="50" width="20" height="5" fill="url(#dots)"/>
+        </svg>"#);
+        let collider = svg.collider(ColliderKind::TriMesh);
+        assert_eq!(collider.shapes.len(), 2);
+        assert_near(shape_area(&collider.shapes), 200.0, 1e-3);
+    }
+
+    #[test]
+    fn polylines_are_flipped_to_y_up() {
+        let shapes = fill(rect(0.0, 10.0, 5.0, 5.0)).collider_shapes(ColliderKind::Polyline);
+        match shapes.as_slice() {
+            [ColliderShape::Polyline { points, closed }] => {
+                assert!(closed);
+                assert_eq!(points[0], Vec2::new(0.0, -10.0));
+                assert_eq!(points[2], Vec2::new(5.0, -15.0));
+            },
+            other => panic!("unexpected shapes {:?}", other),
+        }
+    }
+
+    #[test]
+    fn trimesh_is_counter_clockwise() {
+        let shapes = fill(rect(0.0, 0.0, 4.0, 3.0)).collider_shapes(ColliderKind::TriMesh);
+        match shapes.as_slice() {
+            [ColliderShape::TriMesh { vertices, indices }] => {
+                for t in indices {
+                    assert!(cross(vertices[t[0] as usize], vertices[t[1] as usize], vertices[t[2] as usize]) > 0.0);
+                }
+            },
+            other => panic!("unexpected shapes {:?}", other),
+        }
+        assert_near(shape_area(&shapes), 12.0, 1e-3);
+    }
+
+    #[test]
+    fn convex_decomposition_of_a_square_is_one_polygon() {
+        let shapes = fill(rect(0.0, 0.0, 4.0, 4.0)).collider_shapes(ColliderKind::ConvexDecomposition);
+        assert_eq!(shapes.len(), 1);
+        assert_near(shape_area(&shapes), 16.0, 1e-3);
+    }
+
+    #[test]
+    fn convex_decomposition_of_concave_shapes() {
+        // An L shape and a comb with many notches.
+        let l = polygon(&[(0.0, 0.0), (4.0, 0.0), (4.0, 1.0), (1.0, 1.0), (1.0, 4.0), (0.0, 4.0)]);
+        let shapes = fill(l).collider_shapes(ColliderKind::ConvexDecomposition);
+        assert!(shapes.len() >= 2);
+        assert_near(shape_area(&shapes), 7.0, 1e-3);
+
+        let mut points = vec![(0.0, 0.0)];
+        for i in 0..50 {
+            let x = i as f32 * 2.0;
+            points.extend_from_slice(&[(x, 5.0), (x + 1.0, 5.0), (x + 1.0, 1.0), (x + 2.0, 1.0)]);
+        }
+        points.push((100.0, 0.0));
+        let shapes = fill(polygon(&points)).collider_shapes(ColliderKind::ConvexDecomposition);
+        for shape in shapes.iter() {
+            match shape {
+                ColliderShape::ConvexPolygon { points } => {
+                    assert!(is_convex(points));
+                    assert!(area(points) > 0.0);
+                },
+                other => panic!("unexpected shape {:?}", other),
+            }
+        }
+        assert_near(shape_area(&shapes), 100.0 + 50.0 * 4.0, 1e-2);
+    }
+
+    #[test]
+    fn scaling_keeps_counter_clockwise_order() {
+        let collider = SvgCollider {
+            shapes: fill(rect(0.0, 0.0, 4.0, 4.0)).collider_shapes(ColliderKind::ConvexDecomposition),
+        };
+        let mirrored = collider.scaled(Vec2::new(-2.0, 1.0));
+        match mirrored.shapes.as_slice() {
+            [ColliderShape::ConvexPolygon { points }] => {
+                assert!(area(points) > 0.0);
+                assert_near(area(points), 32.0, 1e-3);
+            },
+            other => panic!("unexpected shapes {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,323 @@
+//! Extrusion of the filled paths of a SVG into a closed 3D mesh.
+//!
+//! The outlines of every filled path are flattened into polygons. The polygons
+//! get a front cap facing `+z` and a back cap facing `-z`, tessellated with
+//! lyon, and a side wall connecting them. An optional bevel insets the caps
+//! and connects them to the walls with a chamfer. Strokes are ignored, filled
+//! paths of filtered groups and pattern fills are extruded without the effect.
+//!
+//! Overlapping paths would have their caps in the same plane, so each path is
+//! made slightly thicker than the ones before it in document order.
+//!
+//! The mesh has positions, normals and UVs, so it can be drawn with a
+//! `StandardMaterial`. The UVs of the caps span the bounds of the SVG, the UVs
+//! of the walls run around each outline.
+
+use bevy::{
+    log::error,
+    math::{Vec2, Vec3},
+    render::{mesh::{Indices, Mesh}, pipeline::PrimitiveTopology},
+};
+use lyon_svg::path::PathEvent;
+use lyon_tessellation::{
+    BuffersBuilder, FillOptions, FillTessellator, FillVertex,
+    math::{Point, Vector, point},
+    path::Path,
+};
+
+use crate::{geometry::{Contour, contours}, svg::{DrawType, Origin, Svg}};
+
+/// Maximum length of the bevel at sharp corners, relative to its width.
+const MITER_LIMIT: f32 = 4.0;
+
+/// How much thicker the last path is than the first one, relative to the
+/// depth.
+const CAP_SPREAD: f32 = 0.01;
+
+/// Settings of [`SvgBuilder::extrude_with`](crate::svg::SvgBuilder::extrude_with).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SvgExtrusion {
+    /// Thickness of the mesh along the z-axis, in SVG units.
+    pub depth: f32,
+    /// Width of the chamfer between the caps and the side walls, in SVG units.
+    /// `0.0` disables the bevel. Large bevels distort thin shapes.
+    pub bevel: f32,
+    /// Maximum distance between a curve and its flattened outline.
+    pub tolerance: f32,
+}
+
+impl Default for SvgExtrusion {
+    fn default() -> Self {
+        SvgExtrusion {
+            depth: 1.0,
+            bevel: 0.0,
+            tolerance: 0.01,
+        }
+    }
+}
+
+#[derive(Default)]
+struct MeshData {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    indices: Vec<u32>,
+}
+
+impl MeshData {
+    fn vertex(&mut self, position: Vec3, normal: Vec3, uv: [f32; 2]) -> u32 {
+        self.positions.push(position.into());
+        self.normals.push(normal.into());
+        self.uvs.push(uv);
+        (self.positions.len() - 1) as u32
+    }
+
+    /// Add a triangle, wound counter-clockwise when seen from the side its
+    /// vertex normals point to.
+    fn triangle(&mut self, a: u32, b: u32, c: u32) {
+        let position = |i: u32| Vec3::from(self.positions[i as usize]);
+        let normal = Vec3::from(self.normals[a as usize]);
+        let face = (position(b) - position(a)).cross(position(c) - position(a));
+        if face.dot(normal) >= 0.0 {
+            self.indices.extend_from_slice(&[a, b, c]);
+        } else {
+            self.indices.extend_from_slice(&[a, c, b]);
+        }
+    }
+
+    fn quad(&mut self, corners: [Vec3; 4], normal: Vec3, uvs: [[f32; 2]; 4]) {
+        let a = self.vertex(corners[0], normal, uvs[0]);
+        let b = self.vertex(corners[1], normal, uvs[1]);
+        let c = self.vertex(corners[2], normal, uvs[2]);
+        let d = self.vertex(corners[3], normal, uvs[3]);
+        self.triangle(a, b, c);
+        self.triangle(a, c, d);
+    }
+}
+
+/// Extrude the filled paths of `svg`, scaled by `scale`, into a mesh. The
+/// origin of the SVG becomes the origin of the mesh and the y-axis points up.
+pub(crate) fn extrude(svg: &Svg, extrusion: &SvgExtrusion, scale: Vec2) -> Mesh {
+    let (x, y) = (svg.view_box.x as f32, svg.view_box.y as f32);
+    let (width, height) = (svg.view_box.w as f32, svg.view_box.h as f32);
+    let origin = match svg.origin {
+        Origin::TopLeft => point(x, y),
+        Origin::Center => point(x + width / 2.0, y + height / 2.0),
+    };
+    let to_3d = |p: Point, z: f32| Vec3::new((p.x - origin.x) * scale.x, -(p.y - origin.y) * scale.y, z);
+    // Normals transform with the inverse of the scale.
+    let normal_3d = |n: Vector, z: f32| Vec3::new(n.x / scale.x, -n.y / scale.y, z).normalize();
+    let cap_uv = |p: Point| [
+        if width > 0.0 { (p.x - x) / width } else { 0.0 },
+        if height > 0.0 { (p.y - y) / height } else { 0.0 },
+    ];
+
+    let paths: Vec<_> = svg.drawn_paths().into_iter()
+        .filter(|path| matches!(path.draw_type, DrawType::Fill))
+        .collect();
+    let step = match paths.len() {
+        0 | 1 => 0.0,
+        n => extrusion.depth.abs() * CAP_SPREAD / 2.0 / (n - 1) as f32,
+    };
+    let bevel = extrusion.bevel.max(0.0).min(extrusion.depth.abs() / 2.0);
+
+    let mut data = MeshData::default();
+    let mut fill_tess = FillTessellator::new();
+
+    for (index, path) in paths.into_iter().enumerate() {
+        let half_depth = extrusion.depth.abs() / 2.0 + step * index as f32;
+        let wall_depth = half_depth - bevel;
+
+        let contours = contours(&path.segments, extrusion.tolerance);
+        if contours.is_empty() {
+            continue;
+        }
+
+        let insets: Vec<Vec<Point>> = contours.iter()
+            .map(|contour| if bevel > 0.0 { inset(contour, bevel) } else { contour.points.clone() })
+            .collect();
+
+        // Front and back caps.
+        let cap = tessellate(&mut fill_tess, &insets, extrusion.tolerance);
+        for &(z, normal) in [(half_depth, Vec3::new(0.0, 0.0, 1.0)), (-half_depth, Vec3::new(0.0, 0.0, -1.0))].iter() {
+            let offset = data.positions.len() as u32;
+            for p in cap.vertices.iter() {
+                data.vertex(to_3d(*p, z), normal, cap_uv(*p));
+            }
+            for triangle in cap.indices.chunks_exact(3) {
+                data.triangle(offset + triangle[0], offset + triangle[1], offset + triangle[2]);
+            }
+        }
+
+        for (contour, inset) in contours.iter().zip(insets.iter()) {
+            let n = contour.points.len();
+            let length: f32 = (0..n).map(|i| (contour.points[(i + 1) % n] - contour.points[i]).length()).sum();
+            let mut distance = 0.0;
+
+            for i in 0..n {
+                let j = (i + 1) % n;
+                let (a, b) = (contour.points[i], contour.points[j]);
+                let u0 = distance / length;
+                distance += (b - a).length();
+                let u1 = distance / length;
+
+                if wall_depth > 0.0 {
+                    data.quad(
+                        [to_3d(a, wall_depth), to_3d(b, wall_depth), to_3d(b, -wall_depth), to_3d(a, -wall_depth)],
+                        normal_3d(contour.normals[i], 0.0),
+                        [[u0, 0.0], [u1, 0.0], [u1, 1.0], [u0, 1.0]],
+                    );
+                }
+                if bevel > 0.0 {
+                    for &side in [1.0_f32, -1.0].iter() {
+                        data.quad(
+                            [
+                                to_3d(a, side * wall_depth), to_3d(b, side * wall_depth),
+                                to_3d(inset[j], side * half_depth), to_3d(inset[i], side * half_depth),
+                            ],
+                            (normal_3d(contour.normals[i], 0.0) + Vec3::new(0.0, 0.0, side)).normalize(),
+                            [[u0, 0.0], [u1, 0.0], [u1, 1.0], [u0, 1.0]],
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_indices(Some(Indices::U32(data.indices)));
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, data.positions);
+    mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, data.normals);
+    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, data.uvs);
+    mesh
+}
+
+/// Move the points of a contour inwards by `distance`.
+fn inset(contour: &Contour, distance: f32) -> Vec<Point> {
+    let n = contour.points.len();
+    (0..n)
+        .map(|i| {
+            let prev = contour.normals[(i + n - 1) % n];
+            let next = contour.normals[i];
+            let sum = prev + next;
+            let normal = if sum.square_length() > f32::EPSILON {
+                let normal = sum.normalize();
+                normal * (1.0 / normal.dot(prev).max(1.0 / MITER_LIMIT))
+            } else {
+                prev
+            };
+            contour.points[i] - normal * distance
+        })
+        .collect()
+}
+
+/// Fill-tessellate closed polygons.
+fn tessellate(
+    fill_tess: &mut FillTessellator,
+    polygons: &[Vec<Point>],
+    tolerance: f32,
+) -> lyon_tessellation::VertexBuffers<Point, u32> {
+    let mut builder = Path::builder();
+    for polygon in polygons {
+        builder.begin(polygon[0]);
+        for p in polygon.iter().skip(1) {
+            builder.line_to(*p);
+        }
+        builder.end(true);
+    }
+    let path = builder.build();
+
+    let mut buffers = lyon_tessellation::VertexBuffers::new();
+    if let Err(e) = fill_tess.tessellate_path(
+        &path,
+        &FillOptions::tolerance(tolerance),
+        &mut BuffersBuilder::new(&mut buffers, |vertex: FillVertex| vertex.position()),
+    ) {
+        error!("FillTessellator error: {:?}", e)
+    }
+    buffers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::mesh::VertexAttributeValues;
+
+    use crate::test_util::{assert_near, load};
+
+    fn triangles(mesh: &Mesh) -> Vec<[Vec3; 3]> {
+        let positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float3(positions)) => positions,
+            _ => panic!("no positions"),
+        };
+        let indices = match mesh.indices() {
+            Some(Indices::U32(indices)) => indices,
+            _ => panic!("no indices"),
+        };
+        indices.chunks_exact(3)
+            .map(|t| [positions[t[0] as usize].into(), positions[t[1] as usize].into(), positions[t[2] as usize].into()])
+            .collect()
+    }
+
+    /// Volume enclosed by the triangles, positive if they face outwards.
+    fn volume(mesh: &Mesh) -> f32 {
+        triangles(mesh).iter().map(|[a, b, c]| a.dot(b.cross(*c)) / 6.0).sum()
+    }
+
+    fn max_z(mesh: &Mesh) -> f32 {
+        triangles(mesh).iter().flat_map(|t| t.iter().map(|p| p.z)).fold(f32::NEG_INFINITY, f32::max)
+    }
+
+    #[test]
+    fn closed_box() {
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+            <rect width="10" height="10"/><rect width="5" height="5" fill="none" stroke="black"/>
+        </svg>"#);
+        let mesh = extrude(&svg, &SvgExtrusion { depth: 2.0, ..Default::default() }, Vec2::ONE);
+        // Two caps of two triangles and four walls of two triangles, the
+        // stroke is ignored.
+        assert_eq!(triangles(&mesh).len(), 12);
+        assert_near(volume(&mesh), 200.0, 1e-2);
+        assert_near(max_z(&mesh), 1.0, 1e-6);
+    }
+
+    #[test]
+    fn holes_face_inwards() {
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+            <path d="M0 0H10V10H0Z M2 2H8V8H2Z"/>
+        </svg>"#);
+        let mesh = extrude(&svg, &SvgExtrusion { depth: 1.0, ..Default::default() }, Vec2::new(2.0, 1.0));
+        assert_near(volume(&mesh), 2.0 * (100.0 - 36.0), 1e-2);
+    }
+
+    #[test]
+    fn bevel_keeps_the_mesh_closed() {
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+            <rect width="10" height="10"/>
+        </svg>"#);
+        let mesh = extrude(&svg, &SvgExtrusion { depth: 2.0, bevel: 0.5, ..Default::default() }, Vec2::ONE);
+        // A 10x10 box of depth 1 with a frustum on either side, from 10x10 to 9x9.
+        let frustum = 0.5 / 3.0 * (100.0 + 81.0 + 90.0);
+        assert_near(volume(&mesh), 100.0 + 2.0 * frustum, 1e-2);
+        assert_near(max_z(&mesh), 1.0, 1e-6);
+    }
+
+    #[test]
+    fn later_paths_stand_out() {
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+            <pattern id="dots" width="2" height="2" patternUnits="userSpaceOnUse"><circle cx="1" cy="1" r="1"/></pattern>
+            <rect width="10" height="10"/>
+            <rect x="2" y="2" width="4" height="4" fill="url(#dots)"/>
+        </svg>"#);
+        let mesh = extrude(&svg, &SvgExtrusion { depth: 2.0, ..Default::default() }, Vec2::ONE);
+        // The pattern-painted path is extruded too, a bit thicker.
+        assert_eq!(triangles(&mesh).len(), 24);
+        assert_near(max_z(&mesh), 1.01, 1e-6);
+        let front: Vec<f32> = triangles(&mesh).iter()
+            .filter(|t| t.iter().all(|p| p.z > 0.0 && (p.z - t[0].z).abs() < 1e-6))
+            .map(|t| t[0].z)
+            .collect();
+        assert!(front.iter().any(|z| (z - 1.0).abs() < 1e-6));
+        assert!(front.iter().any(|z| (z - 1.01).abs() < 1e-6));
+    }
+}
@@ -0,0 +1,635 @@
+//! SVG filter effects.
+//!
+//! Groups with a `filter` can't be drawn as a mesh. Their paths are instead
+//! rasterized on the CPU into an image covering the filter region, the filter
+//! primitives are applied to that image and the result is shown as a textured
+//! child entity of the SVG.
+//!
+//! Supported primitives are `feFlood`, `feGaussianBlur`, `feOffset`,
+//! `feDropShadow`, `feColorMatrix` and `feComposite`. Other primitives pass their
+//! input through unchanged and log a warning. Primitive subregions are ignored and colors are
+//! blended in sRGB, regardless of `color-interpolation-filters`.
+
+use std::collections::HashMap;
+
+use bevy::{
+    asset::Assets,
+    ecs::{entity::Entity, query::Added, system::{Commands, Query, ResMut}},
+    log::warn,
+    math::{Mat4, Vec2, Vec3},
+    render::texture::Texture,
+    sprite::{ColorMaterial, Sprite, entity::SpriteBundle},
+    transform::{components::Transform, hierarchy::BuildChildren},
+};
+use lyon_tessellation::{FillTessellator, StrokeTessellator, math::{Rect, Transform as Transform2D, point, size}};
+use usvg::NodeExt;
+
+use crate::{
+    geometry::union,
+    plugin::tessellate_path,
+    raster::{Image, MAX_IMAGE_SIZE},
+    svg::{PathDescriptor, Svg},
+    vertex_buffer::VertexBuffers,
+};
+
+/// Z offset of the filtered groups relative to the z of their paths.
+const FILTER_DEPTH: f32 = 0.001;
+
+/// A group of a SVG with a filter applied to it.
+///
+/// The paths of the group aren't part of [`Svg::paths`], they are drawn with
+/// the filter applied at the z of the group's topmost path. Use
+/// [`SvgBuilder::z_range`](crate::svg::SvgBuilder::z_range) to keep them in
+/// document order, otherwise they are drawn on top of the rest of the SVG.
+#[derive(Clone, Debug)]
+pub struct SvgFilter {
+    /// The `id` of the filtered group, empty if it has none.
+    pub id: String,
+    /// The paths of the group.
+    pub paths: Vec<PathDescriptor>,
+    /// The filter region, in the coordinate system of the filtered group.
+    pub region: Rect,
+    /// Maps the coordinate system of the filtered group to the local
+    /// coordinates of the SVG mesh.
+    pub transform: Mat4,
+    /// Resolution of the filter image, in pixels per unit of the group.
+    pub resolution: f32,
+    primitives: Vec<FilterStep>,
+}
+
+/// A filter primitive and the name of its result.
+#[derive(Clone, Debug)]
+struct FilterStep {
+    result: String,
+    primitive: FilterPrimitive,
+}
+
+#[derive(Clone, Debug)]
+enum FilterInput {
+    SourceGraphic,
+    SourceAlpha,
+    Result(String),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum CompositeOperator {
+    Over,
+    In,
+    Out,
+    Atop,
+    Xor,
+    Arithmetic([f32; 4]),
+}
+
+/// The supported filter primitives. Offsets and standard deviations are in
+/// pixels of the filter image.
+#[derive(Clone, Debug)]
+enum FilterPrimitive {
+    Flood { color: [f32; 4] },
+    GaussianBlur { input: FilterInput, std_dev: (f32, f32) },
+    Offset { input: FilterInput, dx: f32, dy: f32 },
+    DropShadow { input: FilterInput, dx: f32, dy: f32, std_dev: (f32, f32), color: [f32; 4] },
+    ColorMatrix { input: FilterInput, matrix: [f32; 20] },
+    Composite { input1: FilterInput, input2: FilterInput, operator: CompositeOperator },
+    PassThrough { input: FilterInput },
+}
+
+impl SvgFilter {
+    /// Create the [`SvgFilter`] of a group from the `<filter>` element it
+    /// references. Returns `None` when the filter doesn't exist, in which case
+    /// the group isn't rendered at all.
+    pub(crate) fn new(
+        tree: &usvg::Tree,
+        group: &usvg::Node,
+        paths: Vec<PathDescriptor>,
+        resolution: f32,
+    ) -> Option<SvgFilter> {
+        let (id, filter_id) = match *group.borrow() {
+            usvg::NodeKind::Group(ref g) => (g.id.clone(), g.filter.clone()?),
+            _ => return None,
+        };
+        let node = tree.defs_by_id(&filter_id)?;
+        let filter = match *node.borrow() {
+            usvg::NodeKind::Filter(ref filter) => filter.clone(),
+            _ => return None,
+        };
+
+        // The region and the primitives are given in the coordinate system of
+        // the group, the paths are in the one of the SVG.
+        let t = group.abs_transform();
+        let transform = Transform2D::new(t.a as f32, t.b as f32, t.c as f32, t.d as f32, t.e as f32, t.f as f32);
+        let to_group = transform.inverse()?;
+        let bbox = union(paths.iter().filter_map(|path| {
+            path.transformed(&to_group).stroke_bounds().map(|r| r.to_box2d())
+        }))?.to_rect();
+        let rect = &filter.rect;
+        let region = match filter.units {
+            usvg::Units::ObjectBoundingBox => Rect::new(
+                point(bbox.min_x() + rect.x() as f32 * bbox.width(), bbox.min_y() + rect.y() as f32 * bbox.height()),
+                size(rect.width() as f32 * bbox.width(), rect.height() as f32 * bbox.height()),
+            ),
+            usvg::Units::UserSpaceOnUse => Rect::new(
+                point(rect.x() as f32, rect.y() as f32),
+                size(rect.width() as f32, rect.height() as f32),
+            ),
+        };
+        if region.is_empty() {
+            return None;
+        }
+
+        let resolution = (resolution * transform.determinant().abs().sqrt())
+            .min(MAX_IMAGE_SIZE / region.width())
+            .min(MAX_IMAGE_SIZE / region.height());
+        // Lengths of the primitives in pixels of the filter image.
+        let (sx, sy) = match filter.primitive_units {
+            usvg::Units::ObjectBoundingBox => (bbox.width() * resolution, bbox.height() * resolution),
+            usvg::Units::UserSpaceOnUse => (resolution, resolution),
+        };
+
+        let primitives = filter.children.iter()
+            .map(|child| FilterStep {
+                result: child.result.clone(),
+                primitive: convert_primitive(&child.kind, sx, sy),
+            })
+            .collect();
+
+        Some(SvgFilter {
+            id,
+            paths,
+            region,
+            transform: Mat4::from_cols(
+                [transform.m11, transform.m12, 0.0, 0.0].into(),
+                [transform.m21, transform.m22, 0.0, 0.0].into(),
+                [0.0, 0.0, 1.0, 0.0].into(),
+                [transform.m31, transform.m32, 0.0, 1.0].into(),
+            ),
+            resolution,
+            primitives,
+        })
+    }
+
+    /// The z of the topmost path of the group, which places the filtered group
+    /// at its position in the document. A filter always has paths, otherwise
+    /// it has no region.
+    pub(crate) fn z(&self) -> f32 {
+        self.paths.iter().map(|path| path.z).fold(f32::NEG_INFINITY, f32::max)
+    }
+
+    /// Size of the filter image in pixels.
+    pub(crate) fn image_size(&self) -> (usize, usize) {
+        (
+            (self.region.width() * self.resolution).ceil().max(1.0) as usize,
+            (self.region.height() * self.resolution).ceil().max(1.0) as usize,
+        )
+    }
+
+    /// Rasterize the paths and apply the filter primitives.
+    pub(crate) fn render(&self, fill_tess: &mut FillTessellator, stroke_tess: &mut StrokeTessellator) -> Image {
+        let (width, height) = self.image_size();
+        let mut source = Image::new(width, height);
+        let m = self.transform;
+        let to_group = Transform2D::new(m.x_axis.x, m.x_axis.y, m.y_axis.x, m.y_axis.y, m.w_axis.x, m.w_axis.y)
+            .inverse()
+            .unwrap_or_else(Transform2D::identity);
+        for path in self.paths.iter() {
+            let path = path.transformed(&to_group);
+            let mut buffers = VertexBuffers::new();
+            tessellate_path(fill_tess, stroke_tess, &path, path.segments.iter().cloned(), &mut buffers);
+            source.rasterize(&buffers, &self.region, self.resolution);
+        }
+
+        let mut results: HashMap<&str, Image> = HashMap::new();
+        let mut last = source.clone();
+        for step in self.primitives.iter() {
+            let input = |input: &FilterInput| match *input {
+                FilterInput::SourceGraphic => source.clone(),
+                FilterInput::SourceAlpha => source.map(|[_, _, _, a]| [0.0, 0.0, 0.0, a]),
+                FilterInput::Result(ref name) => results.get(name.as_str()).unwrap_or(&last).clone(),
+            };
+
+            let image = match step.primitive {
+                FilterPrimitive::Flood { color } => Image::filled(width, height, color),
+                FilterPrimitive::GaussianBlur { input: ref i, std_dev } => input(i).blurred(std_dev),
+                FilterPrimitive::Offset { input: ref i, dx, dy } => input(i).offset(dx, dy),
+                FilterPrimitive::DropShadow { input: ref i, dx, dy, std_dev, color } => {
+                    let input = input(i);
+                    let shadow = input
+                        .map(|[_, _, _, a]| [color[0] * a, color[1] * a, color[2] * a, color[3] * a])
+                        .blurred(std_dev)
+                        .offset(dx, dy);
+                    input.composite(&shadow, CompositeOperator::Over)
+                },
+                FilterPrimitive::ColorMatrix { input: ref i, ref matrix } => input(i).color_matrix(matrix),
+                FilterPrimitive::Composite { ref input1, ref input2, operator } =>
+                    input(input1).composite(&input(input2), operator),
+                FilterPrimitive::PassThrough { input: ref i } => input(i),
+            };
+
+            results.insert(step.result.as_str(), image.clone());
+            last = image;
+        }
+
+        last
+    }
+}
+
+/// Convert a usvg filter primitive, scaling its lengths by `sx` and `sy`.
+fn convert_primitive(kind: &usvg::FilterKind, sx: f32, sy: f32) -> FilterPrimitive {
+    match *kind {
+        usvg::FilterKind::FeFlood(ref fe) => FilterPrimitive::Flood {
+            color: premultiplied(fe.color, fe.opacity.value() as f32),
+        },
+        usvg::FilterKind::FeGaussianBlur(ref fe) => FilterPrimitive::GaussianBlur {
+            input: convert_input(&fe.input),
+            std_dev: (fe.std_dev_x.value() as f32 * sx, fe.std_dev_y.value() as f32 * sy),
+        },
+        usvg::FilterKind::FeOffset(ref fe) => FilterPrimitive::Offset {
+            input: convert_input(&fe.input),
+            dx: fe.dx as f32 * sx,
+            dy: fe.dy as f32 * sy,
+        },
+        usvg::FilterKind::FeDropShadow(ref fe) => FilterPrimitive::DropShadow {
+            input: convert_input(&fe.input),
+            dx: fe.dx as f32 * sx,
+            dy: fe.dy as f32 * sy,
+            std_dev: (fe.std_dev_x.value() as f32 * sx, fe.std_dev_y.value() as f32 * sy),
+            color: premultiplied(fe.color, fe.opacity.value() as f32),
+        },
+        usvg::FilterKind::FeColorMatrix(ref fe) => FilterPrimitive::ColorMatrix {
+            input: convert_input(&fe.input),
+            matrix: color_matrix(&fe.kind),
+        },
+        usvg::FilterKind::FeComposite(ref fe) => FilterPrimitive::Composite {
+            input1: convert_input(&fe.input1),
+            input2: convert_input(&fe.input2),
+            operator: match fe.operator {
+                usvg::FeCompositeOperator::Over => CompositeOperator::Over,
+                usvg::FeCompositeOperator::In => CompositeOperator::In,
+                usvg::FeCompositeOperator::Out => CompositeOperator::Out,
+                usvg::FeCompositeOperator::Atop => CompositeOperator::Atop,
+                usvg::FeCompositeOperator::Xor => CompositeOperator::Xor,
+                usvg::FeCompositeOperator::Arithmetic { k1, k2, k3, k4 } =>
+                    CompositeOperator::Arithmetic([k1 as f32, k2 as f32, k3 as f32, k4 as f32]),
+            },
+        },
+        ref other => {
+            // Only the name of the variant, some primitives carry whole images.
+            let name = format!("{:?}", other);
+            warn!(
+                "Unsupported SVG filter primitive {}, its input is passed through",
+                name.split(|c: char| !c.is_alphanumeric()).next().unwrap_or_default(),
+            );
+            FilterPrimitive::PassThrough { input: FilterInput::Result(String::new()) }
+        },
+    }
+}
+
+fn convert_input(input: &usvg::FilterInput) -> FilterInput {
+    match *input {
+        usvg::FilterInput::SourceGraphic => FilterInput::SourceGraphic,
+        usvg::FilterInput::SourceAlpha => FilterInput::SourceAlpha,
+        usvg::FilterInput::Reference(ref name) => FilterInput::Result(name.clone()),
+        // Background and paint inputs are not available, like in most browsers.
+        _ => FilterInput::SourceGraphic,
+    }
+}
+
+/// The 4x5 matrix of a `feColorMatrix`, in row-major order.
+fn color_matrix(kind: &usvg::FeColorMatrixKind) -> [f32; 20] {
+    const IDENTITY: [f32; 20] = [
+        1.0, 0.0, 0.0, 0.0, 0.0,
+        0.0, 1.0, 0.0, 0.0, 0.0,
+        0.0, 0.0, 1.0, 0.0, 0.0,
+        0.0, 0.0, 0.0, 1.0, 0.0,
+    ];
+
+    match *kind {
+        usvg::FeColorMatrixKind::Matrix(ref values) if values.len() == 20 => {
+            let mut matrix = [0.0; 20];
+            for (m, v) in matrix.iter_mut().zip(values.iter()) {
+                *m = *v as f32;
+            }
+            matrix
+        },
+        usvg::FeColorMatrixKind::Matrix(_) => IDENTITY,
+        usvg::FeColorMatrixKind::Saturate(s) => {
+            let s = s.value() as f32;
+            [
+                0.213 + 0.787 * s, 0.715 - 0.715 * s, 0.072 - 0.072 * s, 0.0, 0.0,
+                0.213 - 0.213 * s, 0.715 + 0.285 * s, 0.072 - 0.072 * s, 0.0, 0.0,
+                0.213 - 0.213 * s, 0.715 - 0.715 * s, 0.072 + 0.928 * s, 0.0, 0.0,
+                0.0, 0.0, 0.0, 1.0, 0.0,
+            ]
+        },
+        usvg::FeColorMatrixKind::HueRotate(degrees) => {
+            let (sin, cos) = (degrees as f32).to_radians().sin_cos();
+            [
+                0.213 + cos * 0.787 - sin * 0.213,
+                0.715 - cos * 0.715 - sin * 0.715,
+                0.072 - cos * 0.072 + sin * 0.928,
+                0.0, 0.0,
+                0.213 - cos * 0.213 + sin * 0.143,
+                0.715 + cos * 0.285 + sin * 0.140,
+                0.072 - cos * 0.072 - sin * 0.283,
+                0.0, 0.0,
+                0.213 - cos * 0.213 - sin * 0.787,
+                0.715 - cos * 0.715 + sin * 0.715,
+                0.072 + cos * 0.928 + sin * 0.072,
+                0.0, 0.0,
+                0.0, 0.0, 0.0, 1.0, 0.0,
+            ]
+        },
+        usvg::FeColorMatrixKind::LuminanceToAlpha => [
+            0.0, 0.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 0.0, 0.0,
+            0.2125, 0.7154, 0.0721, 0.0, 0.0,
+        ],
+    }
+}
+
+fn premultiplied(color: usvg::Color, opacity: f32) -> [f32; 4] {
+    [
+        color.red as f32 / 255.0 * opacity,
+        color.green as f32 / 255.0 * opacity,
+        color.blue as f32 / 255.0 * opacity,
+        opacity,
+    ]
+}
+
+impl Image {
+    /// Approximate a gaussian blur with three box blurs per axis, like the SVG
+    /// specification suggests.
+    fn blurred(&self, (std_dev_x, std_dev_y): (f32, f32)) -> Image {
+        let mut image = self.clone();
+        for _ in 0..3 {
+            image.box_blur(box_radius(std_dev_x), true);
+            image.box_blur(box_radius(std_dev_y), false);
+        }
+        image
+    }
+
+    fn box_blur(&mut self, radius: usize, horizontal: bool) {
+        if radius == 0 {
+            return;
+        }
+        let (lines, length) = if horizontal { (self.height, self.width) } else { (self.width, self.height) };
+        let index = |line: usize, i: usize| if horizontal { line * self.width + i } else { i * self.width + line };
+        let size = (2 * radius + 1) as f32;
+
+        let mut line_pixels = vec![[0.0; 4]; length];
+        for line in 0..lines {
+            for (i, pixel) in line_pixels.iter_mut().enumerate() {
+                *pixel = self.pixels[index(line, i)];
+            }
+
+            // Running sum over the window, pixels outside the image are transparent.
+            let mut sum = [0.0; 4];
+            for pixel in line_pixels.iter().take(radius) {
+                add(&mut sum, pixel, 1.0);
+            }
+            for i in 0..length {
+                if i + radius < length {
+                    add(&mut sum, &line_pixels[i + radius], 1.0);
+                }
+                if i > radius {
+                    add(&mut sum, &line_pixels[i - radius - 1], -1.0);
+                }
+                self.pixels[index(line, i)] = [sum[0] / size, sum[1] / size, sum[2] / size, sum[3] / size];
+            }
+        }
+    }
+
+    /// Move the image by whole pixels.
+    fn offset(&self, dx: f32, dy: f32) -> Image {
+        let (dx, dy) = (dx.round() as isize, dy.round() as isize);
+        let mut image = Image::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (sx, sy) = (x as isize - dx, y as isize - dy);
+                if sx >= 0 && sy >= 0 && (sx as usize) < self.width && (sy as usize) < self.height {
+                    image.pixels[y * self.width + x] = self.pixels[sy as usize * self.width + sx as usize];
+                }
+            }
+        }
+        image
+    }
+
+    fn color_matrix(&self, m: &[f32; 20]) -> Image {
+        self.map(|[r, g, b, a]| {
+            if a <= 0.0 && m[19] <= 0.0 {
+                return [0.0; 4];
+            }
+            let (r, g, b) = if a > 0.0 { (r / a, g / a, b / a) } else { (0.0, 0.0, 0.0) };
+            let row = |i: usize| (m[i] * r + m[i + 1] * g + m[i + 2] * b + m[i + 3] * a + m[i + 4]).max(0.0).min(1.0);
+            let alpha = row(15);
+            [row(0) * alpha, row(5) * alpha, row(10) * alpha, alpha]
+        })
+    }
+
+    /// Composite `self` as the first input with `other` as the second one.
+    fn composite(&self, other: &Image, operator: CompositeOperator) -> Image {
+        let pixels = self.pixels.iter().zip(other.pixels.iter())
+            .map(|(a, b)| {
+                let (fa, fb) = match operator {
+                    CompositeOperator::Over => (1.0, 1.0 - a[3]),
+                    CompositeOperator::In => (b[3], 0.0),
+                    CompositeOperator::Out => (1.0 - b[3], 0.0),
+                    CompositeOperator::Atop => (b[3], 1.0 - a[3]),
+                    CompositeOperator::Xor => (1.0 - b[3], 1.0 - a[3]),
+                    CompositeOperator::Arithmetic([k1, k2, k3, k4]) => {
+                        let channel = |i: usize| (k1 * a[i] * b[i] + k2 * a[i] + k3 * b[i] + k4).max(0.0).min(1.0);
+                        let alpha = channel(3);
+                        return [channel(0).min(alpha), channel(1).min(alpha), channel(2).min(alpha), alpha];
+                    },
+                };
+                let mut pixel = [0.0; 4];
+                add(&mut pixel, a, fa);
+                add(&mut pixel, b, fb);
+                pixel
+            })
+            .collect();
+        Image { width: self.width, height: self.height, pixels }
+    }
+}
+
+fn add(sum: &mut [f32; 4], pixel: &[f32; 4], factor: f32) {
+    for i in 0..4 {
+        sum[i] += pixel[i] * factor;
+    }
+}
+
+/// Radius of the box blurs approximating a gaussian blur with the given
+/// standard deviation.
+fn box_radius(std_dev: f32) -> usize {
+    if std_dev <= 0.0 {
+        return 0;
+    }
+    let size = (std_dev * 3.0 * (2.0 * std::f32::consts::PI).sqrt() / 4.0 + 0.5).floor();
+    (size / 2.0) as usize
+}
+
+/// Bevy system which renders the [`SvgFilter`]s of newly spawned SVGs and
+/// spawns them as textured child entities.
+pub(crate) fn svg_filter_spawner(
+    mut commands: Commands,
+    mut textures: ResMut<Assets<Texture>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut fill_tess: ResMut<FillTessellator>,
+    mut stroke_tess: ResMut<StrokeTessellator>,
+    query: Query<(Entity, &Svg), Added<Svg>>,
+) {
+    for (entity, svg) in query.iter() {
+        if svg.filters.is_empty() {
+            continue;
+        }
+
+        let mut children = Vec::with_capacity(svg.filters.len());
+        for (i, filter) in svg.filters.iter().enumerate() {
+            let texture = filter.render(&mut fill_tess, &mut stroke_tess).to_texture();
+            let center = filter.region.center();
+            let z = filter.z() + FILTER_DEPTH * (i + 1) as f32;
+            let mut local = Transform::from_translation(Vec3::new(center.x, center.y, z));
+            // The SVG entity flips the y-axis, which would turn the texture upside down.
+            local.scale = Vec3::new(1.0, -1.0, 1.0);
+            let transform = Transform::from_matrix(filter.transform * local.compute_matrix());
+
+            children.push(SpriteBundle {
+                sprite: Sprite::new(Vec2::new(filter.region.width(), filter.region.height())),
+                material: materials.add(ColorMaterial::texture(textures.add(texture))),
+                transform,
+                ..Default::default()
+            });
+        }
+
+        commands.entity(entity).with_children(|parent| {
+            for child in children {
+                parent.spawn_bundle(child);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_near, load};
+
+    fn dot(size: usize) -> Image {
+        let mut image = Image::new(size, size);
+        image.pixels[size / 2 * size + size / 2] = [1.0, 0.0, 0.0, 1.0];
+        image
+    }
+
+    fn total_alpha(image: &Image) -> f32 {
+        image.pixels.iter().map(|p| p[3]).sum()
+    }
+
+    #[test]
+    fn box_radius_follows_the_specification() {
+        assert_eq!(box_radius(0.0), 0);
+        assert_eq!(box_radius(1.0), 1);
+        assert_eq!(box_radius(2.0), 2);
+        assert_eq!(box_radius(10.0), 9);
+    }
+
+    #[test]
+    fn blur_spreads_and_keeps_alpha() {
+        let blurred = dot(21).blurred((2.0, 2.0));
+        assert_near(total_alpha(&blurred), 1.0, 1e-4);
+        let center = blurred.pixels[10 * 21 + 10][3];
+        assert!(center < 1.0 && center > blurred.pixels[10 * 21 + 12][3]);
+        // Only horizontally.
+        let blurred = dot(21).blurred((2.0, 0.0));
+        assert!(blurred.pixels[10 * 21 + 11][3] > 0.0);
+        assert_eq!(blurred.pixels[11 * 21 + 10][3], 0.0);
+    }
+
+    #[test]
+    fn box_blur_averages_the_window() {
+        let mut image = Image::filled(5, 1, [0.0; 4]);
+        image.pixels[2] = [0.0, 0.0, 0.0, 3.0];
+        image.box_blur(1, true);
+        let alpha: Vec<f32> = image.pixels.iter().map(|p| p[3]).collect();
+        assert_eq!(alpha, vec![0.0, 1.0, 1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn offset_moves_whole_pixels() {
+        let moved = dot(5).offset(1.4, -1.0);
+        assert_eq!(moved.pixels[5 + 3], [1.0, 0.0, 0.0, 1.0]);
+        assert_near(total_alpha(&moved), 1.0, 1e-6);
+    }
+
+    #[test]
+    fn composite_operators() {
+        let a = Image::filled(1, 1, [0.5, 0.0, 0.0, 0.5]);
+        let b = Image::filled(1, 1, [0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(a.composite(&b, CompositeOperator::Over).pixels[0], [0.5, 0.0, 0.5, 1.0]);
+        assert_eq!(a.composite(&b, CompositeOperator::In).pixels[0], [0.5, 0.0, 0.0, 0.5]);
+        assert_eq!(a.composite(&b, CompositeOperator::Out).pixels[0], [0.0; 4]);
+        assert_eq!(a.composite(&b, CompositeOperator::Xor).pixels[0], [0.0, 0.0, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn color_matrix_on_premultiplied_colors() {
+        let image = Image::filled(1, 1, [0.5, 0.25, 0.0, 0.5]);
+        let identity = color_matrix(&usvg::FeColorMatrixKind::Saturate(usvg::PositiveNumber::new(1.0)));
+        let pixel = image.color_matrix(&identity).pixels[0];
+        for (actual, expected) in pixel.iter().zip([0.5, 0.25, 0.0, 0.5].iter()) {
+            assert_near(*actual, *expected, 1e-3);
+        }
+
+        let alpha = image.color_matrix(&color_matrix(&usvg::FeColorMatrixKind::LuminanceToAlpha)).pixels[0];
+        assert_near(alpha[3], 0.2125 + 0.7154 * 0.5, 1e-4);
+    }
+
+    #[test]
+    fn filtered_group_from_document() {
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <filter id="shadow"><feOffset dx="2" dy="2"/><feTurbulence baseFrequency="0.1"/></filter>
+            <g id="card" filter="url(#shadow)"><rect x="10" y="10" width="20" height="10"/></g>
+        </svg>"#);
+        assert!(svg.paths.is_empty());
+        let filter = &svg.filters[0];
+        assert_eq!(filter.id, "card");
+        // The default region reaches 10% beyond the bounding box.
+        assert_near(filter.region.min_x(), 8.0, 1e-4);
+        assert_near(filter.region.width(), 24.0, 1e-4);
+        assert_eq!(filter.image_size(), (24, 12));
+        assert_eq!(filter.z(), 0.0);
+
+        let image = filter.render(&mut FillTessellator::new(), &mut StrokeTessellator::new());
+        // The unsupported turbulence passes the offset rectangle through, which
+        // is cut off by the bottom of the region.
+        assert_near(total_alpha(&image), 180.0, 1e-3);
+        assert_eq!(image.pixels[0][3], 0.0);
+        assert_eq!(image.pixels[11 * 24 + 23][3], 1.0);
+    }
+
+    #[test]
+    fn region_in_group_coordinates() {
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <filter id="shift"><feOffset dx="2" dy="0"/></filter>
+            <g transform="translate(50 0) scale(2)" filter="url(#shift)">
+                <rect x="10" y="10" width="20" height="10"/>
+            </g>
+        </svg>"#);
+        let filter = &svg.filters[0];
+        assert_near(filter.region.min_x(), 8.0, 1e-4);
+        assert_near(filter.region.min_y(), 9.0, 1e-4);
+        assert_near(filter.region.width(), 24.0, 1e-4);
+        // The resolution is per unit of the SVG, the group is scaled by 2.
+        assert_eq!(filter.image_size(), (48, 24));
+
+        let corner = filter.transform.transform_point3(Vec3::new(8.0, 9.0, 0.0));
+        assert_near(corner.x, 66.0, 1e-4);
+        assert_near(corner.y, 18.0, 1e-4);
+
+        // The rectangle covers pixels 4..44 by 2..22, moved by 2 units.
+        let image = filter.render(&mut FillTessellator::new(), &mut StrokeTessellator::new());
+        assert_eq!(image.pixels[2 * 48 + 7][3], 0.0);
+        assert_eq!(image.pixels[2 * 48 + 8][3], 1.0);
+        assert_eq!(image.pixels[2 * 48 + 47][3], 1.0);
+    }
+}
@@ -0,0 +1,579 @@
+//! Bounds and geometry queries on the paths of a [`Svg`].
+
+use lyon_geom::{Box2D, CubicBezierSegment, LineSegment, QuadraticBezierSegment, Segment};
+use lyon_svg::path::{PathEvent, iterator::PathIterator};
+use lyon_tessellation::{LineCap, LineJoin, math::{Point, Rect, Vector, vector}};
+
+use crate::svg::{DrawType, PathDescriptor, Svg};
+
+/// Tolerance used when approximating the length of curves.
+const LENGTH_TOLERANCE: f32 = 0.01;
+
+/// Axis-aligned bounds of a loaded [`Svg`] and of each of its paths.
+///
+/// All rectangles are in the same coordinate space as the tessellated mesh,
+/// i.e. SVG user units before the Bevy [`Transform`](bevy::transform::components::Transform)
+/// is applied.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SvgBounds {
+    /// Bounds of the geometry of all paths, ignoring stroke widths.
+    pub geometry: Rect,
+    /// Bounds of everything that gets drawn, including stroke widths.
+    pub visual: Rect,
+    /// Bounds of each path, in the same order as [`Svg::paths`]. The paths of
+    /// filtered groups and pattern-painted paths only count towards
+    /// `geometry` and `visual`.
+    pub paths: Vec<PathBounds>,
+}
+
+/// Axis-aligned bounds of a single [`PathDescriptor`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PathBounds {
+    /// Bounds of the path geometry, ignoring the stroke width.
+    pub geometry: Rect,
+    /// Bounds of what gets drawn for the path, including the stroke width.
+    pub visual: Rect,
+}
+
+impl Svg {
+    /// Compute the [`SvgBounds`] of all paths of this SVG, including the
+    /// ones of filtered groups and pattern-painted ones.
+    pub fn bounds(&self) -> SvgBounds {
+        let paths: Vec<PathBounds> = self.paths.iter()
+            .map(|path| PathBounds {
+                geometry: path.bounds().unwrap_or_default(),
+                visual: path.stroke_bounds().unwrap_or_default(),
+            })
+            .collect();
+
+        let drawn = self.drawn_paths();
+        let geometry = drawn.iter().filter_map(|path| path.bounds().map(|r| r.to_box2d()));
+        let visual = drawn.iter().filter_map(|path| path.stroke_bounds().map(|r| r.to_box2d()));
+
+        SvgBounds {
+            geometry: union(geometry).map(|b| b.to_rect()).unwrap_or_default(),
+            visual: union(visual).map(|b| b.to_rect()).unwrap_or_default(),
+            paths,
+        }
+    }
+}
+
+impl PathDescriptor {
+    /// Axis-aligned bounds of the path geometry, without taking the stroke
+    /// width into account. Returns `None` if the path has no segments.
+    pub fn bounds(&self) -> Option<Rect> {
+        let boxes = self.segments.iter().filter_map(|event| match *event {
+            PathEvent::Begin { at } => Some(Box2D::new(at, at)),
+            _ => Edge::from_event(event).map(|edge| edge.bounding_box()),
+        });
+
+        union(boxes).map(|b| b.to_rect())
+    }
+
+    /// Axis-aligned bounds of what gets drawn for this path. For a
+    /// [`DrawType::Stroke`] this includes the line width, joins and caps,
+    /// for a [`DrawType::Fill`] it is the same as [`PathDescriptor::bounds`].
+    pub fn stroke_bounds(&self) -> Option<Rect> {
+        let bounds = self.bounds()?;
+        match self.draw_type {
+            DrawType::Fill => Some(bounds),
+            DrawType::Stroke(ref opts) => {
+                let half_width = opts.line_width / 2.0;
+                let mut extent = half_width;
+                if opts.start_cap == LineCap::Square || opts.end_cap == LineCap::Square {
+                    extent = extent.max(half_width * std::f32::consts::SQRT_2);
+                }
+                if let LineJoin::Miter | LineJoin::MiterClip = opts.line_join {
+                    extent = extent.max(half_width * opts.miter_limit);
+                }
+                Some(bounds.inflate(extent, extent))
+            },
+        }
+    }
+
+    /// Length of the path, summed over all its sub-paths.
+    pub fn length(&self) -> f32 {
+        length(&self.segments)
+    }
+
+    /// Point at the given distance along the path. The distance is clamped
+    /// to the length of the path. Returns `None` if the path has no edges.
+    pub fn point_at_length(&self, distance: f32) -> Option<Point> {
+        edge_at_length(&self.segments, distance).map(|(edge, t)| edge.sample(t))
+    }
+
+    /// Normalized tangent at the given distance along the path. The distance
+    /// is clamped to the length of the path. Returns `None` if the path has
+    /// no edges.
+    pub fn tangent_at_length(&self, distance: f32) -> Option<Vector> {
+        edge_at_length(&self.segments, distance).map(|(edge, t)| edge.tangent(t))
+    }
+
+    /// The segments of the first `distance` units of the path, e.g. to draw
+    /// only part of a stroke. Sub-paths that get cut are left open.
+    pub fn segments_to_length(&self, distance: f32) -> Vec<PathEvent> {
+        let mut segments = Vec::new();
+        let mut remaining = distance;
+        let mut current = Point::zero();
+        let mut first = Point::zero();
+        for event in self.segments.iter() {
+            if let PathEvent::Begin { at } = *event {
+                if remaining <= 0.0 {
+                    break;
+                }
+                current = at;
+                first = at;
+                segments.push(*event);
+                continue;
+            }
+
+            let edge = match Edge::from_event(event) {
+                Some(edge) => edge,
+                None => {
+                    segments.push(*event);
+                    continue;
+                },
+            };
+            let length = edge.length();
+            if remaining >= length {
+                remaining -= length;
+                current = edge.to();
+                segments.push(*event);
+                continue;
+            }
+
+            if remaining > 0.0 {
+                let part = edge.split_range(0.0, edge.t_at_length(remaining, length));
+                current = part.to();
+                segments.push(part.to_event());
+            }
+            segments.push(PathEvent::End { last: current, first, close: false });
+            break;
+        }
+
+        segments
+    }
+}
+
+/// Iterate over all drawable edges of a path, including the implicit closing
+/// line of closed sub-paths.
+pub(crate) fn edges(segments: &[PathEvent]) -> impl Iterator<Item = Edge> + '_ {
+    segments.iter().filter_map(Edge::from_event)
+}
+
+/// Length of a path, summed over all its sub-paths.
+pub(crate) fn length(segments: &[PathEvent]) -> f32 {
+    edges(segments).map(|edge| edge.length()).sum()
+}
+
+/// Find the edge at the given distance along a path and the curve parameter
+/// `t` on that edge. The distance is clamped to the length of the path.
+pub(crate) fn edge_at_length(segments: &[PathEvent], distance: f32) -> Option<(Edge, f32)> {
+    let mut remaining = distance.max(0.0);
+    let mut last = None;
+    for edge in edges(segments) {
+        let length = edge.length();
+        if remaining <= length {
+            return Some((edge, edge.t_at_length(remaining, length)));
+        }
+        remaining -= length;
+        last = Some(edge);
+    }
+
+    last.map(|edge| (edge, 1.0))
+}
+
+/// The parts of a path between the distances `start` and `end` along it, e.g.
+/// for trimmed paths. Every part becomes an open sub-path.
+#[cfg(feature = "lottie")]
+pub(crate) fn trim(segments: &[PathEvent], start: f32, end: f32) -> Vec<PathEvent> {
+    let mut trimmed = Vec::new();
+    let mut offset = 0.0;
+    let mut open: Option<(Point, Point)> = None;
+    for event in segments.iter() {
+        if let PathEvent::Begin { .. } = *event {
+            if let Some((first, last)) = open.take() {
+                trimmed.push(PathEvent::End { last, first, close: false });
+            }
+        }
+
+        let edge = match Edge::from_event(event) {
+            Some(edge) => edge,
+            None => continue,
+        };
+        let length = edge.length();
+        let (edge_start, edge_end) = (offset, offset + length);
+        offset = edge_end;
+        if length <= 0.0 || edge_end <= start || edge_start >= end {
+            continue;
+        }
+
+        let t0 = if start > edge_start { edge.t_at_length(start - edge_start, length) } else { 0.0 };
+        let t1 = if end < edge_end { edge.t_at_length(end - edge_start, length) } else { 1.0 };
+        let part = edge.split_range(t0, t1);
+        match open {
+            Some((_, ref mut last)) => *last = part.to(),
+            None => {
+                trimmed.push(PathEvent::Begin { at: part.from() });
+                open = Some((part.from(), part.to()));
+            },
+        }
+        trimmed.push(part.to_event());
+    }
+    if let Some((first, last)) = open {
+        trimmed.push(PathEvent::End { last, first, close: false });
+    }
+
+    trimmed
+}
+
+/// A single drawable edge of a path.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Edge {
+    Line(LineSegment<f32>),
+    Quadratic(QuadraticBezierSegment<f32>),
+    Cubic(CubicBezierSegment<f32>),
+}
+
+impl Edge {
+    /// Create an [`Edge`] from a [`PathEvent`]. `Begin` events and `End`
+    /// events of open or already closed sub-paths have no edge.
+    pub(crate) fn from_event(event: &PathEvent) -> Option<Edge> {
+        match *event {
+            PathEvent::Begin { .. } => None,
+            PathEvent::Line { from, to } => Some(Edge::Line(LineSegment { from, to })),
+            PathEvent::Quadratic { from, ctrl, to } =>
+                Some(Edge::Quadratic(QuadraticBezierSegment { from, ctrl, to })),
+            PathEvent::Cubic { from, ctrl1, ctrl2, to } =>
+                Some(Edge::Cubic(CubicBezierSegment { from, ctrl1, ctrl2, to })),
+            PathEvent::End { last, first, close } if close && last != first =>
+                Some(Edge::Line(LineSegment { from: last, to: first })),
+            PathEvent::End { .. } => None,
+        }
+    }
+
+    pub(crate) fn from(&self) -> Point {
+        match self {
+            Edge::Line(s) => s.from,
+            Edge::Quadratic(s) => s.from,
+            Edge::Cubic(s) => s.from,
+        }
+    }
+
+    pub(crate) fn to(&self) -> Point {
+        match self {
+            Edge::Line(s) => s.to,
+            Edge::Quadratic(s) => s.to,
+            Edge::Cubic(s) => s.to,
+        }
+    }
+
+    pub(crate) fn sample(&self, t: f32) -> Point {
+        match self {
+            Edge::Line(s) => s.sample(t),
+            Edge::Quadratic(s) => s.sample(t),
+            Edge::Cubic(s) => s.sample(t),
+        }
+    }
+
+    pub(crate) fn length(&self) -> f32 {
+        match self {
+            Edge::Line(s) => s.length(),
+            Edge::Quadratic(s) => s.approximate_length(LENGTH_TOLERANCE),
+            Edge::Cubic(s) => s.approximate_length(LENGTH_TOLERANCE),
+        }
+    }
+
+    /// Normalized tangent at `t`. Falls back to the direction between the
+    /// end points for degenerate curves.
+    pub(crate) fn tangent(&self, t: f32) -> Vector {
+        let derivative = match self {
+            Edge::Line(s) => s.to_vector(),
+            Edge::Quadratic(s) => s.derivative(t),
+            Edge::Cubic(s) => s.derivative(t),
+        };
+        let tangent = if derivative.square_length() > f32::EPSILON {
+            derivative
+        } else {
+            self.to() - self.from()
+        };
+
+        tangent.try_normalize().unwrap_or_else(Vector::zero)
+    }
+
+    /// The part of this edge between the curve parameters `t0` and `t1`.
+    pub(crate) fn split_range(&self, t0: f32, t1: f32) -> Edge {
+        match self {
+            Edge::Line(s) => Edge::Line(s.split_range(t0..t1)),
+            Edge::Quadratic(s) => Edge::Quadratic(s.split_range(t0..t1)),
+            Edge::Cubic(s) => Edge::Cubic(s.split_range(t0..t1)),
+        }
+    }
+
+    /// The edge as a cubic bézier curve.
+    pub(crate) fn to_cubic(&self) -> CubicBezierSegment<f32> {
+        match *self {
+            Edge::Line(s) => CubicBezierSegment {
+                from: s.from,
+                ctrl1: s.sample(1.0 / 3.0),
+                ctrl2: s.sample(2.0 / 3.0),
+                to: s.to,
+            },
+            Edge::Quadratic(s) => s.to_cubic(),
+            Edge::Cubic(s) => s,
+        }
+    }
+
+    /// Convert the edge back into a [`PathEvent`].
+    pub(crate) fn to_event(&self) -> PathEvent {
+        match *self {
+            Edge::Line(s) => PathEvent::Line { from: s.from, to: s.to },
+            Edge::Quadratic(s) => PathEvent::Quadratic { from: s.from, ctrl: s.ctrl, to: s.to },
+            Edge::Cubic(s) => PathEvent::Cubic { from: s.from, ctrl1: s.ctrl1, ctrl2: s.ctrl2, to: s.to },
+        }
+    }
+
+    fn bounding_box(&self) -> Box2D<f32> {
+        match self {
+            Edge::Line(s) => s.bounding_box(),
+            Edge::Quadratic(s) => s.bounding_box(),
+            Edge::Cubic(s) => s.bounding_box(),
+        }
+    }
+
+    /// Curve parameter at `distance` along the edge, found by bisection over
+    /// the approximated length of the sub-curve.
+    pub(crate) fn t_at_length(&self, distance: f32, length: f32) -> f32 {
+        if length <= f32::EPSILON {
+            return 0.0;
+        }
+        if let Edge::Line(_) = self {
+            return (distance / length).min(1.0);
+        }
+
+        let (mut low, mut high) = (0.0_f32, 1.0_f32);
+        for _ in 0..16 {
+            let mid = (low + high) / 2.0;
+            if self.split_range(0.0, mid).length() < distance {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+
+        (low + high) / 2.0
+    }
+}
+
+/// A closed outline and the outward normal of each edge, from point `i` to
+/// point `i + 1`.
+pub(crate) struct Contour {
+    pub(crate) points: Vec<Point>,
+    pub(crate) normals: Vec<Vector>,
+}
+
+/// Flatten the segments into closed contours and find the outward normals of
+/// their edges, using the even-odd rule like the fill tessellation.
+pub(crate) fn contours(segments: &[PathEvent], tolerance: f32) -> Vec<Contour> {
+    let mut polygons: Vec<Vec<Point>> = Vec::new();
+    let mut polygon: Vec<Point> = Vec::new();
+    for event in segments.iter().cloned().flattened(tolerance) {
+        match event {
+            PathEvent::Begin { at } => {
+                polygon.clear();
+                polygon.push(at);
+            },
+            PathEvent::Line { to, .. } => {
+                if polygon.last().map_or(true, |last| (*last - to).square_length() > f32::EPSILON) {
+                    polygon.push(to);
+                }
+            },
+            PathEvent::End { .. } => {
+                while polygon.len() > 1 && (polygon[0] - polygon[polygon.len() - 1]).square_length() <= f32::EPSILON {
+                    polygon.pop();
+                }
+                if polygon.len() >= 3 {
+                    polygons.push(std::mem::take(&mut polygon));
+                }
+            },
+            _ => {},
+        }
+    }
+
+    polygons.iter()
+        .map(|points| {
+            let n = points.len();
+            let normals = (0..n)
+                .map(|i| {
+                    let (a, b) = (points[i], points[(i + 1) % n]);
+                    let d = b - a;
+                    let normal = vector(d.y, -d.x).normalize();
+                    let probe = a.lerp(b, 0.5) + normal * (d.length() * 1e-3).max(1e-4);
+                    if inside(&polygons, probe) { -normal } else { normal }
+                })
+                .collect();
+            Contour { points: points.clone(), normals }
+        })
+        .collect()
+}
+
+/// Whether `p` is inside the polygons according to the even-odd rule.
+fn inside(polygons: &[Vec<Point>], p: Point) -> bool {
+    let mut inside = false;
+    for polygon in polygons {
+        let n = polygon.len();
+        for i in 0..n {
+            let (a, b) = (polygon[i], polygon[(i + 1) % n]);
+            if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+/// Union of all given boxes, `None` if there are none. Unlike
+/// [`Box2D::union`] this keeps degenerate boxes, e.g. of horizontal lines.
+pub(crate) fn union(boxes: impl Iterator<Item = Box2D<f32>>) -> Option<Box2D<f32>> {
+    boxes.fold(None, |acc: Option<Box2D<f32>>, b| match acc {
+        Some(acc) => Some(Box2D::new(acc.min.min(b.min), acc.max.max(b.max))),
+        None => Some(b),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use lyon_tessellation::math::point;
+
+    use super::*;
+    use crate::test_util::{assert_near, fill, load, rect, stroke};
+
+    #[test]
+    fn bounds_of_fill_and_stroke() {
+        let path = fill(rect(10.0, 20.0, 30.0, 40.0));
+        assert_eq!(path.bounds(), Some(Rect::new(point(10.0, 20.0), lyon_tessellation::math::size(30.0, 40.0))));
+        assert_eq!(path.stroke_bounds(), path.bounds());
+
+        let path = stroke(rect(10.0, 20.0, 30.0, 40.0), 4.0);
+        let visual = path.stroke_bounds().unwrap();
+        // Miter joins reach up to the miter limit times the half width.
+        assert!(visual.min_x() <= 8.0 && visual.max_x() >= 42.0);
+        assert!(visual.contains_rect(&path.bounds().unwrap()));
+    }
+
+    #[test]
+    fn empty_path_has_no_bounds() {
+        assert_eq!(fill(Vec::new()).bounds(), None);
+    }
+
+    #[test]
+    fn svg_bounds_cover_all_paths() {
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <rect x="10" y="10" width="10" height="10"/>
+            <rect x="50" y="60" width="20" height="5" fill="none" stroke="black" stroke-width="2"/>
+        </svg>"#);
+        let bounds = svg.bounds();
+        assert_eq!(bounds.paths.len(), 2);
+        assert_near(bounds.geometry.min_x(), 10.0, 1e-4);
+        assert_near(bounds.geometry.max_x(), 70.0, 1e-4);
+        assert_near(bounds.geometry.max_y(), 65.0, 1e-4);
+        assert!(bounds.visual.max_x() >= 71.0);
+    }
+
+    #[test]
+    fn svg_bounds_cover_filtered_groups() {
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <filter id="shift"><feOffset dx="2" dy="0"/></filter>
+            <rect x="10" y="10" width="10" height="10"/>
+            <g filter="url(#shift)"><rect x="50" y="60" width="20" height="5"/></g>
+        </svg>"#);
+        let bounds = svg.bounds();
+        assert_eq!(bounds.paths.len(), 1);
+        assert_near(bounds.geometry.max_x(), 70.0, 1e-4);
+        assert_near(bounds.geometry.max_y(), 65.0, 1e-4);
+    }
+
+    #[test]
+    fn svg_bounds_cover_pattern_painted_paths() {
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <pattern id="dots" width="10" height="10" patternUnits="userSpaceOnUse"><circle cx="5" cy="5" r="2"/></pattern>
+            <rect x="10" y="10" width="10" height="10"/>
+            <rect x="50" y="60" width="20" height="5" fill="url(#dots)"/>
+        </svg>"#);
+        let bounds = svg.bounds();
+        assert_eq!(bounds.paths.len(), 1);
+        assert_near(bounds.geometry.max_x(), 70.0, 1e-4);
+        assert_near(bounds.geometry.max_y(), 65.0, 1e-4);
+    }
+
+    #[test]
+    fn length_includes_closing_edge() {
+        let path = fill(rect(0.0, 0.0, 10.0, 5.0));
+        assert_near(path.length(), 30.0, 1e-4);
+    }
+
+    #[test]
+    fn point_and_tangent_at_length() {
+        let path = fill(rect(0.0, 0.0, 10.0, 5.0));
+        let p = path.point_at_length(12.0).unwrap();
+        assert_near(p.x, 10.0, 1e-4);
+        assert_near(p.y, 2.0, 1e-4);
+        let tangent = path.tangent_at_length(12.0).unwrap();
+        assert_near(tangent.x, 0.0, 1e-4);
+        assert_near(tangent.y, 1.0, 1e-4);
+
+        // Distances are clamped to the path.
+        let end = path.point_at_length(100.0).unwrap();
+        assert_near(end.x, 0.0, 1e-4);
+        assert_near(end.y, 0.0, 1e-4);
+    }
+
+    #[test]
+    fn length_of_curves_is_approximated() {
+        // A quarter circle of radius 10 as a cubic.
+        let k = 10.0 * 0.552_284_8;
+        let segments = vec![
+            PathEvent::Begin { at: point(10.0, 0.0) },
+            PathEvent::Cubic { from: point(10.0, 0.0), ctrl1: point(10.0, k), ctrl2: point(k, 10.0), to: point(0.0, 10.0) },
+            PathEvent::End { last: point(0.0, 10.0), first: point(10.0, 0.0), close: false },
+        ];
+        assert_near(length(&segments), std::f32::consts::FRAC_PI_2 * 10.0, 0.05);
+
+        let (edge, t) = edge_at_length(&segments, length(&segments) / 2.0).unwrap();
+        let middle = edge.sample(t);
+        assert_near(middle.x, middle.y, 0.05);
+    }
+
+    #[test]
+    fn segments_to_length_cuts_the_path() {
+        let path = fill(rect(0.0, 0.0, 10.0, 5.0));
+        let segments = path.segments_to_length(12.0);
+        assert_near(length(&segments), 12.0, 1e-4);
+        match segments.last() {
+            Some(PathEvent::End { last, close, .. }) => {
+                assert!(!close);
+                assert_near(last.y, 2.0, 1e-4);
+            },
+            other => panic!("unexpected last event {:?}", other),
+        }
+    }
+
+    #[test]
+    fn contour_normals_point_out_of_the_fill() {
+        let mut segments = rect(0.0, 0.0, 10.0, 10.0);
+        segments.extend(rect(4.0, 4.0, 2.0, 2.0));
+        let contours = contours(&segments, 0.1);
+        assert_eq!(contours.len(), 2);
+
+        // The outer square's normals point away from its center, the hole's
+        // normals towards its center.
+        for (contour, sign) in contours.iter().zip([1.0, -1.0].iter()) {
+            for (i, normal) in contour.normals.iter().enumerate() {
+                let a = contour.points[i];
+                let b = contour.points[(i + 1) % contour.points.len()];
+                let from_center = a.lerp(b, 0.5) - point(5.0, 5.0);
+                assert!(from_center.dot(*normal) * sign > 0.0);
+            }
+        }
+    }
+}
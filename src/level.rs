@@ -0,0 +1,311 @@
+//! Use SVG files, e.g. drawn in Inkscape, as a level format.
+//!
+//! When enabled with [`SvgBuilder::import_level`](crate::svg::SvgBuilder::import_level),
+//! every top-level `<g inkscape:groupmode="layer">` becomes a [`SvgLayer`] and
+//! every element with at least one `data-*` or `inkscape:label` attribute
+//! becomes a [`SvgObject`]. When the SVG is spawned, both are spawned
+//! as child entities and the [`SvgObjectRegistry`] maps their attributes to user
+//! components.
+
+use std::collections::HashMap;
+
+use bevy::{
+    ecs::{entity::Entity, query::Added, system::{Commands, EntityCommands, Query, Res}},
+    math::{Vec2, Vec3},
+    transform::{components::{GlobalTransform, Transform}, hierarchy::BuildChildren},
+};
+use lyon_geom::Box2D;
+use lyon_tessellation::math::Rect;
+
+use crate::{
+    geometry::union,
+    metadata::{ElementAttributes, generated_id},
+    svg::{PathDescriptor, Svg},
+};
+
+/// Namespace of the `inkscape:*` attributes.
+pub(crate) const INKSCAPE_NS: &str = "http://www.inkscape.org/namespaces/inkscape";
+
+/// A top-level Inkscape layer of a SVG.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgLayer {
+    /// The `id` of the layer group.
+    pub id: String,
+    /// The `inkscape:label` of the layer, i.e. the name shown in Inkscape.
+    pub label: String,
+    /// Index of the layer in document order, the bottom-most layer is `0`.
+    pub index: usize,
+    /// Z offset of the layer entity relative to the SVG entity.
+    pub depth: f32,
+}
+
+/// An element of a SVG that was marked with custom attributes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SvgObject {
+    /// The `id` of the element, empty if it has none.
+    pub id: String,
+    /// The `inkscape:label` of the element, if any.
+    pub label: Option<String>,
+    /// Index of the [`SvgLayer`] containing the element, if any.
+    pub layer: Option<usize>,
+    /// The custom attributes of the element, i.e. `class`, all `data-*`
+    /// attributes and all attributes in foreign namespaces like `inkscape:label`.
+    pub attributes: HashMap<String, String>,
+    /// Bounds of the element's geometry, in the local coordinate space of
+    /// the SVG mesh. Empty for elements without geometry.
+    pub bounds: Rect,
+    /// The `id` of the element in the usvg tree, generated for elements
+    /// without one.
+    pub(crate) key: String,
+}
+
+impl SvgObject {
+    /// Center of the element's bounds, which is also the translation of the
+    /// spawned object entity relative to the SVG.
+    pub fn position(&self) -> Vec2 {
+        let center = self.bounds.center();
+        Vec2::new(center.x, center.y)
+    }
+}
+
+type ObjectHandler = Box<dyn Fn(&str, &SvgObject, &mut EntityCommands<'_, '_>) + Send + Sync>;
+
+/// Resource mapping attributes of [`SvgObject`]s to user components.
+///
+/// ```ignore
+/// fn setup(mut registry: ResMut<SvgObjectRegistry>) {
+///     registry.register("data-kind", |value, _object, entity| match value {
+///         "spawn" => { entity.insert(SpawnPoint); },
+///         "trigger" => { entity.insert(Trigger); },
+///         _ => {},
+///     });
+/// }
+/// ```
+#[derive(Default)]
+pub struct SvgObjectRegistry {
+    handlers: Vec<(String, ObjectHandler)>,
+}
+
+impl SvgObjectRegistry {
+    /// Register a handler that gets called for every spawned [`SvgObject`]
+    /// which has the given attribute, with the value of the attribute, the
+    /// object and the commands of the object entity.
+    pub fn register<F>(&mut self, attribute: &str, handler: F) -> &mut Self
+    where
+        F: Fn(&str, &SvgObject, &mut EntityCommands<'_, '_>) + Send + Sync + 'static,
+    {
+        self.handlers.push((attribute.to_string(), Box::new(handler)));
+        self
+    }
+
+    fn apply(&self, object: &SvgObject, entity: &mut EntityCommands<'_, '_>) {
+        for (attribute, handler) in self.handlers.iter() {
+            if let Some(value) = object.attributes.get(attribute) {
+                handler(value, object, entity);
+            }
+        }
+    }
+}
+
+/// Layers and marked elements found in the XML of a SVG.
+pub(crate) struct LevelElements {
+    pub(crate) layers: Vec<SvgLayer>,
+    /// Marked elements without their bounds, which are only known after the
+    /// paths have been converted.
+    pub(crate) objects: Vec<SvgObject>,
+}
+
+/// Find the layers and marked elements in the XML of a SVG.
+pub(crate) fn parse_level(
+    doc: &roxmltree::Document<'_>,
+    attributes: &ElementAttributes,
+    layer_depth: f32,
+) -> LevelElements {
+    let root = doc.root_element();
+
+    let mut layers = Vec::new();
+    for node in root.children().filter(roxmltree::Node::is_element) {
+        if node.tag_name().name() == "g" && node.attribute((INKSCAPE_NS, "groupmode")) == Some("layer") {
+            if let Some(id) = node.attribute("id") {
+                let index = layers.len();
+                layers.push(SvgLayer {
+                    id: id.to_string(),
+                    label: node.attribute((INKSCAPE_NS, "label")).unwrap_or(id).to_string(),
+                    index,
+                    depth: index as f32 * layer_depth,
+                });
+            }
+        }
+    }
+
+    let mut objects = Vec::new();
+    // Enumerated like the elements that get a generated `id`.
+    for (index, node) in root.descendants().filter(roxmltree::Node::is_element).enumerate() {
+        let id = node.attribute("id").unwrap_or_default();
+        if layers.iter().any(|layer| layer.id == id) {
+            continue;
+        }
+
+        let is_marked = node.attributes().iter().any(|attr| match attr.namespace() {
+            None => attr.name().starts_with("data-"),
+            Some(ns) => ns == INKSCAPE_NS && attr.name() == "label",
+        });
+        if !is_marked {
+            continue;
+        }
+
+        let layer = node.ancestors()
+            .filter_map(|ancestor| ancestor.attribute("id"))
+            .find_map(|ancestor_id| layers.iter().position(|layer| layer.id == ancestor_id));
+
+        let key = if id.is_empty() { generated_id(index) } else { id.to_string() };
+        objects.push(SvgObject {
+            id: id.to_string(),
+            label: node.attribute((INKSCAPE_NS, "label")).map(str::to_string),
+            layer,
+            attributes: attributes.get(&key).cloned().unwrap_or_default(),
+            bounds: Rect::zero(),
+            key,
+        });
+    }
+
+    LevelElements { layers, objects }
+}
+
+/// Extend the bounds of the elements with the given usvg ids by the bounds
+/// of `path`.
+pub(crate) fn extend_bounds(bounds: &mut HashMap<String, Box2D<f32>>, ids: &[String], path: &PathDescriptor) {
+    let path_bounds = match path.stroke_bounds() {
+        Some(path_bounds) => path_bounds.to_box2d(),
+        None => return,
+    };
+    for id in ids {
+        let extended = match bounds.get(id) {
+            Some(&element) => union(vec![element, path_bounds].into_iter()).unwrap_or(path_bounds),
+            None => path_bounds,
+        };
+        bounds.insert(id.clone(), extended);
+    }
+}
+
+/// Bevy system which spawns the [`SvgLayer`]s and [`SvgObject`]s of newly
+/// spawned SVGs as child entities.
+pub(crate) fn svg_level_spawner(
+    mut commands: Commands,
+    registry: Res<SvgObjectRegistry>,
+    query: Query<(Entity, &Svg), Added<Svg>>,
+) {
+    for (entity, svg) in query.iter() {
+        if svg.layers.is_empty() && svg.objects.is_empty() {
+            continue;
+        }
+
+        commands.entity(entity).with_children(|parent| {
+            for layer in svg.layers.iter() {
+                parent.spawn_bundle((
+                    layer.clone(),
+                    Transform::from_translation(Vec3::new(0.0, 0.0, layer.depth)),
+                    GlobalTransform::default(),
+                ))
+                .with_children(|parent| {
+                    for object in svg.objects.iter().filter(|o| o.layer == Some(layer.index)) {
+                        let mut entity = parent.spawn_bundle(object_bundle(object));
+                        registry.apply(object, &mut entity);
+                    }
+                });
+            }
+
+            for object in svg.objects.iter().filter(|o| o.layer.is_none()) {
+                let mut entity = parent.spawn_bundle(object_bundle(object));
+                registry.apply(object, &mut entity);
+            }
+        });
+    }
+}
+
+fn object_bundle(object: &SvgObject) -> (SvgObject, Transform, GlobalTransform) {
+    (
+        object.clone(),
+        Transform::from_translation(object.position().extend(0.0)),
+        GlobalTransform::default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{svg::SvgBuilder, test_util::assert_near};
+
+    const LEVEL: &str = r#"<svg xmlns="http://www.w3.org/2000/svg"
+            xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape" width="100" height="100">
+        <g id="background" inkscape:groupmode="layer" inkscape:label="Background">
+            <rect width="100" height="100" fill="blue"/>
+        </g>
+        <g id="layer2" inkscape:groupmode="layer">
+            <rect id="spawn" x="10" y="20" width="10" height="10" data-kind="spawn"/>
+            <g id="door" inkscape:label="Door"><rect x="50" y="50" width="10" height="20"/></g>
+            <rect id="plain" width="1" height="1"/>
+        </g>
+        <g id="nested" inkscape:groupmode="layer"/>
+    </svg>"#;
+
+    #[test]
+    fn layers_and_objects() {
+        let svg = SvgBuilder::from_bytes(LEVEL.as_bytes(), "level.svg").import_level(10.0).load().unwrap();
+
+        let layers: Vec<(&str, &str, f32)> = svg.layers.iter()
+            .map(|layer| (layer.id.as_str(), layer.label.as_str(), layer.depth))
+            .collect();
+        assert_eq!(layers, vec![("background", "Background", 0.0), ("layer2", "layer2", 10.0), ("nested", "nested", 20.0)]);
+
+        let ids: Vec<&str> = svg.objects.iter().map(|object| object.id.as_str()).collect();
+        assert_eq!(ids, vec!["spawn", "door"]);
+
+        let spawn = &svg.objects[0];
+        assert_eq!(spawn.layer, Some(1));
+        assert_eq!(spawn.label, None);
+        assert_eq!(spawn.attributes.get("data-kind").map(String::as_str), Some("spawn"));
+        assert_eq!(spawn.position(), Vec2::new(15.0, 25.0));
+
+        let door = &svg.objects[1];
+        assert_eq!(door.label.as_deref(), Some("Door"));
+        // The bounds of a group cover the paths inside it.
+        assert_near(door.bounds.height(), 20.0, 1e-5);
+        assert_eq!(door.position(), Vec2::new(55.0, 60.0));
+    }
+
+    #[test]
+    fn objects_without_id() {
+        let text = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <rect x="30" y="40" width="4" height="6" data-kind="coin"/>
+            <g data-kind="chest"><rect x="60" y="60" width="10" height="10"/></g>
+        </svg>"#;
+        let svg = SvgBuilder::from_bytes(text.as_bytes(), "level.svg").import_level(10.0).load().unwrap();
+        assert_eq!(svg.objects.len(), 2);
+
+        let coin = &svg.objects[0];
+        assert_eq!(coin.id, "");
+        assert_eq!(coin.attributes.get("data-kind").map(String::as_str), Some("coin"));
+        assert_eq!(coin.position(), Vec2::new(32.0, 43.0));
+
+        let chest = &svg.objects[1];
+        assert_eq!(chest.attributes.get("data-kind").map(String::as_str), Some("chest"));
+        assert_eq!(chest.position(), Vec2::new(65.0, 65.0));
+    }
+
+    #[test]
+    fn paths_get_the_depth_of_their_layer() {
+        let svg = SvgBuilder::from_bytes(LEVEL.as_bytes(), "level.svg").import_level(10.0).load().unwrap();
+        let layers: Vec<Option<usize>> = svg.paths.iter().map(|path| path.layer).collect();
+        assert_eq!(layers, vec![Some(0), Some(1), Some(1), Some(1)]);
+        let z: Vec<f32> = svg.paths.iter().map(|path| path.z).collect();
+        assert_eq!(z, vec![0.0, 10.0, 10.0, 10.0]);
+    }
+
+    #[test]
+    fn no_level_without_import() {
+        let svg = SvgBuilder::from_bytes(LEVEL.as_bytes(), "level.svg").load().unwrap();
+        assert!(svg.layers.is_empty() && svg.objects.is_empty());
+        assert!(svg.paths.iter().all(|path| path.layer.is_none()));
+    }
+}
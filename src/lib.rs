@@ -27,15 +27,61 @@
     clippy::cargo
 )]
 
+mod animation;
+mod anti_alias;
+mod batch;
+mod boolean;
 mod bundle;
+#[cfg(feature = "collider")]
+mod collider;
+mod extrude;
+mod filter;
+mod geometry;
+mod level;
+mod limits;
+mod loader;
+mod lod;
+#[cfg(feature = "lottie")]
+mod lottie;
+mod material;
+mod metadata;
+mod morph;
+mod nine_slice;
+mod offset;
+mod pattern;
 mod plugin;
+mod raster;
+mod reveal;
+mod sampler;
 mod svg;
+#[cfg(test)]
+mod test_util;
+mod ui;
 mod vertex_buffer;
 
 /// Import this module as `use bevy_svg::prelude::*` to get
 /// convenient imports.
 pub mod prelude {
-    pub use crate::{bundle::SvgBundle, plugin::SvgPlugin, svg::{SvgBuilder, Origin}};
+    pub use crate::{
+        animation::{
+            AnimationEffect, AnimationTiming, MotionRotate, PaintTarget, SvgAnimation,
+            SvgAnimationPlayer, TransformKind,
+        },
+        batch::{SvgBatched, SvgTint}, boolean::PathOp, bundle::{SvgAssetBundle, SvgBundle, SvgUiBundle}, extrude::SvgExtrusion, filter::SvgFilter,
+        geometry::{PathBounds, SvgBounds},
+        level::{SvgLayer, SvgObject, SvgObjectRegistry},
+        limits::{SvgLimit, SvgLimitError, SvgLimits}, loader::{SvgAssetLoader, SvgLoaderSettings}, lod::SvgLod,
+        material::SvgMaterialPlugin,
+        morph::SvgMorph, nine_slice::NineSlice, pattern::SvgPattern, plugin::{SVG_VERTEX_SHADER, SvgPlugin}, reveal::SvgStrokeReveal,
+        sampler::{ShapeSample, SvgShapeSampler},
+        svg::{Svg, SvgBuilder, Origin, UvMode}, ui::SvgUiFit,
+    };
+    #[cfg(feature = "collider")]
+    pub use crate::collider::{
+        ColliderKind, ColliderOptions, ColliderShape, ColliderTarget, SvgCollider,
+    };
+    #[cfg(feature = "lottie")]
+    pub use crate::lottie::Lottie;
     pub use lyon_tessellation::{
         FillOptions, FillRule, LineCap, LineJoin, Orientation, StrokeOptions,
     };
@@ -0,0 +1,302 @@
+//! Limits for loading untrusted SVGs.
+
+use lyon_tessellation::{FillTessellator, StrokeTessellator};
+
+use crate::{plugin::tessellate_path, svg::PathDescriptor, vertex_buffer::VertexBuffers};
+
+/// Number of parameters of each path command.
+const PATH_COMMANDS: [(char, usize); 10] = [
+    ('m', 2), ('l', 2), ('h', 1), ('v', 1), ('c', 6), ('s', 4), ('q', 4), ('t', 2), ('a', 7), ('z', 0),
+];
+
+/// Limits that a SVG has to stay within to be loaded, see
+/// [`SvgBuilder::limits`](crate::svg::SvgBuilder::limits). Every limit is
+/// disabled when `None`, which is the default.
+///
+/// ```ignore
+/// let limits = SvgLimits {
+///     max_bytes: Some(256 * 1024),
+///     max_nodes: Some(10_000),
+///     max_path_segments: Some(10_000),
+///     max_vertices: Some(100_000),
+///     max_raster_pixels: Some(1024 * 1024),
+///     max_depth: Some(32),
+/// };
+/// let bundle = SvgBuilder::from_bytes(&emblem, "emblem").limits(limits).build()?;
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SvgLimits {
+    /// Maximum size of the document in bytes, after decompressing `.svgz` data.
+    pub max_bytes: Option<usize>,
+    /// Maximum number of XML elements.
+    pub max_nodes: Option<usize>,
+    /// Maximum number of segments of a single path.
+    pub max_path_segments: Option<usize>,
+    /// Maximum number of vertices of all tessellated paths, including the
+    /// paths of filtered groups, of pattern-painted paths and of the pattern
+    /// tiles. Checking this limit tessellates the SVG once while building it.
+    pub max_vertices: Option<usize>,
+    /// Maximum number of pixels of all raster images together, i.e. of the
+    /// pattern tiles and of the images of filtered groups.
+    pub max_raster_pixels: Option<usize>,
+    /// Maximum nesting depth of XML elements.
+    pub max_depth: Option<usize>,
+}
+
+/// The limit of a [`SvgLimits`] that was exceeded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SvgLimit {
+    /// [`SvgLimits::max_bytes`]
+    Bytes,
+    /// [`SvgLimits::max_nodes`]
+    Nodes,
+    /// [`SvgLimits::max_path_segments`]
+    PathSegments,
+    /// [`SvgLimits::max_vertices`]
+    Vertices,
+    /// [`SvgLimits::max_raster_pixels`]
+    RasterPixels,
+    /// [`SvgLimits::max_depth`]
+    Depth,
+}
+
+/// Error returned when a SVG exceeds one of its [`SvgLimits`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SvgLimitError {
+    /// The exceeded limit.
+    pub limit: SvgLimit,
+    /// The maximum allowed by the limit.
+    pub max: usize,
+}
+
+impl std::fmt::Display for SvgLimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let what = match self.limit {
+            SvgLimit::Bytes => "bytes",
+            SvgLimit::Nodes => "elements",
+            SvgLimit::PathSegments => "segments in a single path",
+            SvgLimit::Vertices => "tessellated vertices",
+            SvgLimit::RasterPixels => "raster image pixels",
+            SvgLimit::Depth => "levels of nested elements",
+        };
+        write!(f, "SVG exceeds the limit of {} {}", self.max, what)
+    }
+}
+
+impl std::error::Error for SvgLimitError {}
+
+impl SvgLimits {
+    /// Check a single value against one of the limits.
+    pub(crate) fn check(max: Option<usize>, limit: SvgLimit, value: usize) -> Result<(), SvgLimitError> {
+        match max {
+            Some(max) if value > max => Err(SvgLimitError { limit, max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the number of elements, their nesting depth and the number of
+    /// segments of the `path`, `polyline` and `polygon` elements, before usvg
+    /// parses them.
+    pub(crate) fn check_xml(&self, doc: &roxmltree::Document<'_>) -> Result<(), SvgLimitError> {
+        if self.max_nodes.is_none() && self.max_depth.is_none() && self.max_path_segments.is_none() {
+            return Ok(());
+        }
+
+        let mut nodes = 0;
+        for node in doc.descendants().filter(roxmltree::Node::is_element) {
+            nodes += 1;
+            SvgLimits::check(self.max_nodes, SvgLimit::Nodes, nodes)?;
+            if self.max_depth.is_some() {
+                // The document root is not an element.
+                let depth = node.ancestors().count() - 1;
+                SvgLimits::check(self.max_depth, SvgLimit::Depth, depth)?;
+            }
+            if self.max_path_segments.is_some() {
+                let segments = match node.tag_name().name() {
+                    "path" => node.attribute("d").map_or(0, path_data_segments),
+                    "polyline" | "polygon" => node.attribute("points").map_or(0, |points| count_numbers(points) / 2),
+                    _ => 0,
+                };
+                SvgLimits::check(self.max_path_segments, SvgLimit::PathSegments, segments)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Start counting the tessellated vertices and raster pixels of a SVG.
+    pub(crate) fn counter(&self) -> LimitCounter {
+        LimitCounter { limits: *self, vertices: 0, pixels: 0 }
+    }
+}
+
+/// Running totals of the limits that span the whole SVG.
+pub(crate) struct LimitCounter {
+    limits: SvgLimits,
+    vertices: usize,
+    pixels: usize,
+}
+
+impl LimitCounter {
+    /// Add vertices that got tessellated anyway.
+    pub(crate) fn add_vertices(&mut self, vertices: usize) -> Result<(), SvgLimitError> {
+        self.vertices += vertices;
+        SvgLimits::check(self.limits.max_vertices, SvgLimit::Vertices, self.vertices)
+    }
+
+    /// Tessellate the paths and add their vertices. Nothing gets tessellated
+    /// if the number of vertices isn't limited.
+    pub(crate) fn add_paths<'p>(&mut self, paths: impl IntoIterator<Item = &'p PathDescriptor>) -> Result<(), SvgLimitError> {
+        if self.limits.max_vertices.is_none() {
+            return Ok(());
+        }
+
+        let mut fill_tess = FillTessellator::new();
+        let mut stroke_tess = StrokeTessellator::new();
+        for path in paths {
+            let mut buffers = VertexBuffers::new();
+            tessellate_path(&mut fill_tess, &mut stroke_tess, path, path.segments.iter().cloned(), &mut buffers);
+            self.add_vertices(buffers.vertices.len())?;
+        }
+
+        Ok(())
+    }
+
+    /// Add a raster image, before allocating it.
+    pub(crate) fn add_image(&mut self, width: usize, height: usize) -> Result<(), SvgLimitError> {
+        self.pixels += width * height;
+        SvgLimits::check(self.limits.max_raster_pixels, SvgLimit::RasterPixels, self.pixels)
+    }
+}
+
+/// Number of segments of SVG path data, counting the implicit repetitions of a
+/// command with several sets of parameters.
+fn path_data_segments(d: &str) -> usize {
+    let mut segments = 0;
+    let mut rest = d;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_alphabetic() && c != 'e' && c != 'E') {
+        let command = rest[start..].chars().next().unwrap_or_default().to_ascii_lowercase();
+        rest = &rest[start + 1..];
+        let end = rest.find(|c: char| c.is_ascii_alphabetic() && c != 'e' && c != 'E').unwrap_or(rest.len());
+        let parameters = PATH_COMMANDS.iter().find(|(c, _)| *c == command).map_or(0, |(_, count)| *count);
+        segments += match parameters {
+            0 => 1,
+            parameters => (count_numbers(&rest[..end]) / parameters).max(1),
+        };
+        rest = &rest[end..];
+    }
+    segments
+}
+
+/// Number of numbers in a list separated by whitespace, commas or signs.
+fn count_numbers(list: &str) -> usize {
+    let mut count = 0;
+    let mut in_number = false;
+    let mut has_dot = false;
+    let mut previous = ' ';
+    for c in list.chars() {
+        let starts_number = match c {
+            '0'..='9' => !in_number,
+            '.' => !in_number || has_dot,
+            '+' | '-' => previous != 'e' && previous != 'E',
+            'e' | 'E' => false,
+            _ => {
+                in_number = false;
+                previous = c;
+                continue;
+            },
+        };
+        if starts_number {
+            count += 1;
+            has_dot = false;
+        }
+        in_number = true;
+        has_dot |= c == '.';
+        previous = c;
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::svg::SvgBuilder;
+
+    const PATTERN: &str = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+        <defs>
+            <pattern id="dots" width="10" height="10" patternUnits="userSpaceOnUse">
+                <circle cx="5" cy="5" r="3"/>
+            </pattern>
+            <filter id="blur"><feGaussianBlur stdDeviation="2"/></filter>
+        </defs>
+        <rect width="100" height="100" fill="url(#dots)"/>
+        <g filter="url(#blur)"><rect x="10" y="10" width="20" height="20"/></g>
+    </svg>"#;
+
+    fn limit_error(text: &str, limits: SvgLimits) -> Option<SvgLimitError> {
+        match SvgBuilder::from_bytes(text.as_bytes(), "test.svg").limits(limits).load() {
+            Ok(_) => None,
+            Err(e) => Some(*e.downcast::<SvgLimitError>().expect("not a limit error")),
+        }
+    }
+
+    #[test]
+    fn counts_path_segments() {
+        assert_eq!(path_data_segments("M0 0 L10 0 L10 10 Z"), 4);
+        assert_eq!(path_data_segments("M0,0 10,0 10,10 20,20"), 4);
+        assert_eq!(path_data_segments("m0-1.5.5e-2-3 c1 2 3 4 5 6 1 2 3 4 5 6"), 4);
+        assert_eq!(path_data_segments(""), 0);
+        assert_eq!(count_numbers("1.5.5-2e-3,4"), 4);
+    }
+
+    #[test]
+    fn no_limits_by_default() {
+        assert_eq!(limit_error(PATTERN, SvgLimits::default()), None);
+    }
+
+    #[test]
+    fn nodes_and_depth() {
+        let nested = r#"<svg xmlns="http://www.w3.org/2000/svg"><g><g><rect width="1" height="1"/></g></g></svg>"#;
+        let error = limit_error(nested, SvgLimits { max_nodes: Some(3), ..Default::default() });
+        assert_eq!(error, Some(SvgLimitError { limit: SvgLimit::Nodes, max: 3 }));
+        let error = limit_error(nested, SvgLimits { max_depth: Some(2), ..Default::default() });
+        assert_eq!(error, Some(SvgLimitError { limit: SvgLimit::Depth, max: 2 }));
+        assert_eq!(limit_error(nested, SvgLimits { max_nodes: Some(4), max_depth: Some(4), ..Default::default() }), None);
+    }
+
+    #[test]
+    fn bytes() {
+        let error = limit_error(PATTERN, SvgLimits { max_bytes: Some(100), ..Default::default() });
+        assert_eq!(error, Some(SvgLimitError { limit: SvgLimit::Bytes, max: 100 }));
+    }
+
+    #[test]
+    fn path_segments_before_parsing() {
+        let points: Vec<String> = (0..100).map(|i| format!("{},{}", i, i % 2)).collect();
+        let text = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><polyline points="{}" stroke="black"/></svg>"#,
+            points.join(" "),
+        );
+        let doc = crate::metadata::parse_xml(&text).unwrap();
+        let limits = SvgLimits { max_path_segments: Some(50), ..Default::default() };
+        assert_eq!(limits.check_xml(&doc), Err(SvgLimitError { limit: SvgLimit::PathSegments, max: 50 }));
+        assert_eq!(SvgLimits { max_path_segments: Some(100), ..limits }.check_xml(&doc), Ok(()));
+    }
+
+    #[test]
+    fn vertices_include_patterns_and_filters() {
+        let plain = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <rect width="100" height="100"/>
+        </svg>"#;
+        let limits = SvgLimits { max_vertices: Some(8), ..Default::default() };
+        assert_eq!(limit_error(plain, limits), None);
+        // The pattern-painted rectangle and the circle of its tile.
+        assert_eq!(limit_error(PATTERN, limits), Some(SvgLimitError { limit: SvgLimit::Vertices, max: 8 }));
+    }
+
+    #[test]
+    fn raster_pixels() {
+        let limits = SvgLimits { max_raster_pixels: Some(100), ..Default::default() };
+        assert_eq!(limit_error(PATTERN, limits), Some(SvgLimitError { limit: SvgLimit::RasterPixels, max: 100 }));
+    }
+}
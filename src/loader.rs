@@ -0,0 +1,109 @@
+//! Loading of SVG files as Bevy assets.
+
+use bevy::{
+    asset::{AssetLoader, Assets, Handle, LoadContext, LoadedAsset},
+    ecs::{entity::Entity, query::Without, system::{Commands, Query, Res}, world::{FromWorld, World}},
+    math::Vec3,
+    transform::components::Transform,
+    utils::BoxedFuture,
+};
+
+use crate::{limits::SvgLimits, svg::{Origin, Svg, SvgBuilder}};
+
+/// Settings of the [`SvgAssetLoader`].
+///
+/// Insert them as a resource before adding the [`SvgPlugin`](crate::plugin::SvgPlugin),
+/// the loader reads them once when it is created.
+///
+/// ```ignore
+/// App::build()
+///     .insert_resource(SvgLoaderSettings { origin: Origin::Center, ..Default::default() })
+///     .add_plugin(SvgPlugin)
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SvgLoaderSettings {
+    /// Origin of the loaded SVGs, see [`SvgBuilder::origin`].
+    pub origin: Origin,
+    /// Limits checked while loading, see [`SvgBuilder::limits`].
+    pub limits: SvgLimits,
+}
+
+/// Loads `.svg` and `.svgz` files as [`Svg`] assets, with the
+/// [`SvgLoaderSettings`] resource or the default settings.
+pub struct SvgAssetLoader {
+    settings: SvgLoaderSettings,
+}
+
+impl FromWorld for SvgAssetLoader {
+    fn from_world(world: &mut World) -> Self {
+        let settings = world.get_resource::<SvgLoaderSettings>().copied().unwrap_or_default();
+        SvgAssetLoader { settings }
+    }
+}
+
+impl AssetLoader for SvgAssetLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let name = load_context.path().file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let svg = SvgBuilder::from_bytes(bytes, &name)
+                .origin(self.settings.origin)
+                .limits(self.settings.limits)
+                .load()
+                .map_err(|e| anyhow::anyhow!("Could not load SVG {}: {}", name, e))?;
+            load_context.set_default_asset(LoadedAsset::new(svg));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["svg", "svgz"]
+    }
+}
+
+/// Bevy system which inserts the [`Svg`] of a [`SvgAssetBundle`](crate::bundle::SvgAssetBundle)
+/// once the asset is loaded, after which it gets tessellated like any other
+/// [`SvgBundle`](crate::bundle::SvgBundle). Like [`SvgBuilder::build`], the
+/// entity is moved so that the [`Origin`] of the SVG is at its position.
+pub(crate) fn svg_asset_spawner(
+    mut commands: Commands,
+    svgs: Res<Assets<Svg>>,
+    mut query: Query<(Entity, &Handle<Svg>, &mut Transform), Without<Svg>>,
+) {
+    for (entity, handle, mut transform) in query.iter_mut() {
+        if let Some(svg) = svgs.get(handle) {
+            if svg.origin == Origin::Center {
+                // The y scale is negative to flip the y-axis.
+                transform.translation += Vec3::new(
+                    -svg.width as f32 * transform.scale.x / 2.0,
+                    -svg.height as f32 * transform.scale.y / 2.0,
+                    0.0,
+                );
+            }
+            commands.entity(entity).insert_bundle((svg.bounds(), svg.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_are_read_from_the_world() {
+        let mut world = World::default();
+        assert_eq!(SvgAssetLoader::from_world(&mut world).settings, SvgLoaderSettings::default());
+
+        let settings = SvgLoaderSettings {
+            origin: Origin::Center,
+            limits: SvgLimits { max_nodes: Some(10), ..Default::default() },
+        };
+        world.insert_resource(settings);
+        assert_eq!(SvgAssetLoader::from_world(&mut world).settings, settings);
+    }
+}
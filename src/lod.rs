@@ -0,0 +1,172 @@
+//! Resolution independent level of detail.
+//!
+//! SVGs are tessellated once with a fixed tolerance, so curves get faceted
+//! when the camera zooms in far enough. A [`SvgLod`] watches the scale at which
+//! the SVG appears on screen and tessellates it again with a matching
+//! tolerance whenever the scale crosses a power of two. The meshes of the last
+//! few levels are kept, so that zooming back and forth only swaps meshes.
+
+use bevy::{
+    asset::{Assets, Handle},
+    ecs::{entity::Entity, system::{Commands, Query, ResMut}},
+    render::{
+        camera::{Camera, OrthographicProjection},
+        mesh::Mesh,
+        render_graph::base::camera::CAMERA_2D,
+    },
+    transform::components::GlobalTransform,
+};
+use lyon_tessellation::{FillTessellator, StrokeTessellator};
+
+use crate::{Convert, plugin::{TessellationOptions, tessellate_svg}, svg::Svg};
+
+/// Component which enables level of detail for a [`Svg`].
+///
+/// Only orthographic cameras are supported. The scale on screen is taken from
+/// the projection and the `GlobalTransform`s of the camera and the SVG entity.
+/// Components that replace the mesh of a SVG, like [`SvgStrokeReveal`](crate::reveal::SvgStrokeReveal),
+/// shouldn't be combined with it. The levels only cover [`Svg::paths`], the
+/// meshes of filtered groups and pattern-painted paths keep their tolerance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SvgLod {
+    /// Maximum distance between a curve and its tessellation, in pixels.
+    pub tolerance: f32,
+    /// Lowest level, level `n` is used from a scale of `2^n` pixels per SVG unit.
+    pub min_level: i32,
+    /// Highest level.
+    pub max_level: i32,
+    /// Number of meshes to keep around per SVG.
+    pub cached_levels: usize,
+}
+
+impl Default for SvgLod {
+    fn default() -> Self {
+        SvgLod {
+            tolerance: 0.25,
+            min_level: -4,
+            max_level: 8,
+            cached_levels: 4,
+        }
+    }
+}
+
+impl SvgLod {
+    /// The level to use at the given scale, in pixels per SVG unit.
+    pub fn level(&self, scale: f32) -> i32 {
+        if scale <= 0.0 {
+            return self.min_level;
+        }
+        (scale.log2().floor() as i32).max(self.min_level).min(self.max_level)
+    }
+
+    /// The tolerance in SVG units used to tessellate the given level.
+    pub fn level_tolerance(&self, level: i32) -> f32 {
+        self.tolerance / 2.0_f32.powi(level)
+    }
+}
+
+/// The meshes of the recently used levels of a [`SvgLod`].
+pub(crate) struct SvgLodCache {
+    level: i32,
+    meshes: Vec<(i32, Handle<Mesh>)>,
+}
+
+/// Bevy system which swaps the mesh of SVGs with a [`SvgLod`] when their scale
+/// on screen crosses a level.
+pub(crate) fn svg_lod(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut fill_tess: ResMut<FillTessellator>,
+    mut stroke_tess: ResMut<StrokeTessellator>,
+    cameras: Query<(&Camera, &OrthographicProjection, &GlobalTransform)>,
+    mut query: Query<(Entity, &Svg, &SvgLod, &GlobalTransform, &mut Handle<Mesh>, Option<&mut SvgLodCache>)>,
+) {
+    let camera = cameras.iter()
+        .find(|(camera, _, _)| camera.name.as_deref() == Some(CAMERA_2D))
+        .or_else(|| cameras.iter().next());
+    let camera_scale = match camera {
+        Some((_, projection, transform)) => projection.scale * max_scale(transform),
+        None => return,
+    };
+    if camera_scale <= 0.0 {
+        return;
+    }
+
+    for (entity, svg, lod, transform, mut mesh, cache) in query.iter_mut() {
+        let level = lod.level(max_scale(transform) / camera_scale);
+
+        let mut cache = cache;
+        let mut new_cache = None;
+        let cache = match cache.as_deref_mut() {
+            Some(cache) if cache.level == level => continue,
+            Some(cache) => cache,
+            None => new_cache.get_or_insert(SvgLodCache { level, meshes: Vec::new() }),
+        };
+
+        let handle = match cache.meshes.iter().find(|(l, _)| *l == level) {
+            Some((_, handle)) => handle.clone(),
+            None => {
+                let tolerance = lod.level_tolerance(level);
+                let buffers = tessellate_svg(
+                    &mut fill_tess, &mut stroke_tess, svg,
+                    TessellationOptions { tolerance: Some(tolerance), ..TessellationOptions::of(svg) },
+                );
+
+                let handle = meshes.add(buffers.convert());
+                cache.meshes.push((level, handle.clone()));
+                if cache.meshes.len() > lod.cached_levels.max(1) {
+                    // Drop the level furthest away from the current one.
+                    if let Some(furthest) = (0..cache.meshes.len())
+                        .max_by_key(|&i| (cache.meshes[i].0 - level).abs())
+                    {
+                        cache.meshes.swap_remove(furthest);
+                    }
+                }
+                handle
+            },
+        };
+
+        cache.level = level;
+        *mesh = handle;
+
+        if let Some(new_cache) = new_cache {
+            commands.entity(entity).insert(new_cache);
+        }
+    }
+}
+
+fn max_scale(transform: &GlobalTransform) -> f32 {
+    transform.scale.x.abs().max(transform.scale.y.abs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::{math::Vec3, transform::components::Transform};
+
+    #[test]
+    fn level_follows_powers_of_two() {
+        let lod = SvgLod::default();
+        assert_eq!(lod.level(1.0), 0);
+        assert_eq!(lod.level(1.9), 0);
+        assert_eq!(lod.level(2.0), 1);
+        assert_eq!(lod.level(0.3), -2);
+        assert_eq!(lod.level(1e6), lod.max_level);
+        assert_eq!(lod.level(1e-6), lod.min_level);
+        assert_eq!(lod.level(0.0), lod.min_level);
+    }
+
+    #[test]
+    fn tolerance_is_given_in_pixels() {
+        let lod = SvgLod { tolerance: 0.5, ..Default::default() };
+        assert_eq!(lod.level_tolerance(0), 0.5);
+        assert_eq!(lod.level_tolerance(2), 0.125);
+        assert_eq!(lod.level_tolerance(-1), 1.0);
+    }
+
+    #[test]
+    fn scale_ignores_the_flipped_axis() {
+        let transform = GlobalTransform::from(Transform::from_scale(Vec3::new(2.0, -3.0, 1.0)));
+        assert_eq!(max_scale(&transform), 3.0);
+    }
+}
@@ -0,0 +1,817 @@
+//! Import of Lottie JSON animations, as exported from After Effects with
+//! Bodymovin.
+//!
+//! Shape layers are converted into the same [`PathDescriptor`]s as SVG paths,
+//! so they are tessellated and drawn exactly like a [`Svg`]. Supported are
+//! paths, ellipses, rectangles, fills, strokes, groups, transforms, trim paths,
+//! layer parenting and keyframes with bézier easing. Other layer types like
+//! images, texts and precompositions, as well as masks, mattes, expressions
+//! and effects are ignored.
+//!
+//! ```ignore
+//! let lottie = Lottie::from_file("assets/loader.json")?;
+//! commands.spawn_bundle(lottie.bundle())
+//!     .insert(lottie)
+//!     .insert(SvgAnimationPlayer::default());
+//! ```
+
+use std::{collections::HashMap, io::Read, path::Path};
+
+use bevy::{
+    asset::{Assets, Handle},
+    core::Time,
+    ecs::system::{Query, Res, ResMut},
+    math::{Vec2, Vec3},
+    render::{color::Color, mesh::Mesh},
+};
+use lyon_svg::{parser::ViewBox, path::PathEvent};
+use lyon_tessellation::{
+    FillTessellator, LineCap, LineJoin, StrokeOptions, StrokeTessellator,
+    math::{Angle, Point, Transform, Vector, point, vector},
+};
+use serde_json::Value;
+
+use crate::{
+    Convert,
+    animation::SvgAnimationPlayer,
+    bundle::SvgBundle,
+    geometry::{length, trim},
+    nine_slice::NineSlice,
+    plugin::{TessellationOptions, mesh_order, tessellate_path_with},
+    svg::{DrawType, Origin, PathDescriptor, Svg},
+    vertex_buffer::VertexBuffers,
+};
+
+/// Layer type of shape layers, the only ones that get drawn.
+const SHAPE_LAYER: u64 = 4;
+
+/// Factor for the control points of cubic béziers approximating a quarter circle.
+const KAPPA: f32 = 0.552_284_8;
+
+/// A Lottie animation.
+///
+/// Insert it together with a [`SvgAnimationPlayer`] on the entity of its
+/// [`Lottie::bundle`] to play it back. The time of the player is in seconds.
+#[derive(Clone, Debug)]
+pub struct Lottie {
+    /// The name of the animation.
+    pub name: String,
+    /// Width of the animation.
+    pub width: f32,
+    /// Height of the animation.
+    pub height: f32,
+    /// Frames per second.
+    pub frame_rate: f32,
+    /// First frame of the animation.
+    pub in_point: f32,
+    /// Frame at which the animation ends.
+    pub out_point: f32,
+    layers: Vec<Layer>,
+    nine_slice: Option<NineSlice>,
+    anti_alias: Option<f32>,
+}
+
+#[derive(Clone, Debug)]
+struct Layer {
+    name: String,
+    kind: u64,
+    index: Option<i64>,
+    parent: Option<i64>,
+    in_point: f32,
+    out_point: f32,
+    transform: ShapeTransform,
+    shapes: Vec<Shape>,
+}
+
+#[derive(Clone, Debug)]
+enum Shape {
+    Group(Vec<Shape>),
+    Path(Property<Bezier>),
+    Ellipse { position: Property<Vec<f32>>, size: Property<Vec<f32>> },
+    Rect { position: Property<Vec<f32>>, size: Property<Vec<f32>> },
+    Fill { color: Property<Vec<f32>>, opacity: Property<Vec<f32>> },
+    Stroke {
+        color: Property<Vec<f32>>,
+        opacity: Property<Vec<f32>>,
+        width: Property<Vec<f32>>,
+        cap: LineCap,
+        join: LineJoin,
+        miter_limit: f32,
+    },
+    Trim { start: Property<Vec<f32>>, end: Property<Vec<f32>>, offset: Property<Vec<f32>> },
+    Transform(ShapeTransform),
+}
+
+/// Transform of a layer or group, angles in degrees and scale and opacity in
+/// percent.
+#[derive(Clone, Debug)]
+struct ShapeTransform {
+    anchor: Property<Vec<f32>>,
+    position: Property<Vec<f32>>,
+    scale: Property<Vec<f32>>,
+    rotation: Property<Vec<f32>>,
+    opacity: Property<Vec<f32>>,
+}
+
+/// A bézier path with tangents relative to their vertex.
+#[derive(Clone, Debug, Default)]
+struct Bezier {
+    closed: bool,
+    vertices: Vec<Point>,
+    in_tangents: Vec<Vector>,
+    out_tangents: Vec<Vector>,
+}
+
+#[derive(Clone, Debug)]
+enum Property<T> {
+    Static(T),
+    Animated(Vec<Keyframe<T>>),
+}
+
+#[derive(Clone, Debug)]
+struct Keyframe<T> {
+    time: f32,
+    start: Option<T>,
+    end: Option<T>,
+    hold: bool,
+    /// Control points of the easing curve, `[x1, y1, x2, y2]`.
+    easing: Option<[f32; 4]>,
+}
+
+trait Lerp: Clone {
+    fn lerp(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Lerp for Vec<f32> {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self.iter().zip(other.iter()).map(|(a, b)| a + (b - a) * t).collect()
+    }
+}
+
+impl Lerp for Bezier {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        if self.vertices.len() != other.vertices.len() {
+            return if t < 1.0 { self.clone() } else { other.clone() };
+        }
+
+        Bezier {
+            closed: self.closed,
+            vertices: self.vertices.iter().zip(other.vertices.iter()).map(|(a, b)| a.lerp(*b, t)).collect(),
+            in_tangents: self.in_tangents.iter().zip(other.in_tangents.iter()).map(|(a, b)| a.lerp(*b, t)).collect(),
+            out_tangents: self.out_tangents.iter().zip(other.out_tangents.iter()).map(|(a, b)| a.lerp(*b, t)).collect(),
+        }
+    }
+}
+
+impl<T: Lerp> Property<T> {
+    /// The value of the property at `frame`.
+    fn sample(&self, frame: f32) -> Option<T> {
+        let keys = match self {
+            Property::Static(value) => return Some(value.clone()),
+            Property::Animated(keys) => keys,
+        };
+
+        // Older exports leave out the start value of a keyframe, which then is
+        // the end value of the previous one.
+        let start_of = |i: usize| -> Option<T> {
+            keys[i].start.clone().or_else(|| i.checked_sub(1).and_then(|prev| keys[prev].end.clone()))
+        };
+
+        let i = keys.iter().rposition(|key| key.time <= frame).unwrap_or(0);
+        let key = &keys[i];
+        let start = start_of(i)?;
+        let next = match keys.get(i + 1) {
+            Some(next) if !key.hold && frame >= key.time => next,
+            _ => return Some(start),
+        };
+        let end = match key.end.clone().or_else(|| start_of(i + 1)) {
+            Some(end) => end,
+            None => return Some(start),
+        };
+
+        let duration = next.time - key.time;
+        let progress = if duration > 0.0 { (frame - key.time) / duration } else { 1.0 };
+        let progress = match key.easing {
+            Some(easing) => ease(easing, progress),
+            None => progress,
+        };
+
+        Some(start.lerp(&end, progress))
+    }
+}
+
+impl Property<Vec<f32>> {
+    /// The value of a single dimension of the property at `frame`.
+    fn value(&self, frame: f32, dimension: usize, default: f32) -> f32 {
+        self.sample(frame).and_then(|v| v.get(dimension).copied()).unwrap_or(default)
+    }
+}
+
+impl ShapeTransform {
+    fn matrix(&self, frame: f32) -> Transform {
+        Transform::translation(-self.anchor.value(frame, 0, 0.0), -self.anchor.value(frame, 1, 0.0))
+            .then_scale(self.scale.value(frame, 0, 100.0) / 100.0, self.scale.value(frame, 1, 100.0) / 100.0)
+            .then_rotate(Angle::degrees(self.rotation.value(frame, 0, 0.0)))
+            .then_translate(vector(self.position.value(frame, 0, 0.0), self.position.value(frame, 1, 0.0)))
+    }
+
+    fn opacity(&self, frame: f32) -> f32 {
+        self.opacity.value(frame, 0, 100.0) / 100.0
+    }
+}
+
+/// Evaluate a CSS like cubic bézier easing curve at `x`.
+fn ease([x1, y1, x2, y2]: [f32; 4], x: f32) -> f32 {
+    let bezier = |a: f32, b: f32, s: f32| {
+        3.0 * (1.0 - s) * (1.0 - s) * s * a + 3.0 * (1.0 - s) * s * s * b + s * s * s
+    };
+
+    let (mut low, mut high) = (0.0_f32, 1.0_f32);
+    for _ in 0..20 {
+        let mid = (low + high) / 2.0;
+        if bezier(x1, x2, mid) < x {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    bezier(y1, y2, (low + high) / 2.0)
+}
+
+/// A fill or stroke, evaluated at a frame.
+#[derive(Clone)]
+struct Style {
+    color: Color,
+    draw_type: DrawType,
+}
+
+/// A trim, evaluated at a frame, in fractions of the path length.
+#[derive(Clone, Copy)]
+struct Trim {
+    start: f32,
+    end: f32,
+}
+
+impl Lottie {
+    /// Load a Lottie animation from a file.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Lottie, Box<dyn std::error::Error>> {
+        let mut data = Vec::new();
+        std::fs::File::open(path)?.read_to_end(&mut data)?;
+        Lottie::from_bytes(&data)
+    }
+
+    /// Load a Lottie animation from a reader.
+    pub fn from_reader<R: std::io::Read>(mut reader: R) -> Result<Lottie, Box<dyn std::error::Error>> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Lottie::from_bytes(&data)
+    }
+
+    /// Load a Lottie animation from bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Lottie, Box<dyn std::error::Error>> {
+        let json: Value = serde_json::from_slice(bytes)?;
+        let layers = json.get("layers").and_then(Value::as_array)
+            .ok_or("Lottie animation has no layers")?;
+
+        Ok(Lottie {
+            name: json.get("nm").and_then(Value::as_str).unwrap_or_default().to_string(),
+            width: number(&json, "w").unwrap_or(0.0),
+            height: number(&json, "h").unwrap_or(0.0),
+            frame_rate: number(&json, "fr").unwrap_or(30.0),
+            in_point: number(&json, "ip").unwrap_or(0.0),
+            out_point: number(&json, "op").unwrap_or(0.0),
+            layers: layers.iter().map(parse_layer).collect(),
+            nine_slice: None,
+            anti_alias: None,
+        })
+    }
+
+    /// Duration of the animation, in seconds.
+    pub fn duration(&self) -> f32 {
+        if self.frame_rate > 0.0 {
+            (self.out_point - self.in_point) / self.frame_rate
+        } else {
+            0.0
+        }
+    }
+
+    /// The paths to draw at the given frame, in the same order as the paths
+    /// of a [`Svg`].
+    pub fn frame(&self, frame: f32) -> Vec<PathDescriptor> {
+        let mut paths = Vec::new();
+        // The first layer is the top-most one.
+        for layer in self.layers.iter().rev() {
+            if layer.kind != SHAPE_LAYER || frame < layer.in_point || frame >= layer.out_point {
+                continue;
+            }
+
+            let transform = self.layer_matrix(layer, frame);
+            let opacity = layer.transform.opacity(frame);
+            render_group(&layer.shapes, &[], None, transform, opacity, frame, &layer.name, &mut paths);
+        }
+
+        paths
+    }
+
+    /// Use nine-slice scaling with the given insets, like
+    /// [`SvgBuilder::nine_slice`](crate::svg::SvgBuilder::nine_slice). Change
+    /// the size of the [`NineSlice`] of the bundle's [`Svg`] to resize it.
+    pub fn nine_slice(mut self, top: f32, right: f32, bottom: f32, left: f32) -> Lottie {
+        let mut nine_slice = NineSlice::new(top, right, bottom, left);
+        nine_slice.size = Vec2::new(self.width, self.height);
+        self.nine_slice = Some(nine_slice);
+        self
+    }
+
+    /// Anti-alias the edges of the animation with a fringe of the given width,
+    /// like [`SvgBuilder::anti_alias`](crate::svg::SvgBuilder::anti_alias).
+    pub fn anti_alias(mut self, fringe_width: f32) -> Lottie {
+        self.anti_alias = Some(fringe_width);
+        self
+    }
+
+    /// Create a [`SvgBundle`] showing the first frame of the animation, with
+    /// the nine-slice scaling and anti-aliasing set on the [`Lottie`].
+    pub fn bundle(&self) -> SvgBundle {
+        let svg = Svg {
+            name: self.name.clone(),
+            width: f64::from(self.width),
+            height: f64::from(self.height),
+            view_box: ViewBox { x: 0.0, y: 0.0, w: f64::from(self.width), h: f64::from(self.height) },
+            origin: Origin::TopLeft,
+            paths: self.frame(self.in_point),
+            layers: Vec::new(),
+            objects: Vec::new(),
+            animations: Vec::new(),
+            element_transforms: HashMap::new(),
+            nine_slice: self.nine_slice,
+            filters: Vec::new(),
+            patterns: Vec::new(),
+            anti_alias: self.anti_alias,
+            uv_mode: Default::default(),
+            #[cfg(feature = "collider")]
+            colliders: None,
+        };
+
+        SvgBundle::new(svg).at_position(Vec3::default())
+    }
+
+    /// Transform of a layer, including the transforms of its parents.
+    fn layer_matrix(&self, layer: &Layer, frame: f32) -> Transform {
+        let mut transform = layer.transform.matrix(frame);
+        let mut parent = layer.parent;
+        // Bounded by the number of layers, in case of cyclic parents.
+        for _ in 0..self.layers.len() {
+            let layer = match parent.and_then(|p| self.layers.iter().find(|l| l.index == Some(p))) {
+                Some(layer) => layer,
+                None => break,
+            };
+            transform = transform.then(&layer.transform.matrix(frame));
+            parent = layer.parent;
+        }
+
+        transform
+    }
+}
+
+/// Convert the geometry of a group into paths for each of its fills and
+/// strokes, and those inherited from the parent groups.
+#[allow(clippy::too_many_arguments)]
+fn render_group(
+    shapes: &[Shape],
+    inherited: &[Style],
+    inherited_trim: Option<Trim>,
+    transform: Transform,
+    opacity: f32,
+    frame: f32,
+    id: &str,
+    paths: &mut Vec<PathDescriptor>,
+) {
+    let (transform, opacity) = match shapes.iter().find_map(|s| if let Shape::Transform(t) = s { Some(t) } else { None }) {
+        Some(group) => (group.matrix(frame).then(&transform), opacity * group.opacity(frame)),
+        None => (transform, opacity),
+    };
+
+    let mut geometry = Vec::new();
+    let mut styles = Vec::new();
+    let mut trim_paths = inherited_trim;
+    for shape in shapes.iter() {
+        match *shape {
+            Shape::Path(ref path) => if let Some(bezier) = path.sample(frame) {
+                bezier_segments(&bezier, &mut geometry);
+            },
+            Shape::Ellipse { ref position, ref size } => ellipse_segments(
+                point(position.value(frame, 0, 0.0), position.value(frame, 1, 0.0)),
+                vector(size.value(frame, 0, 0.0), size.value(frame, 1, 0.0)) / 2.0,
+                &mut geometry,
+            ),
+            Shape::Rect { ref position, ref size } => rect_segments(
+                point(position.value(frame, 0, 0.0), position.value(frame, 1, 0.0)),
+                vector(size.value(frame, 0, 0.0), size.value(frame, 1, 0.0)) / 2.0,
+                &mut geometry,
+            ),
+            Shape::Fill { ref color, ref opacity } => styles.push(Style {
+                color: color_at(color, opacity, frame),
+                draw_type: DrawType::Fill,
+            }),
+            Shape::Stroke { ref color, ref opacity, ref width, cap, join, miter_limit } => {
+                // Strokes get scaled along with the geometry.
+                let scale = transform.determinant().abs().sqrt();
+                styles.push(Style {
+                    color: color_at(color, opacity, frame),
+                    draw_type: DrawType::Stroke(
+                        StrokeOptions::tolerance(0.01)
+                            .with_line_width(width.value(frame, 0, 1.0) * scale)
+                            .with_line_cap(cap)
+                            .with_line_join(join)
+                            .with_miter_limit(miter_limit.max(StrokeOptions::MINIMUM_MITER_LIMIT)),
+                    ),
+                });
+            },
+            Shape::Trim { ref start, ref end, ref offset } => {
+                let offset = offset.value(frame, 0, 0.0) / 360.0;
+                trim_paths = Some(Trim {
+                    start: start.value(frame, 0, 0.0) / 100.0 + offset,
+                    end: end.value(frame, 0, 100.0) / 100.0 + offset,
+                });
+            },
+            Shape::Group(_) | Shape::Transform(_) => {},
+        }
+    }
+
+    if let Some(trim_paths) = trim_paths {
+        geometry = trimmed(&geometry, trim_paths);
+    }
+    let geometry: Vec<PathEvent> = geometry.iter().map(|event| event.transformed(&transform)).collect();
+
+    // Shapes listed first are drawn on top, so fills and strokes get emitted
+    // in reverse order.
+    if !geometry.is_empty() {
+        for style in styles.iter().rev().chain(inherited.iter()) {
+            let mut color = style.color;
+            color.set_a(color.a() * opacity);
+            paths.push(PathDescriptor {
+                segments: geometry.clone(),
+                abs_transform: Default::default(),
+                color,
+                draw_type: style.draw_type.clone(),
+                id: id.to_string(),
+                group_ids: Vec::new(),
+                attributes: HashMap::new(),
+                layer: None,
+                z: 0.0,
+            });
+        }
+    }
+
+    styles.extend(inherited.iter().cloned());
+    for shape in shapes.iter().rev() {
+        if let Shape::Group(ref shapes) = *shape {
+            render_group(shapes, &styles, trim_paths, transform, opacity, frame, id, paths);
+        }
+    }
+}
+
+/// Trim a path to the given fractions of its length, wrapping around at the end.
+fn trimmed(segments: &[PathEvent], trim_paths: Trim) -> Vec<PathEvent> {
+    let total = length(segments);
+    let shift = trim_paths.start.min(trim_paths.end).floor();
+    let (start, end) = (trim_paths.start - shift, trim_paths.end - shift);
+    let (start, end) = if start <= end { (start, end) } else { (end, start) };
+
+    let mut result = trim(segments, start * total, end.min(1.0) * total);
+    if end > 1.0 {
+        result.extend(trim(segments, 0.0, (end - 1.0) * total));
+    }
+    result
+}
+
+fn bezier_segments(bezier: &Bezier, segments: &mut Vec<PathEvent>) {
+    let first = match bezier.vertices.first() {
+        Some(first) => *first,
+        None => return,
+    };
+    let tangent = |tangents: &[Vector], i: usize| tangents.get(i).copied().unwrap_or_else(Vector::zero);
+
+    let count = bezier.vertices.len();
+    let edges = if bezier.closed { count } else { count - 1 };
+    segments.push(PathEvent::Begin { at: first });
+    for i in 0..edges {
+        let (from, to) = (bezier.vertices[i], bezier.vertices[(i + 1) % count]);
+        segments.push(PathEvent::Cubic {
+            from,
+            ctrl1: from + tangent(&bezier.out_tangents, i),
+            ctrl2: to + tangent(&bezier.in_tangents, (i + 1) % count),
+            to,
+        });
+    }
+    let last = if bezier.closed { first } else { bezier.vertices[count - 1] };
+    segments.push(PathEvent::End { last, first, close: bezier.closed });
+}
+
+fn ellipse_segments(center: Point, radius: Vector, segments: &mut Vec<PathEvent>) {
+    let (rx, ry) = (radius.x, radius.y);
+    let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+    let top = center + vector(0.0, -ry);
+    let right = center + vector(rx, 0.0);
+    let bottom = center + vector(0.0, ry);
+    let left = center + vector(-rx, 0.0);
+
+    segments.push(PathEvent::Begin { at: top });
+    segments.push(PathEvent::Cubic { from: top, ctrl1: top + vector(kx, 0.0), ctrl2: right + vector(0.0, -ky), to: right });
+    segments.push(PathEvent::Cubic { from: right, ctrl1: right + vector(0.0, ky), ctrl2: bottom + vector(kx, 0.0), to: bottom });
+    segments.push(PathEvent::Cubic { from: bottom, ctrl1: bottom + vector(-kx, 0.0), ctrl2: left + vector(0.0, ky), to: left });
+    segments.push(PathEvent::Cubic { from: left, ctrl1: left + vector(0.0, -ky), ctrl2: top + vector(-kx, 0.0), to: top });
+    segments.push(PathEvent::End { last: top, first: top, close: true });
+}
+
+/// Rectangle without rounded corners.
+fn rect_segments(center: Point, half_size: Vector, segments: &mut Vec<PathEvent>) {
+    let corners = [
+        center + vector(half_size.x, -half_size.y),
+        center + half_size,
+        center + vector(-half_size.x, half_size.y),
+        center - half_size,
+    ];
+
+    segments.push(PathEvent::Begin { at: corners[0] });
+    for i in 0..3 {
+        segments.push(PathEvent::Line { from: corners[i], to: corners[i + 1] });
+    }
+    segments.push(PathEvent::End { last: corners[3], first: corners[0], close: true });
+}
+
+fn color_at(color: &Property<Vec<f32>>, opacity: &Property<Vec<f32>>, frame: f32) -> Color {
+    Color::rgba(
+        color.value(frame, 0, 0.0),
+        color.value(frame, 1, 0.0),
+        color.value(frame, 2, 0.0),
+        opacity.value(frame, 0, 100.0) / 100.0,
+    )
+}
+
+fn number(json: &Value, key: &str) -> Option<f32> {
+    json.get(key).and_then(Value::as_f64).map(|n| n as f32)
+}
+
+fn parse_layer(json: &Value) -> Layer {
+    Layer {
+        name: json.get("nm").and_then(Value::as_str).unwrap_or_default().to_string(),
+        kind: json.get("ty").and_then(Value::as_u64).unwrap_or_default(),
+        index: json.get("ind").and_then(Value::as_i64),
+        parent: json.get("parent").and_then(Value::as_i64),
+        in_point: number(json, "ip").unwrap_or(f32::MIN),
+        out_point: number(json, "op").unwrap_or(f32::MAX),
+        transform: parse_transform(json.get("ks")),
+        shapes: parse_shapes(json.get("shapes")),
+    }
+}
+
+fn parse_shapes(json: Option<&Value>) -> Vec<Shape> {
+    json.and_then(Value::as_array)
+        .map(|shapes| shapes.iter().filter_map(parse_shape).collect())
+        .unwrap_or_default()
+}
+
+fn parse_shape(json: &Value) -> Option<Shape> {
+    if json.get("hd").and_then(Value::as_bool) == Some(true) {
+        return None;
+    }
+
+    let numbers = |key: &str| property(json.get(key), parse_numbers);
+    let shape = match json.get("ty")?.as_str()? {
+        "gr" => Shape::Group(parse_shapes(json.get("it"))),
+        "sh" => Shape::Path(property(json.get("ks"), parse_bezier)),
+        "el" => Shape::Ellipse { position: numbers("p"), size: numbers("s") },
+        "rc" => Shape::Rect { position: numbers("p"), size: numbers("s") },
+        "fl" => Shape::Fill { color: numbers("c"), opacity: numbers("o") },
+        "st" => Shape::Stroke {
+            color: numbers("c"),
+            opacity: numbers("o"),
+            width: numbers("w"),
+            cap: match json.get("lc").and_then(Value::as_u64) {
+                Some(2) => LineCap::Round,
+                Some(3) => LineCap::Square,
+                _ => LineCap::Butt,
+            },
+            join: match json.get("lj").and_then(Value::as_u64) {
+                Some(2) => LineJoin::Round,
+                Some(3) => LineJoin::Bevel,
+                _ => LineJoin::Miter,
+            },
+            miter_limit: number(json, "ml").unwrap_or(4.0),
+        },
+        "tm" => Shape::Trim { start: numbers("s"), end: numbers("e"), offset: numbers("o") },
+        "tr" => Shape::Transform(parse_transform(Some(json))),
+        _ => return None,
+    };
+
+    Some(shape)
+}
+
+fn parse_transform(json: Option<&Value>) -> ShapeTransform {
+    let numbers = |key: &str| property(json.and_then(|json| json.get(key)), parse_numbers);
+    ShapeTransform {
+        anchor: numbers("a"),
+        position: numbers("p"),
+        scale: numbers("s"),
+        rotation: numbers("r"),
+        opacity: numbers("o"),
+    }
+}
+
+/// Parse a static or animated property, missing or invalid properties are
+/// empty and fall back to their default when sampled.
+fn property<T: Default>(json: Option<&Value>, parse: fn(&Value) -> Option<T>) -> Property<T> {
+    let value = match json.and_then(|json| json.get("k")) {
+        Some(value) => value,
+        None => return Property::Static(T::default()),
+    };
+
+    let keyframes = value.as_array()
+        .filter(|keys| keys.first().and_then(|key| key.get("t")).is_some());
+    match keyframes {
+        Some(keys) => Property::Animated(keys.iter().map(|key| Keyframe {
+            time: number(key, "t").unwrap_or(0.0),
+            start: key.get("s").and_then(parse),
+            end: key.get("e").and_then(parse),
+            hold: key.get("h").and_then(Value::as_u64) == Some(1),
+            easing: parse_easing(key),
+        }).collect()),
+        None => Property::Static(parse(value).unwrap_or_default()),
+    }
+}
+
+fn parse_easing(key: &Value) -> Option<[f32; 4]> {
+    let (out, into) = (key.get("o")?, key.get("i")?);
+    Some([
+        first_number(out.get("x"))?,
+        first_number(out.get("y"))?,
+        first_number(into.get("x"))?,
+        first_number(into.get("y"))?,
+    ])
+}
+
+fn first_number(json: Option<&Value>) -> Option<f32> {
+    match json? {
+        Value::Array(values) => values.first()?.as_f64().map(|n| n as f32),
+        value => value.as_f64().map(|n| n as f32),
+    }
+}
+
+fn parse_numbers(json: &Value) -> Option<Vec<f32>> {
+    match json {
+        Value::Array(values) => values.iter().map(|v| v.as_f64().map(|n| n as f32)).collect(),
+        value => value.as_f64().map(|n| vec![n as f32]),
+    }
+}
+
+fn parse_bezier(json: &Value) -> Option<Bezier> {
+    // Keyframed paths wrap their value in an array.
+    let json = match json {
+        Value::Array(values) => values.first()?,
+        value => value,
+    };
+    let points = |key: &str| -> Option<Vec<[f32; 2]>> {
+        json.get(key)?.as_array()?.iter()
+            .map(|p| match parse_numbers(p)?[..] {
+                [x, y, ..] => Some([x, y]),
+                _ => None,
+            })
+            .collect()
+    };
+
+    Some(Bezier {
+        closed: json.get("c").and_then(Value::as_bool).unwrap_or(false),
+        vertices: points("v")?.into_iter().map(|[x, y]| point(x, y)).collect(),
+        in_tangents: points("i").unwrap_or_default().into_iter().map(|[x, y]| vector(x, y)).collect(),
+        out_tangents: points("o").unwrap_or_default().into_iter().map(|[x, y]| vector(x, y)).collect(),
+    })
+}
+
+/// Bevy system which advances the [`SvgAnimationPlayer`]s of [`Lottie`]
+/// animations and re-tessellates the current frame, with the tessellation
+/// options and nine-slice scaling of the entity's [`Svg`].
+pub(crate) fn lottie_player(
+    time: Res<Time>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut fill_tess: ResMut<FillTessellator>,
+    mut stroke_tess: ResMut<StrokeTessellator>,
+    mut query: Query<(&Lottie, &Svg, &mut SvgAnimationPlayer, &Handle<Mesh>)>,
+) {
+    for (lottie, svg, mut player, mesh) in query.iter_mut() {
+        if player.playing {
+            player.time += time.delta_seconds() * player.speed;
+            let duration = lottie.duration();
+            if player.time > duration {
+                player.time = if player.looping && duration > 0.0 { player.time % duration } else { duration };
+            }
+        }
+        if !player.is_changed() {
+            continue;
+        }
+
+        let frame = lottie.in_point + player.time * lottie.frame_rate;
+        let paths = lottie.frame(frame);
+        let mut buffers = VertexBuffers::new();
+        for path in mesh_order(&paths, svg.anti_alias.is_some()).into_iter().map(|index| &paths[index]) {
+            tessellate_path_with(
+                &mut fill_tess, &mut stroke_tess, path, path.segments.iter().cloned(),
+                TessellationOptions::of(svg), &mut buffers,
+            );
+        }
+        if let Some(ref nine_slice) = svg.nine_slice {
+            nine_slice.apply(&svg.view_box, &mut buffers);
+        }
+
+        if let Some(mesh) = meshes.get_mut(mesh) {
+            *mesh = buffers.convert();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_near, fill_area};
+
+    const SQUARE: &str = r#"{
+        "nm": "square", "w": 100, "h": 50, "fr": 25, "ip": 0, "op": 50,
+        "layers": [{
+            "nm": "box", "ty": 4, "ind": 1,
+            "ks": { "p": { "k": [50, 25] } },
+            "shapes": [
+                { "ty": "rc", "p": { "k": [0, 0] }, "s": { "k": [
+                    { "t": 0, "s": [10, 10], "e": [20, 20] },
+                    { "t": 50 }
+                ] } },
+                { "ty": "fl", "c": { "k": [1, 0, 0, 1] }, "o": { "k": 50 } },
+                { "ty": "st", "c": { "k": [0, 0, 1, 1] }, "o": { "k": 100 }, "w": { "k": 2 } }
+            ]
+        }, {
+            "nm": "image", "ty": 2
+        }]
+    }"#;
+
+    #[test]
+    fn header() {
+        let lottie = Lottie::from_bytes(SQUARE.as_bytes()).unwrap();
+        assert_eq!(lottie.name, "square");
+        assert_eq!((lottie.width, lottie.height), (100.0, 50.0));
+        assert_near(lottie.duration(), 2.0, 1e-6);
+
+        assert!(Lottie::from_bytes(b"{}").is_err());
+        assert!(Lottie::from_bytes(b"not json").is_err());
+    }
+
+    #[test]
+    fn bundle_keeps_the_options() {
+        let lottie = Lottie::from_bytes(SQUARE.as_bytes()).unwrap();
+        let svg = lottie.bundle().svg;
+        assert_eq!((svg.nine_slice, svg.anti_alias), (None, None));
+
+        let svg = lottie.nine_slice(1.0, 2.0, 3.0, 4.0).anti_alias(0.5).bundle().svg;
+        let nine_slice = svg.nine_slice.unwrap();
+        assert_eq!((nine_slice.top, nine_slice.right, nine_slice.bottom, nine_slice.left), (1.0, 2.0, 3.0, 4.0));
+        assert_eq!(nine_slice.size, Vec2::new(100.0, 50.0));
+        assert_eq!(svg.anti_alias, Some(0.5));
+    }
+
+    #[test]
+    fn shape_layer_becomes_fill_and_stroke() {
+        let lottie = Lottie::from_bytes(SQUARE.as_bytes()).unwrap();
+        let paths = lottie.frame(0.0);
+        assert_eq!(paths.len(), 2);
+
+        // The stroke is listed last, so it is drawn below the fill and comes first.
+        assert!(matches!(paths[0].draw_type, DrawType::Stroke(_)));
+        assert!(matches!(paths[1].draw_type, DrawType::Fill));
+        assert_eq!(paths[1].id, "box");
+        assert_near(paths[1].color.a(), 0.5, 1e-6);
+        assert_near(fill_area(&paths[1].segments), 100.0, 0.01);
+        assert_eq!(paths[1].bounds().map(|b| b.center()), Some(point(50.0, 25.0)));
+    }
+
+    #[test]
+    fn keyframes_interpolate() {
+        let lottie = Lottie::from_bytes(SQUARE.as_bytes()).unwrap();
+        assert_near(fill_area(&lottie.frame(25.0)[1].segments), 225.0, 0.01);
+        assert_near(fill_area(&lottie.frame(50.0)[1].segments), 400.0, 0.01);
+    }
+
+    #[test]
+    fn easing_curve() {
+        assert_near(ease([0.0, 0.0, 1.0, 1.0], 0.3), 0.3, 1e-3);
+        assert_near(ease([0.42, 0.0, 0.58, 1.0], 0.5), 0.5, 1e-3);
+        assert!(ease([0.42, 0.0, 1.0, 1.0], 0.25) < 0.25);
+    }
+
+    #[test]
+    fn trim_wraps_around() {
+        let mut square = Vec::new();
+        rect_segments(point(5.0, 5.0), vector(5.0, 5.0), &mut square);
+        assert_near(length(&trimmed(&square, Trim { start: 0.25, end: 0.5 })), 10.0, 1e-3);
+        assert_near(length(&trimmed(&square, Trim { start: 0.75, end: 1.25 })), 20.0, 1e-3);
+    }
+}
@@ -0,0 +1,129 @@
+//! Custom materials and shaders for SVG meshes.
+//!
+//! SVG meshes are drawn with the pipeline of their [`RenderPipelines`](bevy::render::pipeline::RenderPipelines)
+//! component, so a custom shader only needs its own [`PipelineDescriptor`](bevy::render::pipeline::PipelineDescriptor),
+//! set with [`SvgBundle::with_pipeline`](crate::bundle::SvgBundle::with_pipeline).
+//! Uniforms and textures come from a [`RenderResources`] asset whose handle
+//! is inserted next to the bundle, once [`SvgMaterialPlugin`] bound it to the
+//! render graph.
+//!
+//! ## Vertex attributes
+//! Bevy matches the attributes of the mesh to the inputs of the vertex shader
+//! by name. [`SVG_VERTEX_SHADER`](crate::plugin::SVG_VERTEX_SHADER) uses the
+//! following locations:
+//!
+//! | Location | Input                   | Content                                            |
+//! |----------|-------------------------|----------------------------------------------------|
+//! | 0        | `vec3 Vertex_Position`  | Position in SVG units, the y-axis points down      |
+//! | 1        | `vec4 Vertex_Color`     | Fill or stroke color                               |
+//! | 2        | `float Vertex_Coverage` | `0` on the outer edge of the anti-aliasing fringe  |
+//! | 3        | `vec2 Vertex_Uv`        | Texture coordinates, see [`UvMode`](crate::svg::UvMode) |
+//!
+//! Meshes also have a `vec3 Vertex_Normal`, always `(0, 0, 1)`. It isn't an
+//! input of the default vertex shader.
+//!
+//! The default vertex shader passes `vec4 v_color`, `float v_coverage` and
+//! `vec2 v_uv` at locations 0, 1 and 2 to the fragment shader, so a custom
+//! fragment shader can reuse it.
+//!
+//! ## Bind groups
+//! - set 0, binding 0: the `CameraViewProj` uniform, with `mat4 ViewProj`.
+//! - set 1, binding 0: the `Transform` uniform, with `mat4 Model`.
+//! - set 2: the fields of the material, named `<Material>_<field>`.
+//!
+//! ## Example
+//! ```ignore
+//! #[derive(RenderResources, Default, TypeUuid)]
+//! #[uuid = "0e5b2a4c-6f1d-4c8e-9a3b-7d2f1e6c5b4a"]
+//! struct Dissolve {
+//!     threshold: f32,
+//! }
+//!
+//! const DISSOLVE_SHADER: &str = r#"
+//! #version 450
+//! layout(location = 0) in vec4 v_color;
+//! layout(location = 1) in float v_coverage;
+//! layout(location = 2) in vec2 v_uv;
+//! layout(location = 0) out vec4 o_Target;
+//!
+//! layout(set = 2, binding = 0) uniform Dissolve_threshold {
+//!     float threshold;
+//! };
+//!
+//! void main() {
+//!     if (v_uv.x < threshold) {
+//!         discard;
+//!     }
+//!     o_Target = vec4(v_color.rgb, v_color.a * v_coverage);
+//! }
+//! "#;
+//!
+//! fn main() {
+//!     App::build()
+//!         .add_plugins(DefaultPlugins)
+//!         .add_plugin(SvgPlugin)
+//!         .add_plugin(SvgMaterialPlugin::<Dissolve>::default())
+//!         .add_startup_system(setup.system())
+//!         .run();
+//! }
+//!
+//! fn setup(
+//!     mut commands: Commands,
+//!     mut pipelines: ResMut<Assets<PipelineDescriptor>>,
+//!     mut shaders: ResMut<Assets<Shader>>,
+//!     mut materials: ResMut<Assets<Dissolve>>,
+//! ) {
+//!     let pipeline = pipelines.add(PipelineDescriptor::default_config(ShaderStages {
+//!         vertex: shaders.add(Shader::from_glsl(ShaderStage::Vertex, SVG_VERTEX_SHADER)),
+//!         fragment: Some(shaders.add(Shader::from_glsl(ShaderStage::Fragment, DISSOLVE_SHADER))),
+//!     }));
+//!     let svg = SvgBuilder::from_file("assets/logo.svg").load().unwrap();
+//!     commands
+//!         .spawn_bundle(SvgBundle::new(svg).with_pipeline(pipeline))
+//!         .insert(materials.add(Dissolve { threshold: 0.5 }));
+//! }
+//! ```
+//!
+//! Filtered groups and pattern-painted paths keep the default shaders. SVGs
+//! with a custom pipeline aren't batched, even if they are
+//! [`SvgBatched`](crate::batch::SvgBatched).
+
+use std::marker::PhantomData;
+
+use bevy::{
+    app::{AppBuilder, Plugin},
+    asset::{AddAsset, Asset, Assets},
+    render::{
+        render_graph::{AssetRenderResourcesNode, base, RenderGraph},
+        renderer::RenderResources,
+    },
+};
+
+/// Plugin binding the [`RenderResources`] of the material `M` to the shaders
+/// of SVG meshes with a custom pipeline. Add it after the
+/// [`SvgPlugin`](crate::plugin::SvgPlugin), once per material type.
+pub struct SvgMaterialPlugin<M> {
+    marker: PhantomData<fn() -> M>,
+}
+
+impl<M> Default for SvgMaterialPlugin<M> {
+    fn default() -> Self {
+        SvgMaterialPlugin { marker: PhantomData }
+    }
+}
+
+impl<M: Asset + RenderResources> Plugin for SvgMaterialPlugin<M> {
+    fn build(&self, app: &mut AppBuilder) {
+        // The material may already be an asset of the app.
+        if app.world().get_resource::<Assets<M>>().is_none() {
+            app.add_asset::<M>();
+        }
+
+        let name = std::any::type_name::<M>();
+        let mut render_graph = app.world_mut()
+            .get_resource_mut::<RenderGraph>()
+            .expect("SvgMaterialPlugin requires the RenderPlugin");
+        render_graph.add_system_node(name, AssetRenderResourcesNode::<M>::new(true));
+        render_graph.add_node_edge(name, base::node::MAIN_PASS).unwrap();
+    }
+}
@@ -0,0 +1,156 @@
+//! Custom XML attributes of SVG elements.
+//!
+//! `usvg` drops every attribute it doesn't need for rendering, so the custom
+//! attributes are collected in a separate pass over the raw XML document.
+//! Elements without an `id` are matched with the `usvg` tree through a
+//! generated `id`, which is inserted into the document before `usvg` parses
+//! it and removed again from the loaded paths.
+
+use std::collections::HashMap;
+
+/// Namespaces whose attributes are part of SVG itself and never retained.
+const SVG_NAMESPACES: &[&str] = &[
+    "http://www.w3.org/2000/svg",
+    "http://www.w3.org/1999/xlink",
+    roxmltree::NS_XML_URI,
+    roxmltree::NS_XMLNS_URI,
+];
+
+/// Prefix of the ids generated for elements without an `id`.
+const GENERATED_ID_PREFIX: &str = "__bevy_svg_element_";
+
+/// Custom attributes of all elements, keyed by their `id` or the generated
+/// `id` of elements without one.
+pub(crate) type ElementAttributes = HashMap<String, HashMap<String, String>>;
+
+/// Parse the raw XML of a SVG with the same options `usvg` uses.
+pub(crate) fn parse_xml(text: &str) -> Result<roxmltree::Document<'_>, roxmltree::Error> {
+    let mut xml_opt = roxmltree::ParsingOptions::default();
+    xml_opt.allow_dtd = true;
+    roxmltree::Document::parse_with_options(text, xml_opt)
+}
+
+/// Collect the custom attributes of all elements.
+///
+/// Retained are `class`, all `data-*` attributes and all attributes in a
+/// foreign namespace, e.g. `inkscape:label`. The latter are keyed as
+/// `prefix:name` with the prefix declared in the document.
+pub(crate) fn custom_attributes(doc: &roxmltree::Document<'_>) -> ElementAttributes {
+    let mut elements = HashMap::new();
+    for (index, node) in doc.descendants().filter(roxmltree::Node::is_element).enumerate() {
+        let attributes = element_attributes(&node);
+        if attributes.is_empty() {
+            continue;
+        }
+        let id = match node.attribute("id") {
+            Some(id) => id.to_string(),
+            None => generated_id(index),
+        };
+        elements.insert(id, attributes);
+    }
+
+    elements
+}
+
+/// The document with a generated `id` on every element that has custom
+/// attributes but no `id`, so that `usvg` keeps a key to the attributes.
+pub(crate) fn with_generated_ids(text: &str, doc: &roxmltree::Document<'_>) -> String {
+    let mut insertions = Vec::new();
+    for (index, node) in doc.descendants().filter(roxmltree::Node::is_element).enumerate() {
+        if node.attribute("id").is_some() || element_attributes(&node).is_empty() {
+            continue;
+        }
+        // Right after the tag name of the start tag.
+        let start = node.range().start + 1;
+        let name_length = text[start..]
+            .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+            .unwrap_or(0);
+        insertions.push((start + name_length, format!(" id=\"{}\"", generated_id(index))));
+    }
+
+    let mut text = text.to_string();
+    for (position, attribute) in insertions.into_iter().rev() {
+        text.insert_str(position, &attribute);
+    }
+    text
+}
+
+/// The `id` of an element as written in the document, i.e. empty for
+/// generated ids.
+pub(crate) fn element_id(id: &str) -> &str {
+    if id.starts_with(GENERATED_ID_PREFIX) { "" } else { id }
+}
+
+pub(crate) fn generated_id(index: usize) -> String {
+    format!("{}{}", GENERATED_ID_PREFIX, index)
+}
+
+fn element_attributes(node: &roxmltree::Node<'_, '_>) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    for attr in node.attributes() {
+        let name = match attr.namespace() {
+            None if attr.name() == "class" || attr.name().starts_with("data-") =>
+                attr.name().to_string(),
+            Some(ns) if !SVG_NAMESPACES.contains(&ns) => match node.lookup_prefix(ns) {
+                Some(prefix) => format!("{}:{}", prefix, attr.name()),
+                None => attr.name().to_string(),
+            },
+            _ => continue,
+        };
+        attributes.insert(name, attr.value().to_string());
+    }
+    attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::load;
+
+    #[test]
+    fn collects_custom_attributes() {
+        let text = r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape">
+            <rect id="a" class="hazard" data-damage="5" inkscape:label="Spikes" fill="red" width="1" height="1"/>
+            <rect width="1" height="1"/>
+        </svg>"#;
+        let doc = parse_xml(text).unwrap();
+        let attributes = custom_attributes(&doc);
+        assert_eq!(attributes.len(), 1);
+        let a = &attributes["a"];
+        assert_eq!(a["class"], "hazard");
+        assert_eq!(a["data-damage"], "5");
+        assert_eq!(a["inkscape:label"], "Spikes");
+        assert!(!a.contains_key("fill"));
+    }
+
+    #[test]
+    fn generated_ids_are_inserted_into_the_start_tag() {
+        let text = r#"<svg xmlns="http://www.w3.org/2000/svg"><rect class="hazard" width="1" height="1"/><path/></svg>"#;
+        let doc = parse_xml(text).unwrap();
+        let rewritten = with_generated_ids(text, &doc);
+        assert_eq!(
+            rewritten,
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><rect id="__bevy_svg_element_1" class="hazard" width="1" height="1"/><path/></svg>"#,
+        );
+        assert_eq!(element_id("__bevy_svg_element_1"), "");
+        assert_eq!(element_id("player"), "player");
+    }
+
+    #[test]
+    fn paths_without_id_keep_their_attributes() {
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+            <g id="hazards" data-zone="1">
+                <rect class="hazard" data-damage="5" width="1" height="1"/>
+            </g>
+            <g class="decoration"><rect id="plain" width="1" height="1"/></g>
+        </svg>"#);
+        assert_eq!(svg.paths.len(), 2);
+        assert_eq!(svg.paths[0].id, "");
+        assert_eq!(svg.paths[0].attributes["class"], "hazard");
+        assert_eq!(svg.paths[0].attributes["data-damage"], "5");
+        assert_eq!(svg.paths[0].group_ids, vec!["hazards".to_string()]);
+        assert_eq!(svg.paths[1].id, "plain");
+        assert!(svg.paths[1].attributes.is_empty());
+        assert!(svg.paths[1].group_ids.is_empty());
+    }
+}
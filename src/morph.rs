@@ -0,0 +1,350 @@
+//! Shape morphing between two SVGs.
+//!
+//! Paths of both SVGs are matched by `id` first and by document order second,
+//! only fills with fills and strokes with strokes. Every matched pair is
+//! converted to cubic béziers and the curves are subdivided until both paths
+//! have the same number of sub-paths and curves, so that their control points
+//! can be interpolated. Paths without a counterpart fade out or in.
+
+use bevy::{
+    asset::{Assets, Handle},
+    ecs::{query::Changed, system::{Query, ResMut}},
+    render::{color::Color, mesh::Mesh},
+};
+use lyon_geom::CubicBezierSegment;
+use lyon_svg::path::PathEvent;
+use lyon_tessellation::{FillTessellator, StrokeTessellator, math::Point};
+
+use crate::{
+    Convert,
+    geometry::Edge,
+    plugin::{TessellationOptions, mesh_order, tessellate_path_with},
+    svg::{DrawType, PathDescriptor, Svg},
+    vertex_buffer::VertexBuffers,
+};
+
+/// Tolerance used to find the longest curve when subdividing.
+const SUBDIVISION_TOLERANCE: f32 = 0.1;
+
+/// Component which morphs the mesh of a SVG entity from one [`Svg`] into
+/// another.
+///
+/// Only [`Svg::paths`] get morphed. Filtered groups and pattern-painted paths
+/// are drawn by child entities of their own and keep their shape.
+///
+/// ```ignore
+/// let from = SvgBuilder::from_file("play.svg").build()?;
+/// let to = SvgBuilder::from_file("pause.svg").build()?;
+/// let morph = SvgMorph::new(&from.svg, &to.svg);
+/// commands.spawn_bundle(from).insert(morph);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SvgMorph {
+    /// Progress of the morph, `0.0` draws the first and `1.0` the second SVG.
+    pub t: f32,
+    paths: Vec<MorphPath>,
+}
+
+/// A pair of matched paths, with compatible geometry.
+#[derive(Clone, Debug)]
+struct MorphPath {
+    /// Descriptor used for everything that isn't interpolated.
+    path: PathDescriptor,
+    from: MorphStyle,
+    to: MorphStyle,
+}
+
+/// The interpolated parts of a path.
+#[derive(Clone, Debug)]
+struct MorphStyle {
+    shape: Vec<SubPath>,
+    color: Color,
+    line_width: f32,
+}
+
+#[derive(Clone, Debug)]
+struct SubPath {
+    start: Point,
+    curves: Vec<CubicBezierSegment<f32>>,
+    closed: bool,
+}
+
+impl SvgMorph {
+    /// Prepare a morph from the paths of `from` into the paths of `to`.
+    pub fn new(from: &Svg, to: &Svg) -> SvgMorph {
+        let matches = match_paths(&from.paths, &to.paths);
+
+        let mut paths = Vec::with_capacity(from.paths.len());
+        for (path, matched) in from.paths.iter().zip(matches.iter()) {
+            let (from, to) = match *matched {
+                Some(j) => compatible(MorphStyle::new(path), MorphStyle::new(&to.paths[j])),
+                None => (MorphStyle::new(path), MorphStyle::new(path).transparent()),
+            };
+            paths.push(MorphPath { path: path.clone(), from, to });
+        }
+
+        for (j, path) in to.paths.iter().enumerate() {
+            if !matches.contains(&Some(j)) {
+                paths.push(MorphPath {
+                    path: path.clone(),
+                    from: MorphStyle::new(path).transparent(),
+                    to: MorphStyle::new(path),
+                });
+            }
+        }
+
+        SvgMorph { t: 0.0, paths }
+    }
+
+    /// Set the progress of the morph.
+    pub fn with_t(mut self, t: f32) -> SvgMorph {
+        self.t = t;
+        self
+    }
+
+    /// The interpolated paths at the current progress.
+    fn interpolated(&self) -> impl Iterator<Item = (PathDescriptor, Vec<PathEvent>)> + '_ {
+        let t = self.t.max(0.0).min(1.0);
+        self.paths.iter().map(move |morph| {
+            let mut path = morph.path.clone();
+            path.color = lerp_color(morph.from.color, morph.to.color, t);
+            if let DrawType::Stroke(ref mut opts) = path.draw_type {
+                opts.line_width = lerp(morph.from.line_width, morph.to.line_width, t);
+            }
+
+            let mut segments = Vec::new();
+            for (a, b) in morph.from.shape.iter().zip(morph.to.shape.iter()) {
+                let first = a.start.lerp(b.start, t);
+                let mut last = first;
+                segments.push(PathEvent::Begin { at: first });
+                for (ca, cb) in a.curves.iter().zip(b.curves.iter()) {
+                    let from = last;
+                    last = ca.to.lerp(cb.to, t);
+                    segments.push(PathEvent::Cubic {
+                        from,
+                        ctrl1: ca.ctrl1.lerp(cb.ctrl1, t),
+                        ctrl2: ca.ctrl2.lerp(cb.ctrl2, t),
+                        to: last,
+                    });
+                }
+                let close = if t < 0.5 { a.closed } else { b.closed };
+                segments.push(PathEvent::End { last, first, close });
+            }
+
+            (path, segments)
+        })
+    }
+}
+
+impl MorphStyle {
+    fn new(path: &PathDescriptor) -> MorphStyle {
+        let mut shape: Vec<SubPath> = Vec::new();
+        for event in path.segments.iter() {
+            match *event {
+                PathEvent::Begin { at } => shape.push(SubPath { start: at, curves: Vec::new(), closed: false }),
+                PathEvent::End { close, .. } => if let Some(sub_path) = shape.last_mut() {
+                    sub_path.closed = close;
+                },
+                _ => {},
+            }
+            if let (Some(edge), Some(sub_path)) = (Edge::from_event(event), shape.last_mut()) {
+                sub_path.curves.push(edge.to_cubic());
+            }
+        }
+
+        MorphStyle {
+            shape,
+            color: path.color,
+            line_width: match path.draw_type {
+                DrawType::Fill => 0.0,
+                DrawType::Stroke(ref opts) => opts.line_width,
+            },
+        }
+    }
+
+    fn transparent(mut self) -> MorphStyle {
+        self.color.set_a(0.0);
+        self
+    }
+}
+
+/// Match every path of `from` with a path of the same [`DrawType`] in `to`,
+/// by `id` first and in document order for the remaining ones.
+fn match_paths(from: &[PathDescriptor], to: &[PathDescriptor]) -> Vec<Option<usize>> {
+    let same_kind = |a: &PathDescriptor, b: &PathDescriptor| {
+        matches!(
+            (&a.draw_type, &b.draw_type),
+            (DrawType::Fill, DrawType::Fill) | (DrawType::Stroke(_), DrawType::Stroke(_))
+        )
+    };
+
+    let mut used = vec![false; to.len()];
+    let mut matches = vec![None; from.len()];
+    for (i, a) in from.iter().enumerate().filter(|(_, a)| !a.id.is_empty()) {
+        if let Some(j) = (0..to.len()).find(|&j| !used[j] && to[j].id == a.id && same_kind(a, &to[j])) {
+            used[j] = true;
+            matches[i] = Some(j);
+        }
+    }
+    for (i, a) in from.iter().enumerate() {
+        if matches[i].is_some() {
+            continue;
+        }
+        if let Some(j) = (0..to.len()).find(|&j| !used[j] && same_kind(a, &to[j])) {
+            used[j] = true;
+            matches[i] = Some(j);
+        }
+    }
+
+    matches
+}
+
+/// Make two styles interpolatable, by giving them the same number of sub-paths
+/// and curves.
+fn compatible(mut from: MorphStyle, mut to: MorphStyle) -> (MorphStyle, MorphStyle) {
+    // Missing sub-paths grow out of or shrink into the start of their
+    // counterpart.
+    for i in from.shape.len()..to.shape.len() {
+        from.shape.push(SubPath { start: to.shape[i].start, curves: Vec::new(), closed: to.shape[i].closed });
+    }
+    for i in to.shape.len()..from.shape.len() {
+        to.shape.push(SubPath { start: from.shape[i].start, curves: Vec::new(), closed: from.shape[i].closed });
+    }
+
+    for (a, b) in from.shape.iter_mut().zip(to.shape.iter_mut()) {
+        if a.curves.len() < b.curves.len() {
+            subdivide(a, b.curves.len());
+        } else {
+            subdivide(b, a.curves.len());
+        }
+    }
+
+    (from, to)
+}
+
+/// Split the longest curve of a sub-path in half until it has `count` curves.
+/// Sub-paths without curves get degenerate curves at their start.
+fn subdivide(sub_path: &mut SubPath, count: usize) {
+    if sub_path.curves.is_empty() {
+        let p = sub_path.start;
+        sub_path.curves = vec![CubicBezierSegment { from: p, ctrl1: p, ctrl2: p, to: p }; count];
+        return;
+    }
+
+    while sub_path.curves.len() < count {
+        let longest = (0..sub_path.curves.len())
+            .max_by(|&i, &j| {
+                let length = |k: usize| sub_path.curves[k].approximate_length(SUBDIVISION_TOLERANCE);
+                length(i).partial_cmp(&length(j)).unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .unwrap_or(0);
+        let (first, second) = sub_path.curves[longest].split(0.5);
+        sub_path.curves[longest] = first;
+        sub_path.curves.insert(longest + 1, second);
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn lerp_color(a: Color, b: Color, t: f32) -> Color {
+    Color::rgba(
+        lerp(a.r(), b.r(), t),
+        lerp(a.g(), b.g(), t),
+        lerp(a.b(), b.b(), t),
+        lerp(a.a(), b.a(), t),
+    )
+}
+
+/// Bevy system which re-tessellates the mesh of a SVG entity when the progress
+/// of its [`SvgMorph`] changes. The tessellation options and the nine-slice
+/// scaling are taken from the entity's [`Svg`].
+pub(crate) fn svg_morph(
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut fill_tess: ResMut<FillTessellator>,
+    mut stroke_tess: ResMut<StrokeTessellator>,
+    query: Query<(&Svg, &SvgMorph, &Handle<Mesh>), Changed<SvgMorph>>,
+) {
+    for (svg, morph, mesh) in query.iter() {
+        let paths: Vec<_> = morph.interpolated().collect();
+
+        let mut buffers = VertexBuffers::new();
+        for index in mesh_order(paths.iter().map(|(path, _)| path), svg.anti_alias.is_some()) {
+            let (ref path, ref segments) = paths[index];
+            tessellate_path_with(
+                &mut fill_tess, &mut stroke_tess, path, segments.iter().cloned(),
+                TessellationOptions::of(svg), &mut buffers,
+            );
+        }
+        if let Some(ref nine_slice) = svg.nine_slice {
+            nine_slice.apply(&svg.view_box, &mut buffers);
+        }
+
+        if let Some(mesh) = meshes.get_mut(mesh) {
+            *mesh = buffers.convert();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_near, fill, fill_area, load, polygon, rect, stroke};
+
+    #[test]
+    fn paths_match_by_id_then_order() {
+        let mut a = fill(rect(0.0, 0.0, 1.0, 1.0));
+        a.id = "a".to_string();
+        let b = fill(rect(0.0, 0.0, 1.0, 1.0));
+        let line = stroke(rect(0.0, 0.0, 1.0, 1.0), 1.0);
+        let mut target = fill(rect(0.0, 0.0, 2.0, 2.0));
+        target.id = "a".to_string();
+
+        let matches = match_paths(&[b.clone(), a, line.clone()], &[b, line, target]);
+        assert_eq!(matches, vec![Some(0), Some(2), Some(1)]);
+    }
+
+    #[test]
+    fn compatible_styles_have_the_same_curves() {
+        let triangle = MorphStyle::new(&fill(polygon(&[(0.0, 0.0), (10.0, 0.0), (0.0, 10.0)])));
+        let square = MorphStyle::new(&fill([rect(0.0, 0.0, 10.0, 10.0), rect(20.0, 0.0, 5.0, 5.0)].concat()));
+        let (from, to) = compatible(triangle, square);
+        assert_eq!(from.shape.len(), 2);
+        assert_eq!(to.shape.len(), 2);
+        for (a, b) in from.shape.iter().zip(to.shape.iter()) {
+            assert_eq!(a.curves.len(), b.curves.len());
+        }
+    }
+
+    #[test]
+    fn morph_ends_on_both_shapes() {
+        let from = load(r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 20 20">
+            <path d="M0 0 L10 0 L0 10 Z"/>
+        </svg>"#);
+        let to = load(r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 20 20">
+            <rect width="10" height="10" fill="red"/>
+        </svg>"#);
+
+        let morph = SvgMorph::new(&from, &to);
+        let (_, start) = morph.interpolated().next().unwrap();
+        assert_near(fill_area(&start), 50.0, 0.1);
+
+        let morph = morph.with_t(1.0);
+        let (path, end) = morph.interpolated().next().unwrap();
+        assert_near(fill_area(&end), 100.0, 0.1);
+        assert_near(path.color.r(), 1.0, 1e-3);
+    }
+
+    #[test]
+    fn unmatched_paths_fade() {
+        let from = load(r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 20 20">
+            <rect width="10" height="10"/>
+        </svg>"#);
+        let to = load(r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 20 20"/>"#);
+
+        let morph = SvgMorph::new(&from, &to).with_t(0.5);
+        let (path, _) = morph.interpolated().next().unwrap();
+        assert_near(path.color.a(), 0.5, 1e-6);
+    }
+}
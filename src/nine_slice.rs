@@ -0,0 +1,174 @@
+//! Nine-slice scaling, e.g. for UI panels and buttons.
+//!
+//! The view box of the SVG is split into nine slices by the insets. The corner
+//! slices keep their size, the edge slices are stretched along the edge and
+//! the center slice is stretched in both directions. The scaling is applied to
+//! the vertices of the tessellated mesh, so triangles crossing the border of
+//! a slice are only remapped at their vertices.
+
+use bevy::{
+    asset::{Assets, Handle},
+    ecs::{query::{ChangeTrackers, Changed}, system::{Query, ResMut}},
+    math::Vec2,
+    render::mesh::Mesh,
+};
+use lyon_svg::parser::ViewBox;
+use lyon_tessellation::{FillTessellator, StrokeTessellator};
+
+use crate::{Convert, plugin::{TessellationOptions, tessellate_svg}, svg::Svg, vertex_buffer::VertexBuffers};
+
+/// Name of the attribute of the root `<svg>` element declaring the insets,
+/// in the order of CSS's `border-image-slice`, i.e. `top right bottom left`.
+pub(crate) const NINE_SLICE_ATTRIBUTE: &str = "data-nine-slice";
+
+/// Nine-slice scaling of a [`Svg`], see [`SvgBuilder::nine_slice`](crate::svg::SvgBuilder::nine_slice).
+///
+/// Change `size` to resize the SVG at runtime, it then gets tessellated again.
+/// Filtered groups and pattern-painted paths aren't sliced, they are drawn by
+/// child entities of their own.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NineSlice {
+    /// Width of the left border, in SVG units.
+    pub left: f32,
+    /// Height of the top border, in SVG units.
+    pub top: f32,
+    /// Width of the right border, in SVG units.
+    pub right: f32,
+    /// Height of the bottom border, in SVG units.
+    pub bottom: f32,
+    /// Size the view box gets stretched to, in SVG units.
+    pub size: Vec2,
+}
+
+impl NineSlice {
+    /// Create a [`NineSlice`] with the given insets, in CSS order. The size is
+    /// set to the size of the view box when the SVG gets built.
+    pub fn new(top: f32, right: f32, bottom: f32, left: f32) -> NineSlice {
+        NineSlice { left, top, right, bottom, size: Vec2::ZERO }
+    }
+
+    /// Parse the insets from the value of a `data-nine-slice` attribute, with
+    /// one to four values like CSS margins.
+    pub(crate) fn parse(value: &str) -> Option<NineSlice> {
+        let values = value.split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|v| !v.is_empty())
+            .map(|v| v.trim_end_matches("px").parse::<f32>().ok())
+            .collect::<Option<Vec<f32>>>()?;
+
+        let (top, right, bottom, left) = match values[..] {
+            [all] => (all, all, all, all),
+            [vertical, horizontal] => (vertical, horizontal, vertical, horizontal),
+            [top, horizontal, bottom] => (top, horizontal, bottom, horizontal),
+            [top, right, bottom, left] => (top, right, bottom, left),
+            _ => return None,
+        };
+
+        Some(NineSlice::new(top, right, bottom, left))
+    }
+
+    /// Move the vertices from the view box into the nine-slice layout of
+    /// `self.size`.
+    pub(crate) fn apply(&self, view_box: &ViewBox, buffers: &mut VertexBuffers) {
+        let (x, y) = (view_box.x as f32, view_box.y as f32);
+        let (width, height) = (view_box.w as f32, view_box.h as f32);
+        for vertex in buffers.vertices.iter_mut() {
+            let [vx, vy, _] = vertex.position;
+            vertex.position[0] = x + remap(vx - x, width, self.size.x, self.left, self.right);
+            vertex.position[1] = y + remap(vy - y, height, self.size.y, self.top, self.bottom);
+        }
+    }
+}
+
+/// Remap a coordinate along one axis from `0..length` to `0..target`. When the
+/// target is smaller than both borders, the borders shrink proportionally.
+fn remap(value: f32, length: f32, target: f32, start: f32, end: f32) -> f32 {
+    let borders = start + end;
+    let shrink = if target < borders && borders > 0.0 { target / borders } else { 1.0 };
+
+    if value <= start {
+        value * shrink
+    } else if value >= length - end {
+        target - (length - value) * shrink
+    } else {
+        let middle = length - borders;
+        let stretched = (target - borders).max(0.0);
+        let scale = if middle > 0.0 { stretched / middle } else { 0.0 };
+        start * shrink + (value - start) * scale
+    }
+}
+
+/// Bevy system which tessellates the mesh of a [`Svg`] again when the size of
+/// its [`NineSlice`] changes. The mesh of newly spawned SVGs gets created by
+/// the mesh maker.
+pub(crate) fn svg_nine_slice(
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut fill_tess: ResMut<FillTessellator>,
+    mut stroke_tess: ResMut<StrokeTessellator>,
+    query: Query<(&Svg, ChangeTrackers<Svg>, &Handle<Mesh>), Changed<Svg>>,
+) {
+    for (svg, trackers, mesh) in query.iter() {
+        if svg.nine_slice.is_none() || trackers.is_added() {
+            continue;
+        }
+
+        let buffers = tessellate_svg(&mut fill_tess, &mut stroke_tess, svg, TessellationOptions::of(svg));
+
+        if let Some(mesh) = meshes.get_mut(mesh) {
+            *mesh = buffers.convert();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{test_util::assert_near, vertex_buffer::Vertex};
+
+    #[test]
+    fn parse_like_css() {
+        assert_eq!(NineSlice::parse("4"), Some(NineSlice::new(4.0, 4.0, 4.0, 4.0)));
+        assert_eq!(NineSlice::parse("1 2"), Some(NineSlice::new(1.0, 2.0, 1.0, 2.0)));
+        assert_eq!(NineSlice::parse("1, 2, 3"), Some(NineSlice::new(1.0, 2.0, 3.0, 2.0)));
+
+        let slice = NineSlice::parse("1px 2px 3px 4px").unwrap();
+        assert_eq!((slice.top, slice.right, slice.bottom, slice.left), (1.0, 2.0, 3.0, 4.0));
+
+        assert_eq!(NineSlice::parse(""), None);
+        assert_eq!(NineSlice::parse("1 2 3 4 5"), None);
+        assert_eq!(NineSlice::parse("wide"), None);
+    }
+
+    #[test]
+    fn remap_keeps_borders() {
+        // Borders of 10 and 20 on a length of 100, stretched to 200.
+        assert_near(remap(5.0, 100.0, 200.0, 10.0, 20.0), 5.0, 1e-5);
+        assert_near(remap(10.0, 100.0, 200.0, 10.0, 20.0), 10.0, 1e-5);
+        assert_near(remap(45.0, 100.0, 200.0, 10.0, 20.0), 95.0, 1e-5);
+        assert_near(remap(80.0, 100.0, 200.0, 10.0, 20.0), 180.0, 1e-5);
+        assert_near(remap(90.0, 100.0, 200.0, 10.0, 20.0), 190.0, 1e-5);
+    }
+
+    #[test]
+    fn remap_shrinks_borders() {
+        // The target is smaller than both borders together.
+        assert_near(remap(10.0, 100.0, 15.0, 10.0, 20.0), 5.0, 1e-5);
+        assert_near(remap(50.0, 100.0, 15.0, 10.0, 20.0), 5.0, 1e-5);
+        assert_near(remap(90.0, 100.0, 15.0, 10.0, 20.0), 10.0, 1e-5);
+        assert_near(remap(100.0, 100.0, 15.0, 10.0, 20.0), 15.0, 1e-5);
+    }
+
+    #[test]
+    fn apply_in_view_box() {
+        let view_box = ViewBox { x: 10.0, y: 20.0, w: 100.0, h: 50.0 };
+        let slice = NineSlice { size: Vec2::new(200.0, 100.0), ..NineSlice::new(10.0, 5.0, 10.0, 5.0) };
+
+        let mut buffers = VertexBuffers::new();
+        for &(x, y) in [(10.0, 20.0), (15.0, 30.0), (110.0, 70.0)].iter() {
+            buffers.vertices.push(Vertex { position: [x, y, 0.0], color: [1.0; 4], coverage: 1.0, uv: [0.0; 2] });
+        }
+        slice.apply(&view_box, &mut buffers);
+
+        let positions: Vec<[f32; 2]> = buffers.vertices.iter().map(|v| [v.position[0], v.position[1]]).collect();
+        assert_eq!(positions, vec![[10.0, 20.0], [15.0, 30.0], [210.0, 120.0]]);
+    }
+}
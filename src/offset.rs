@@ -0,0 +1,145 @@
+//! Offsetting of filled paths.
+//!
+//! The outline of the path is stroked with twice the offset distance. The
+//! triangles of the stroke are merged into the band around the outline, which
+//! is then added to or cut from the area of the path with a boolean operation.
+
+use bevy::log::error;
+use geo_booleanop::boolean::BooleanOp;
+use geo_types::{LineString, MultiPolygon, Polygon};
+use lyon_svg::path::PathEvent;
+use lyon_tessellation::{
+    BuffersBuilder, LineJoin, StrokeOptions, StrokeTessellator, StrokeVertex, VertexBuffers,
+    math::Point,
+};
+
+use crate::{
+    boolean::{to_polygons, to_segments},
+    svg::{DrawType, PathDescriptor},
+};
+
+impl PathDescriptor {
+    /// Grow the area of this path by `distance`, or shrink it for a negative
+    /// `distance`. Corners get the given join, like the corners of a stroke;
+    /// miter joins fall back to bevels beyond a miter limit of `4`. Curves are
+    /// flattened with the given tolerance and the area follows the even-odd
+    /// rule, like the fill tessellation.
+    ///
+    /// Unlike a stroke, the result is a [`DrawType::Fill`] path, made of
+    /// straight lines, with the color, ids and attributes of this path.
+    pub fn offset(&self, distance: f32, join: LineJoin, tolerance: f32) -> PathDescriptor {
+        PathDescriptor {
+            segments: offset(&self.segments, distance, join, tolerance),
+            draw_type: DrawType::Fill,
+            ..self.clone()
+        }
+    }
+}
+
+/// Offset the area enclosed by the segments. Every sub-path is treated as
+/// closed.
+pub(crate) fn offset(segments: &[PathEvent], distance: f32, join: LineJoin, tolerance: f32) -> Vec<PathEvent> {
+    let area = to_polygons(segments, tolerance);
+    if distance == 0.0 {
+        return to_segments(&area);
+    }
+
+    let band = band(segments, 2.0 * distance.abs(), join, tolerance);
+    let result = if distance > 0.0 { area.union(&band) } else { area.difference(&band) };
+    to_segments(&result)
+}
+
+/// The area covered by the stroke of the closed outline.
+fn band(segments: &[PathEvent], width: f32, join: LineJoin, tolerance: f32) -> MultiPolygon<f64> {
+    let closed = segments.iter().map(|event| match *event {
+        PathEvent::End { last, first, .. } => PathEvent::End { last, first, close: true },
+        event => event,
+    });
+    let opts = StrokeOptions::tolerance(tolerance)
+        .with_line_width(width)
+        .with_line_join(join);
+
+    let mut buffers: VertexBuffers<Point, u32> = VertexBuffers::new();
+    if let Err(e) = StrokeTessellator::new().tessellate(
+        closed,
+        &opts,
+        &mut BuffersBuilder::new(&mut buffers, |vertex: StrokeVertex| vertex.position()),
+    ) {
+        error!("StrokeTessellator error: {:?}", e);
+    }
+
+    let triangles: Vec<MultiPolygon<f64>> = buffers.indices.chunks_exact(3)
+        .filter_map(|triangle| {
+            let vertex = |i: usize| buffers.vertices[triangle[i] as usize];
+            let (a, b, c) = (vertex(0), vertex(1), vertex(2));
+            if (b - a).cross(c - a).abs() <= f32::EPSILON {
+                return None;
+            }
+            let ring = vec![(a.x as f64, a.y as f64), (b.x as f64, b.y as f64), (c.x as f64, c.y as f64)];
+            Some(MultiPolygon(vec![Polygon::new(LineString::from(ring), Vec::new())]))
+        })
+        .collect();
+    union_all(triangles)
+}
+
+/// Union of all polygons, merged pairwise to keep the intermediate polygons
+/// small.
+fn union_all(mut polygons: Vec<MultiPolygon<f64>>) -> MultiPolygon<f64> {
+    while polygons.len() > 1 {
+        polygons = polygons.chunks(2)
+            .map(|pair| match pair {
+                [a, b] => a.union(b),
+                [a] => a.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    polygons.pop().unwrap_or_else(|| MultiPolygon(Vec::new()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_near, fill, fill_area, rect};
+
+    const JOINS: [LineJoin; 4] = [LineJoin::Miter, LineJoin::MiterClip, LineJoin::Round, LineJoin::Bevel];
+
+    fn offset_area(distance: f32, join: LineJoin) -> f32 {
+        fill_area(&offset(&rect(0.0, 0.0, 10.0, 10.0), distance, join, 0.01))
+    }
+
+    #[test]
+    fn grow_square_with_each_join() {
+        assert_near(offset_area(1.0, LineJoin::Miter), 144.0, 1e-2);
+        assert_near(offset_area(1.0, LineJoin::MiterClip), 144.0, 1e-2);
+        // Each corner is cut by a triangle with legs of 1.
+        assert_near(offset_area(1.0, LineJoin::Bevel), 142.0, 1e-2);
+        assert_near(offset_area(1.0, LineJoin::Round), 140.0 + std::f32::consts::PI, 5e-2);
+    }
+
+    #[test]
+    fn shrink_square_with_each_join() {
+        // The inner corners of the band are sharp whatever the join.
+        for &join in JOINS.iter() {
+            assert_near(offset_area(-1.0, join), 64.0, 1e-2);
+        }
+    }
+
+    #[test]
+    fn zero_and_vanishing_offsets() {
+        for &join in JOINS.iter() {
+            assert_near(offset_area(0.0, join), 100.0, 1e-3);
+            assert_near(offset_area(-6.0, join), 0.0, 1e-3);
+        }
+    }
+
+    #[test]
+    fn offset_holes_shrink() {
+        let mut ring = rect(0.0, 0.0, 10.0, 10.0);
+        ring.extend(rect(3.0, 3.0, 4.0, 4.0));
+        let grown = fill(ring).offset(1.0, LineJoin::Miter, 0.01);
+        assert!(matches!(grown.draw_type, DrawType::Fill));
+        // 12x12 outside, the hole shrinks to 2x2.
+        assert_near(fill_area(&grown.segments), 140.0, 1e-2);
+    }
+}
@@ -0,0 +1,433 @@
+//! Pattern fills and strokes.
+//!
+//! The content of a `<pattern>` is rasterized once into a tile texture. Paths
+//! painted with the pattern are drawn as child entities of the SVG, with a
+//! shader that repeats the tile according to the pattern's units, `viewBox` and
+//! `patternTransform`. Like filtered groups, they are drawn just above the z
+//! of their path.
+
+use std::collections::HashMap;
+
+use bevy::{
+    asset::{Assets, Handle, HandleUntyped},
+    ecs::{entity::Entity, query::Added, system::{Commands, Query, ResMut}},
+    math::{Mat4, Vec3},
+    reflect::TypeUuid,
+    render::{
+        color::Color,
+        draw::{Draw, Visible},
+        mesh::Mesh,
+        pipeline::{PipelineDescriptor, RenderPipeline, RenderPipelines},
+        render_graph::base::MainPass,
+        renderer::RenderResources,
+        texture::{AddressMode, Texture},
+    },
+    transform::{components::{GlobalTransform, Transform}, hierarchy::BuildChildren},
+};
+use lyon_tessellation::{
+    FillTessellator, StrokeTessellator,
+    math::{Rect, Transform as Transform2D, point, size, vector},
+};
+use usvg::NodeExt;
+
+use crate::{
+    Convert,
+    limits::{LimitCounter, SvgLimitError},
+    plugin::{TessellationOptions, tessellate_path, tessellate_path_with},
+    raster::{Image, MAX_IMAGE_SIZE},
+    metadata::element_id,
+    svg::{DrawType, PathDescriptor, Svg, convert_path, convert_stroke},
+    vertex_buffer::VertexBuffers,
+};
+
+pub(crate) const SVG_PATTERN_PIPELINE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 3051488249164812207);
+
+/// Z offset of the pattern-painted paths relative to their own z.
+const PATTERN_DEPTH: f32 = 0.0001;
+
+/// A path of a SVG painted with a pattern.
+///
+/// The path isn't part of [`Svg::paths`], it is drawn with the pattern just
+/// above its z. Use [`SvgBuilder::z_range`](crate::svg::SvgBuilder::z_range)
+/// to keep it in document order, otherwise it is drawn on top of the rest of
+/// the SVG.
+#[derive(Clone, Debug)]
+pub struct SvgPattern {
+    /// The `id` of the `<pattern>` element.
+    pub id: String,
+    /// The painted path. Its color tints the pattern.
+    pub path: PathDescriptor,
+    /// Maps the local coordinates of the SVG mesh to the coordinates of the
+    /// tile, in which a single tile covers `0..1`.
+    pub tile_transform: Mat4,
+    tile: Image,
+}
+
+/// Material of the pattern-painted paths.
+#[derive(RenderResources, Default, TypeUuid)]
+#[uuid = "5b4f7a3e-2c61-4d0a-9f8e-3c1d2b6a7e90"]
+pub(crate) struct SvgPatternMaterial {
+    pub(crate) transform: Mat4,
+    pub(crate) texture: Handle<Texture>,
+}
+
+/// The `<pattern>` element a paint refers to, if any.
+pub(crate) fn find_pattern(tree: &usvg::Tree, paint: &usvg::Paint) -> Option<usvg::Node> {
+    let node = match *paint {
+        usvg::Paint::Link(ref id) => tree.defs_by_id(id)?,
+        _ => return None,
+    };
+    let is_pattern = matches!(*node.borrow(), usvg::NodeKind::Pattern(_));
+    if is_pattern { Some(node) } else { None }
+}
+
+impl SvgPattern {
+    /// Create the [`SvgPattern`] painting `path` with the given opacity and
+    /// rasterize its tile. `transform` maps the painted element to the SVG,
+    /// the pattern is defined in the element's coordinate system. Returns
+    /// `None` for an empty tile, which disables the paint. The painted path,
+    /// the tile and its content count towards the limits of the SVG.
+    pub(crate) fn new(
+        node: &usvg::Node,
+        mut path: PathDescriptor,
+        transform: &Transform2D,
+        opacity: f32,
+        resolution: f32,
+        counter: &mut LimitCounter,
+    ) -> Result<Option<SvgPattern>, SvgLimitError> {
+        let pattern = match *node.borrow() {
+            usvg::NodeKind::Pattern(ref pattern) => pattern.clone(),
+            _ => return Ok(None),
+        };
+        let to_element = match transform.inverse() {
+            Some(inverse) => inverse,
+            None => return Ok(None),
+        };
+        let bbox = match path.transformed(&to_element).bounds() {
+            Some(bbox) => bbox,
+            None => return Ok(None),
+        };
+
+        let r = &pattern.rect;
+        let tile = match pattern.units {
+            usvg::Units::ObjectBoundingBox => Rect::new(
+                point(bbox.min_x() + r.x() as f32 * bbox.width(), bbox.min_y() + r.y() as f32 * bbox.height()),
+                size(r.width() as f32 * bbox.width(), r.height() as f32 * bbox.height()),
+            ),
+            usvg::Units::UserSpaceOnUse => Rect::new(
+                point(r.x() as f32, r.y() as f32),
+                size(r.width() as f32, r.height() as f32),
+            ),
+        };
+        if tile.is_empty() {
+            return Ok(None);
+        }
+
+        let t = pattern.transform;
+        let pattern_transform = Transform2D::new(t.a as f32, t.b as f32, t.c as f32, t.d as f32, t.e as f32, t.f as f32);
+        let inverse = match pattern_transform.inverse() {
+            Some(inverse) => inverse,
+            None => return Ok(None),
+        };
+        let to_tile = to_element
+            .then(&inverse)
+            .then_translate(vector(-tile.min_x(), -tile.min_y()))
+            .then_scale(1.0 / tile.width(), 1.0 / tile.height());
+
+        // Maps the coordinates of the pattern's content into the tile.
+        let content_transform = match pattern.view_box {
+            Some(view_box) => Transform2D::translation(-view_box.rect.x() as f32, -view_box.rect.y() as f32)
+                .then_scale(
+                    tile.width() / view_box.rect.width() as f32,
+                    tile.height() / view_box.rect.height() as f32,
+                ),
+            None => match pattern.content_units {
+                usvg::Units::ObjectBoundingBox => Transform2D::scale(bbox.width(), bbox.height()),
+                usvg::Units::UserSpaceOnUse => Transform2D::identity(),
+            },
+        };
+
+        // The resolution is given per unit of the SVG, the tile is in units of
+        // the element.
+        let resolution = (resolution * transform.determinant().abs().sqrt())
+            .min(MAX_IMAGE_SIZE / tile.width())
+            .min(MAX_IMAGE_SIZE / tile.height());
+        let width = (tile.width() * resolution).ceil().max(1.0) as usize;
+        let height = (tile.height() * resolution).ceil().max(1.0) as usize;
+        counter.add_paths(std::iter::once(&path))?;
+        counter.add_image(width, height)?;
+        let mut image = Image::new(width, height);
+        let region = Rect::new(point(0.0, 0.0), tile.size);
+
+        let mut fill_tess = FillTessellator::new();
+        let mut stroke_tess = StrokeTessellator::new();
+        for content in content_paths(node, &content_transform) {
+            let mut buffers = VertexBuffers::new();
+            tessellate_path(&mut fill_tess, &mut stroke_tess, &content, content.segments.iter().cloned(), &mut buffers);
+            counter.add_vertices(buffers.vertices.len())?;
+            image.rasterize(&buffers, &region, resolution);
+        }
+
+        path.color = Color::rgba(1.0, 1.0, 1.0, opacity);
+        Ok(Some(SvgPattern {
+            id: pattern.id,
+            path,
+            tile_transform: Mat4::from_cols(
+                [to_tile.m11, to_tile.m12, 0.0, 0.0].into(),
+                [to_tile.m21, to_tile.m22, 0.0, 0.0].into(),
+                [0.0, 0.0, 1.0, 0.0].into(),
+                [to_tile.m31, to_tile.m32, 0.0, 1.0].into(),
+            ),
+            tile: image,
+        }))
+    }
+}
+
+/// The paths of a pattern's content, moved into the tile by `transform`. Only
+/// plain colors are supported as paint.
+fn content_paths(node: &usvg::Node, transform: &Transform2D) -> Vec<PathDescriptor> {
+    let mut paths = Vec::new();
+    for node in node.descendants() {
+        let p = match *node.borrow() {
+            usvg::NodeKind::Path(ref p) => p.clone(),
+            _ => continue,
+        };
+        let t = node.abs_transform();
+        let transform = Transform2D::new(t.a as f32, t.b as f32, t.c as f32, t.d as f32, t.e as f32, t.f as f32)
+            .then(transform);
+        // Stroke widths scale with the average scale of the transform.
+        let scale = transform.determinant().abs().sqrt();
+        let segments: Vec<_> = convert_path(&p).map(|event| event.transformed(&transform)).collect();
+        let descriptor = |color, draw_type| PathDescriptor {
+            segments: segments.clone(),
+            abs_transform: Transform::identity(),
+            color,
+            draw_type,
+            id: element_id(&p.id).to_string(),
+            group_ids: Vec::new(),
+            attributes: HashMap::new(),
+            layer: None,
+            z: 0.0,
+        };
+
+        if let Some(ref fill) = p.fill {
+            if let usvg::Paint::Color(c) = fill.paint {
+                let color = Color::rgba_u8(c.red, c.green, c.blue, fill.opacity.to_u8());
+                paths.push(descriptor(color, DrawType::Fill));
+            }
+        }
+        if let Some(ref stroke) = p.stroke {
+            if let usvg::Paint::Color(_) = stroke.paint {
+                let (color, opts) = convert_stroke(stroke);
+                let opts = opts.with_line_width(opts.line_width * scale);
+                paths.push(descriptor(color, DrawType::Stroke(opts)));
+            }
+        }
+    }
+    paths
+}
+
+/// Bevy system which spawns the [`SvgPattern`]s of newly spawned SVGs as child
+/// entities.
+pub(crate) fn svg_pattern_spawner(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut textures: ResMut<Assets<Texture>>,
+    mut materials: ResMut<Assets<SvgPatternMaterial>>,
+    mut fill_tess: ResMut<FillTessellator>,
+    mut stroke_tess: ResMut<StrokeTessellator>,
+    query: Query<(Entity, &Svg), Added<Svg>>,
+) {
+    for (entity, svg) in query.iter() {
+        if svg.patterns.is_empty() {
+            continue;
+        }
+
+        let mut children = Vec::with_capacity(svg.patterns.len());
+        for (i, pattern) in svg.patterns.iter().enumerate() {
+            let mut buffers = VertexBuffers::new();
+            tessellate_path_with(
+                &mut fill_tess, &mut stroke_tess, &pattern.path, pattern.path.segments.iter().cloned(),
+                TessellationOptions::of(svg), &mut buffers,
+            );
+
+            let mut texture = pattern.tile.to_texture();
+            texture.sampler.address_mode_u = AddressMode::Repeat;
+            texture.sampler.address_mode_v = AddressMode::Repeat;
+            let material = materials.add(SvgPatternMaterial {
+                transform: pattern.tile_transform,
+                texture: textures.add(texture),
+            });
+
+            let transform = Transform::from_translation(Vec3::new(0.0, 0.0, PATTERN_DEPTH * (i + 1) as f32));
+            children.push((
+                meshes.add(buffers.convert()),
+                material,
+                MainPass,
+                Draw::default(),
+                Visible { is_visible: true, is_transparent: true },
+                RenderPipelines::from_pipelines(vec![RenderPipeline::new(SVG_PATTERN_PIPELINE_HANDLE.typed())]),
+                transform,
+                GlobalTransform::from(transform),
+            ));
+        }
+
+        commands.entity(entity).with_children(|parent| {
+            for child in children {
+                parent.spawn_bundle(child);
+            }
+        });
+    }
+}
+
+pub(crate) const PATTERN_VERTEX_SHADER: &str = r#"
+#version 450
+layout(location = 0) in vec3 Vertex_Position;
+layout(location = 1) in vec4 Vertex_Color;
+layout(location = 2) in float Vertex_Coverage;
+
+layout(location = 0) out vec4 v_color;
+layout(location = 1) out float v_coverage;
+layout(location = 2) out vec2 v_uv;
+
+layout(set = 0, binding = 0) uniform CameraViewProj {
+    mat4 ViewProj;
+};
+layout(set = 1, binding = 0) uniform Transform {
+    mat4 Model;
+};
+layout(set = 2, binding = 0) uniform SvgPatternMaterial_transform {
+    mat4 PatternTransform;
+};
+
+void main() {
+    gl_Position = ViewProj * Model * vec4(Vertex_Position, 1.0);
+    v_color = Vertex_Color;
+    v_coverage = Vertex_Coverage;
+    v_uv = (PatternTransform * vec4(Vertex_Position.xy, 0.0, 1.0)).xy;
+}
+"#;
+
+pub(crate) const PATTERN_FRAGMENT_SHADER: &str = r#"
+#version 450
+layout(location = 0) in vec4 v_color;
+layout(location = 1) in float v_coverage;
+layout(location = 2) in vec2 v_uv;
+layout(location = 0) out vec4 o_Target;
+
+layout(set = 2, binding = 1) uniform texture2D SvgPatternMaterial_texture;
+layout(set = 2, binding = 2) uniform sampler SvgPatternMaterial_texture_sampler;
+
+void main() {
+    vec4 tile = texture(sampler2D(SvgPatternMaterial_texture, SvgPatternMaterial_texture_sampler), v_uv);
+    o_Target = vec4(tile.rgb * v_color.rgb, tile.a * v_color.a * v_coverage);
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_near, load};
+
+    fn to_tile(pattern: &SvgPattern, x: f32, y: f32) -> (f32, f32) {
+        let p = pattern.tile_transform.transform_point3(Vec3::new(x, y, 0.0));
+        (p.x, p.y)
+    }
+
+    #[test]
+    fn user_space_tile() {
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <pattern id="checker" width="10" height="10" patternUnits="userSpaceOnUse">
+                <rect width="5" height="5" fill="red"/>
+            </pattern>
+            <rect width="100" height="100" fill="url(#checker)" fill-opacity="0.5"/>
+        </svg>"#);
+        // The pattern's content is only drawn into the tile.
+        assert!(svg.paths.is_empty());
+        let pattern = &svg.patterns[0];
+        assert_eq!(pattern.id, "checker");
+        assert_eq!(pattern.path.color, Color::rgba(1.0, 1.0, 1.0, 0.5));
+
+        assert_eq!((pattern.tile.width, pattern.tile.height), (10, 10));
+        assert_eq!(pattern.tile.pixels[0], [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(pattern.tile.pixels[4 * 10 + 4], [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(pattern.tile.pixels[5 * 10 + 5], [0.0; 4]);
+
+        let (u, v) = to_tile(pattern, 15.0, 5.0);
+        assert_near(u, 1.5, 1e-5);
+        assert_near(v, 0.5, 1e-5);
+    }
+
+    #[test]
+    fn bounding_box_tile() {
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <pattern id="stripes" width="0.5" height="0.5">
+                <rect width="20" height="5" fill="blue"/>
+            </pattern>
+            <rect x="20" y="20" width="40" height="20" fill="url(#stripes)"/>
+        </svg>"#);
+        let pattern = &svg.patterns[0];
+        assert_eq!((pattern.tile.width, pattern.tile.height), (20, 10));
+        assert_eq!(pattern.tile.pixels[4 * 20 + 19], [0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(pattern.tile.pixels[5 * 20], [0.0; 4]);
+
+        for &(x, y, u, v) in [(20.0, 20.0, 0.0, 0.0), (40.0, 30.0, 1.0, 1.0), (60.0, 40.0, 2.0, 2.0)].iter() {
+            let (actual_u, actual_v) = to_tile(pattern, x, y);
+            assert_near(actual_u, u, 1e-5);
+            assert_near(actual_v, v, 1e-5);
+        }
+    }
+
+    #[test]
+    fn view_box_and_pattern_transform() {
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <pattern id="dots" width="10" height="10" patternUnits="userSpaceOnUse"
+                     viewBox="0 0 2 2" patternTransform="translate(5 0)">
+                <rect width="1" height="1" fill="lime"/>
+            </pattern>
+            <rect width="100" height="100" fill="url(#dots)"/>
+        </svg>"#);
+        let pattern = &svg.patterns[0];
+        // The view box scales the content by 5.
+        assert_eq!(pattern.tile.pixels[4 * 10 + 4], [0.0, 1.0, 0.0, 1.0]);
+        assert_eq!(pattern.tile.pixels[5 * 10 + 5], [0.0; 4]);
+
+        let (u, v) = to_tile(pattern, 5.0, 0.0);
+        assert_near(u, 0.0, 1e-5);
+        assert_near(v, 0.0, 1e-5);
+    }
+
+    #[test]
+    fn empty_tile_disables_the_paint() {
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <pattern id="none" width="0" height="10" patternUnits="userSpaceOnUse">
+                <rect width="5" height="5"/>
+            </pattern>
+            <rect width="100" height="100" fill="url(#none)"/>
+        </svg>"#);
+        assert!(svg.patterns.is_empty());
+        assert!(svg.paths.is_empty());
+    }
+
+    #[test]
+    fn tile_in_element_coordinates() {
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <pattern id="checker" width="10" height="10" patternUnits="userSpaceOnUse">
+                <rect width="5" height="5" fill="red"/>
+            </pattern>
+            <g transform="translate(50 50) scale(2)">
+                <rect width="20" height="20" fill="url(#checker)"/>
+            </g>
+        </svg>"#);
+        let pattern = &svg.patterns[0];
+        // The resolution is per unit of the SVG, the element is scaled by 2.
+        assert_eq!((pattern.tile.width, pattern.tile.height), (20, 20));
+
+        for &(x, y, u, v) in [(50.0, 50.0, 0.0, 0.0), (70.0, 70.0, 1.0, 1.0), (90.0, 60.0, 2.0, 0.5)].iter() {
+            let (actual_u, actual_v) = to_tile(pattern, x, y);
+            assert_near(actual_u, u, 1e-5);
+            assert_near(actual_v, v, 1e-5);
+        }
+    }
+}
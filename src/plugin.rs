@@ -11,29 +11,62 @@
 //! that creates a mesh for each entity that has been spawned as a
 //! `SvgBundle`.
 
-use crate::{Convert, svg::{DrawType, Svg}, vertex_buffer::{VertexBuffers, VertexConstructor}};
+use crate::{
+    Convert,
+    animation::svg_animation_player,
+    anti_alias::{fill_fringe, stroke_fringe},
+    batch::{SvgBatches, svg_batcher},
+    filter::svg_filter_spawner,
+    level::{SvgObjectRegistry, svg_level_spawner},
+    loader::{SvgAssetLoader, svg_asset_spawner},
+    lod::svg_lod,
+    morph::svg_morph,
+    nine_slice::svg_nine_slice,
+    pattern::{
+        PATTERN_FRAGMENT_SHADER, PATTERN_VERTEX_SHADER, SVG_PATTERN_PIPELINE_HANDLE, SvgPatternMaterial,
+        svg_pattern_spawner,
+    },
+    reveal::svg_stroke_reveal,
+    svg::{DrawType, PathDescriptor, Svg, UvMode},
+    ui::svg_ui_layout,
+    vertex_buffer::{VertexBuffers, VertexConstructor},
+};
 use bevy::{
     app::{AppBuilder, Plugin}, asset::{Assets, Handle},
     asset::{AddAsset, HandleUntyped},
     ecs::{
         query::Added,
-        schedule::{StageLabel, SystemStage},
+        schedule::{ParallelSystemDescriptorCoercion, StageLabel, SystemLabel, SystemStage},
         system::{IntoSystem, Query, ResMut}
     },
     log::error,
     reflect::TypeUuid,
     render::{
         draw::Visible, mesh::Mesh,
-        pipeline::PipelineDescriptor,
+        pipeline::{PipelineDescriptor, RenderPipelines},
         render_graph::{AssetRenderResourcesNode, base, RenderGraph},
         renderer::RenderResources,
         shader::{Shader, ShaderStage, ShaderStages}
     },
+    transform::TransformSystem,
+    ui::{self, UiSystem},
+};
+use lyon_svg::path::PathEvent;
+use lyon_tessellation::{
+    self, BuffersBuilder, FillOptions, FillTessellator, StrokeTessellator,
+    math::{Rect, point, size},
 };
-use lyon_tessellation::{self, BuffersBuilder, FillOptions, FillTessellator, StrokeTessellator};
 
 pub const SVG_PIPELINE_HANDLE: HandleUntyped = HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 8514826620251853414);
 
+/// Pipeline of anti-aliased SVGs. It doesn't write depth, so that the mostly
+/// transparent fringe doesn't hide what gets drawn behind it afterwards.
+pub(crate) const SVG_ANTI_ALIAS_PIPELINE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 13624097522815294071);
+
+/// Tolerance used to flatten curves when tessellating filled paths.
+pub(crate) const FILL_TOLERANCE: f32 = 0.001;
+
 /// Stages for this plugin.
 #[derive(Debug, Hash, PartialEq, Eq, Clone, StageLabel)]
 pub enum Stage {
@@ -41,6 +74,17 @@ pub enum Stage {
     SVG,
 }
 
+/// Labels for the systems of this plugin.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum SvgSystem {
+    /// System that tessellates newly spawned [`SvgBundle`](crate::bundle::SvgBundle)s
+    /// into meshes.
+    MeshMaker,
+    /// System that tessellates the meshes of [`Svg`]s with a
+    /// [`NineSlice`](crate::prelude::NineSlice) again when they get resized.
+    NineSlice,
+}
+
 /// A plugin that provides resources and a system to draw [`SvgBundle`]s in Bevy with..
 pub struct SvgPlugin;
 
@@ -49,15 +93,56 @@ impl Plugin for SvgPlugin {
         let fill_tess = FillTessellator::new();
         let stroke_tess = StrokeTessellator::new();
         app.add_asset::<SvgMaterial>()
+            .add_asset::<Svg>()
+            .add_asset::<SvgPatternMaterial>()
+            .init_asset_loader::<SvgAssetLoader>()
             .insert_resource(fill_tess)
             .insert_resource(stroke_tess)
+            .init_resource::<SvgObjectRegistry>()
+            .init_resource::<SvgBatches>()
             .add_startup_system(setup.system())
             .add_stage_after(
                 bevy::app::CoreStage::Update,
                 Stage::SVG,
                 SystemStage::parallel(),
             )
-            .add_system_to_stage(Stage::SVG, svg_mesh_maker.system());
+            .add_system_to_stage(Stage::SVG, svg_asset_spawner.system())
+            .add_system_to_stage(Stage::SVG, svg_mesh_maker.system().label(SvgSystem::MeshMaker))
+            .add_system_to_stage(Stage::SVG, svg_level_spawner.system())
+            .add_system_to_stage(Stage::SVG, svg_filter_spawner.system())
+            .add_system_to_stage(Stage::SVG, svg_pattern_spawner.system())
+            .add_system_to_stage(
+                Stage::SVG,
+                // A changed `Svg` is tessellated again by the nine-slice system,
+                // the revealed mesh has to replace that one.
+                svg_stroke_reveal.system().after(SvgSystem::MeshMaker).after(SvgSystem::NineSlice),
+            )
+            .add_system_to_stage(Stage::SVG, svg_animation_player.system().after(SvgSystem::MeshMaker))
+            .add_system_to_stage(Stage::SVG, svg_morph.system().after(SvgSystem::MeshMaker))
+            .add_system_to_stage(Stage::SVG, svg_nine_slice.system().label(SvgSystem::NineSlice).after(SvgSystem::MeshMaker))
+            .add_system_to_stage(Stage::SVG, svg_lod.system().after(SvgSystem::MeshMaker))
+            .add_system_to_stage(
+                bevy::app::CoreStage::PostUpdate,
+                svg_ui_layout.system()
+                    .after(UiSystem::Flex)
+                    .before(TransformSystem::TransformPropagate),
+            )
+            .add_system_to_stage(
+                bevy::app::CoreStage::PostUpdate,
+                svg_batcher.system().after(TransformSystem::TransformPropagate),
+            );
+
+        #[cfg(feature = "collider")]
+        app.add_system_to_stage(
+            Stage::SVG,
+            crate::collider::svg_collider_maker.system(),
+        );
+
+        #[cfg(feature = "lottie")]
+        app.add_system_to_stage(
+            Stage::SVG,
+            crate::lottie::lottie_player.system().after(SvgSystem::MeshMaker),
+        );
     }
 }
 
@@ -67,13 +152,17 @@ fn setup(
     mut render_graph: ResMut<RenderGraph>,
 ) {
     // Create a new shader pipeline
-    pipelines.set_untracked(
-        SVG_PIPELINE_HANDLE,
-        PipelineDescriptor::default_config(ShaderStages {
-            vertex: shaders.add(Shader::from_glsl(ShaderStage::Vertex, VERTEX_SHADER)),
-            fragment: Some(shaders.add(Shader::from_glsl(ShaderStage::Fragment, FRAGMENT_SHADER))),
-        })
-    );
+    let shader_stages = ShaderStages {
+        vertex: shaders.add(Shader::from_glsl(ShaderStage::Vertex, SVG_VERTEX_SHADER)),
+        fragment: Some(shaders.add(Shader::from_glsl(ShaderStage::Fragment, SVG_FRAGMENT_SHADER))),
+    };
+    pipelines.set_untracked(SVG_PIPELINE_HANDLE, PipelineDescriptor::default_config(shader_stages.clone()));
+
+    let mut anti_alias = PipelineDescriptor::default_config(shader_stages);
+    if let Some(ref mut depth_stencil) = anti_alias.depth_stencil {
+        depth_stencil.depth_write_enabled = false;
+    }
+    pipelines.set_untracked(SVG_ANTI_ALIAS_PIPELINE_HANDLE, anti_alias);
 
     // Add an AssetRenderResourcesNode to our Render Graph. This will bind MyMaterialWithVertexColorSupport resources to our shader
     render_graph.add_system_node(
@@ -88,67 +177,203 @@ fn setup(
             base::node::MAIN_PASS,
         )
         .unwrap();
+
+    // SVGs in UI nodes are drawn by the UI pass, which only exists when the
+    // `UiPlugin` was added.
+    render_graph.add_node_edge("svg_material", ui::node::UI_PASS).ok();
+
+    // Paths painted with a pattern sample the tile texture of their material.
+    pipelines.set_untracked(
+        SVG_PATTERN_PIPELINE_HANDLE,
+        PipelineDescriptor::default_config(ShaderStages {
+            vertex: shaders.add(Shader::from_glsl(ShaderStage::Vertex, PATTERN_VERTEX_SHADER)),
+            fragment: Some(shaders.add(Shader::from_glsl(ShaderStage::Fragment, PATTERN_FRAGMENT_SHADER))),
+        })
+    );
+    render_graph.add_system_node(
+        "svg_pattern_material",
+        AssetRenderResourcesNode::<SvgPatternMaterial>::new(true),
+    );
+    render_graph
+        .add_node_edge(
+            "svg_pattern_material",
+            base::node::MAIN_PASS,
+        )
+        .unwrap();
 }
 
 /// Bevy system which queries all [`SvgBundle`]s to complete them with a mesh and material.
+/// Anti-aliased SVGs with the default pipeline switch to one without depth writes.
 fn svg_mesh_maker(
     mut meshes: ResMut<Assets<Mesh>>,
     mut fill_tess: ResMut<FillTessellator>,
     mut stroke_tess: ResMut<StrokeTessellator>,
     mut query: Query<
-        (&mut Svg, &mut Handle<Mesh>, &mut Visible),
+        (&Svg, &mut Handle<Mesh>, &mut Visible, Option<&mut RenderPipelines>),
         Added<Svg>
     >,
 ) {
-    for (mut svg, mut mesh, mut visible) in query.iter_mut() {
-        let mut buffers = VertexBuffers::new();
-
-        //TODO: still need to do something about the color, it is pretty washed out
-        let mut color = None;
-        // Convert path descriptors into vertices and afterwards drop the descriptor
-        // to save memory. If one really needs to access the paths again, then they
-        // can be loaded with the `Svg` struct.
-        while let Some(path) = svg.paths.pop() {
-            if color.is_none() {
-                color = Some(path.color);
-            }
-            match path.draw_type {
-                DrawType::Fill => {
-                    if let Err(e) = fill_tess.tessellate(
-                        path.segments.clone(),
-                        &FillOptions::tolerance(0.001),
-                        &mut BuffersBuilder::new(&mut buffers, VertexConstructor { color: path.color })
-                    ) {
-                        error!("FillTessellator error: {:?}", e)
-                    }
-                },
-                DrawType::Stroke(opts) => {
-                    if let Err(e) = stroke_tess.tessellate(
-                        path.segments.clone(),
-                        &opts,
-                        &mut BuffersBuilder::new(&mut buffers, VertexConstructor { color: path.color })
-                    ) {
-                        error!("StrokeTessellator error: {:?}", e)
-                    }
+    for (svg, mut mesh, mut visible, render_pipelines) in query.iter_mut() {
+        if let (Some(_), Some(mut render_pipelines)) = (svg.anti_alias, render_pipelines) {
+            for render_pipeline in render_pipelines.pipelines.iter_mut() {
+                if render_pipeline.pipeline == SVG_PIPELINE_HANDLE.typed() {
+                    render_pipeline.pipeline = SVG_ANTI_ALIAS_PIPELINE_HANDLE.typed();
                 }
             }
         }
 
+        //TODO: still need to do something about the color, it is pretty washed out
+        // The path descriptors are kept on the `Svg`, so that they can be queried
+        // and re-tessellated later on.
+        let buffers = tessellate_svg(&mut fill_tess, &mut stroke_tess, svg, TessellationOptions::of(svg));
         *mesh = meshes.add(buffers.convert());
         visible.is_visible = true;
     }
 }
 
+/// Indices of `paths` in the order they get tessellated into a mesh. With
+/// depth writes the topmost path comes first, so that it wins the depth test
+/// against the paths below it at the same z. Anti-aliased meshes are drawn
+/// without depth writes, so their paths blend in document order, sorted by z.
+pub(crate) fn mesh_order<'a>(paths: impl IntoIterator<Item = &'a PathDescriptor>, anti_alias: bool) -> Vec<usize> {
+    let z: Vec<f32> = paths.into_iter().map(|path| path.z).collect();
+    let mut order: Vec<usize> = (0..z.len()).collect();
+    if anti_alias {
+        order.sort_by(|&a, &b| z[a].partial_cmp(&z[b]).unwrap_or(std::cmp::Ordering::Equal));
+    } else {
+        order.reverse();
+    }
+    order
+}
+
+/// Tessellate [`Svg::paths`] into a single mesh and apply the nine-slice
+/// scaling. Paths of filtered groups and pattern-painted paths are drawn by
+/// their own child entities, so they aren't part of it.
+pub(crate) fn tessellate_svg(
+    fill_tess: &mut FillTessellator,
+    stroke_tess: &mut StrokeTessellator,
+    svg: &Svg,
+    options: TessellationOptions,
+) -> VertexBuffers {
+    let mut buffers = VertexBuffers::new();
+    for index in mesh_order(&svg.paths, svg.anti_alias.is_some()) {
+        let path = &svg.paths[index];
+        tessellate_path_with(fill_tess, stroke_tess, path, path.segments.iter().cloned(), options, &mut buffers);
+    }
+    if let Some(ref nine_slice) = svg.nine_slice {
+        nine_slice.apply(&svg.view_box, &mut buffers);
+    }
+    buffers
+}
+
+/// Tessellate a single path into `buffers`. The given segments are used instead
+/// of the path's own, which allows to draw a modified version of the path.
+pub(crate) fn tessellate_path(
+    fill_tess: &mut FillTessellator,
+    stroke_tess: &mut StrokeTessellator,
+    path: &PathDescriptor,
+    segments: impl IntoIterator<Item = PathEvent>,
+    buffers: &mut VertexBuffers,
+) {
+    tessellate_path_with(fill_tess, stroke_tess, path, segments, TessellationOptions::default(), buffers);
+}
+
+/// Optional settings for [`tessellate_path_with`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct TessellationOptions {
+    /// Tolerance used to flatten curves instead of the default one.
+    pub(crate) tolerance: Option<f32>,
+    /// Width of the anti-aliasing fringe around the outline.
+    pub(crate) fringe: Option<f32>,
+    /// Rectangle the UVs are normalized to, the bounds of each path if `None`.
+    pub(crate) uv_bounds: Option<Rect>,
+}
+
+impl TessellationOptions {
+    /// The options to tessellate the paths of the given [`Svg`].
+    pub(crate) fn of(svg: &Svg) -> TessellationOptions {
+        let uv_bounds = match svg.uv_mode {
+            UvMode::ViewBox => Some(Rect::new(
+                point(svg.view_box.x as f32, svg.view_box.y as f32),
+                size(svg.view_box.w as f32, svg.view_box.h as f32),
+            )),
+            UvMode::PathBounds => None,
+        };
+        TessellationOptions { tolerance: None, fringe: svg.anti_alias, uv_bounds }
+    }
+}
+
+/// Same as [`tessellate_path`], but with the given [`TessellationOptions`].
+pub(crate) fn tessellate_path_with(
+    fill_tess: &mut FillTessellator,
+    stroke_tess: &mut StrokeTessellator,
+    path: &PathDescriptor,
+    segments: impl IntoIterator<Item = PathEvent>,
+    options: TessellationOptions,
+    buffers: &mut VertexBuffers,
+) {
+    let first_vertex = buffers.vertices.len();
+    // The fringe needs the segments a second time.
+    let segments: Vec<PathEvent> = segments.into_iter().collect();
+    match path.draw_type {
+        DrawType::Fill => {
+            let tolerance = options.tolerance.unwrap_or(FILL_TOLERANCE);
+            if let Err(e) = fill_tess.tessellate(
+                segments.iter().cloned(),
+                &FillOptions::tolerance(tolerance),
+                &mut BuffersBuilder::new(buffers, VertexConstructor { color: path.color })
+            ) {
+                error!("FillTessellator error: {:?}", e)
+            }
+            if let Some(width) = options.fringe {
+                fill_fringe(&segments, path.color, width, tolerance, buffers);
+            }
+        },
+        DrawType::Stroke(ref opts) => {
+            let opts = match options.tolerance {
+                Some(tolerance) => opts.with_tolerance(tolerance),
+                None => *opts,
+            };
+            if let Err(e) = stroke_tess.tessellate(
+                segments.iter().cloned(),
+                &opts,
+                &mut BuffersBuilder::new(buffers, VertexConstructor { color: path.color })
+            ) {
+                error!("StrokeTessellator error: {:?}", e)
+            }
+            if let Some(width) = options.fringe {
+                stroke_fringe(stroke_tess, &segments, &opts, path.color, width, buffers);
+            }
+        }
+    }
+
+    let uv_bounds = options.uv_bounds.or_else(|| path.stroke_bounds()).unwrap_or_default();
+    let uv = |value: f32, min: f32, length: f32| if length > 0.0 { (value - min) / length } else { 0.0 };
+    for vertex in buffers.vertices[first_vertex..].iter_mut() {
+        vertex.position[2] = path.z;
+        vertex.uv = [
+            uv(vertex.position[0], uv_bounds.min_x(), uv_bounds.width()),
+            uv(vertex.position[1], uv_bounds.min_y(), uv_bounds.height()),
+        ];
+    }
+}
+
 #[derive(RenderResources, Default, TypeUuid)]
 #[uuid = "d2c5985d-e221-4257-9e3b-ff0fb87e28ba"]
 pub struct SvgMaterial;
 
-const VERTEX_SHADER: &str = r#"
+/// The vertex shader of SVG meshes, to pair with a custom fragment shader. See
+/// the [`material`](crate::material) module for its inputs and outputs.
+pub const SVG_VERTEX_SHADER: &str = r#"
 #version 450
 layout(location = 0) in vec3 Vertex_Position;
 layout(location = 1) in vec4 Vertex_Color;
+layout(location = 2) in float Vertex_Coverage;
+layout(location = 3) in vec2 Vertex_Uv;
 
 layout(location = 0) out vec4 v_color;
+layout(location = 1) out float v_coverage;
+layout(location = 2) out vec2 v_uv;
 
 layout(set = 0, binding = 0) uniform CameraViewProj {
     mat4 ViewProj;
@@ -160,15 +385,109 @@ layout(set = 1, binding = 0) uniform Transform {
 void main() {
     gl_Position = ViewProj * Model * vec4(Vertex_Position, 1.0);
     v_color = Vertex_Color;
+    v_coverage = Vertex_Coverage;
+    v_uv = Vertex_Uv;
 }
 "#;
 
-const FRAGMENT_SHADER: &str = r#"
+/// The fragment shader of SVG meshes.
+pub const SVG_FRAGMENT_SHADER: &str = r#"
 #version 450
 layout(location = 0) in vec4 v_color;
+layout(location = 1) in float v_coverage;
 layout(location = 0) out vec4 o_Target;
 
 void main() {
-    o_Target = v_color;
+    o_Target = vec4(v_color.rgb, v_color.a * v_coverage);
 }
 "#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{fill, load, rect, stroke};
+
+    fn tessellate(path: &PathDescriptor, options: TessellationOptions) -> VertexBuffers {
+        let mut buffers = VertexBuffers::new();
+        tessellate_path_with(
+            &mut FillTessellator::new(), &mut StrokeTessellator::new(), path, path.segments.iter().cloned(),
+            options, &mut buffers,
+        );
+        buffers
+    }
+
+    #[test]
+    fn vertices_get_the_z_and_uvs_of_the_path() {
+        let mut path = fill(rect(10.0, 10.0, 20.0, 10.0));
+        path.z = 2.5;
+        let buffers = tessellate(&path, TessellationOptions::default());
+        assert!(!buffers.indices.is_empty());
+        for vertex in buffers.vertices.iter() {
+            assert_eq!(vertex.position[2], 2.5);
+            assert_eq!(vertex.uv, [(vertex.position[0] - 10.0) / 20.0, (vertex.position[1] - 10.0) / 10.0]);
+        }
+
+        let options = TessellationOptions {
+            uv_bounds: Some(Rect::new(point(0.0, 0.0), size(40.0, 40.0))),
+            ..Default::default()
+        };
+        for vertex in tessellate(&path, options).vertices.iter() {
+            assert_eq!(vertex.uv, [vertex.position[0] / 40.0, vertex.position[1] / 40.0]);
+        }
+    }
+
+    #[test]
+    fn fringe_adds_transparent_vertices() {
+        let path = stroke(rect(0.0, 0.0, 10.0, 10.0), 1.0);
+        let plain = tessellate(&path, TessellationOptions::default());
+        assert!(plain.vertices.iter().all(|vertex| vertex.coverage == 1.0));
+
+        let smooth = tessellate(&path, TessellationOptions { fringe: Some(0.5), ..Default::default() });
+        assert!(smooth.vertices.len() > plain.vertices.len());
+        assert!(smooth.vertices.iter().any(|vertex| vertex.coverage == 0.0));
+    }
+
+    #[test]
+    fn anti_aliased_paths_get_drawn_in_document_order() {
+        let mut svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+            <rect width="10" height="10" fill="red"/>
+            <rect x="2" y="2" width="6" height="6" fill="blue"/>
+        </svg>"#);
+        let is_red = |buffers: &VertexBuffers, triangle: usize| {
+            let color = buffers.vertices[buffers.indices[3 * triangle] as usize].color;
+            color[0] > color[2]
+        };
+        let triangles = |buffers: &VertexBuffers| buffers.indices.len() / 3;
+
+        // With depth writes the top-most path comes first.
+        let options = TessellationOptions::of(&svg);
+        let buffers = tessellate_svg(&mut FillTessellator::new(), &mut StrokeTessellator::new(), &svg, options);
+        assert!(!is_red(&buffers, 0));
+        assert!(is_red(&buffers, triangles(&buffers) - 1));
+
+        // Without them the paths blend over each other, from the bottom up.
+        svg.anti_alias = Some(0.5);
+        let options = TessellationOptions::of(&svg);
+        let buffers = tessellate_svg(&mut FillTessellator::new(), &mut StrokeTessellator::new(), &svg, options);
+        assert!(is_red(&buffers, 0));
+        assert!(!is_red(&buffers, triangles(&buffers) - 1));
+    }
+
+    #[test]
+    fn mesh_order_sorts_by_z_when_anti_aliased() {
+        let mut paths = vec![fill(rect(0.0, 0.0, 1.0, 1.0)); 3];
+        paths[0].z = 2.0;
+        paths[2].z = 1.0;
+        assert_eq!(mesh_order(&paths, false), vec![2, 1, 0]);
+        assert_eq!(mesh_order(&paths, true), vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn options_of_svg() {
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="5 5 10 20"/>"#);
+        let options = TessellationOptions::of(&svg);
+        assert_eq!(options.uv_bounds, Some(Rect::new(point(5.0, 5.0), size(10.0, 20.0))));
+        assert_eq!(options.fringe, None);
+        assert_eq!(options.tolerance, None);
+    }
+}
@@ -0,0 +1,151 @@
+//! A minimal CPU rasterizer for the parts of a SVG that are drawn as textures.
+
+use bevy::render::texture::{Extent3d, Texture, TextureDimension, TextureFormat};
+use lyon_tessellation::math::Rect;
+
+use crate::vertex_buffer::VertexBuffers;
+
+/// Maximum width and height of a rasterized image in pixels. Larger images are
+/// rendered at a lower resolution.
+pub(crate) const MAX_IMAGE_SIZE: f32 = 4096.0;
+
+/// Sample positions within a pixel used for anti-aliasing the rasterization.
+const SAMPLES: [(f32, f32); 4] = [(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)];
+
+/// An RGBA image with premultiplied alpha, row by row from the top.
+#[derive(Clone, Debug)]
+pub(crate) struct Image {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    pub(crate) pixels: Vec<[f32; 4]>,
+}
+
+impl Image {
+    pub(crate) fn new(width: usize, height: usize) -> Image {
+        Image::filled(width, height, [0.0; 4])
+    }
+
+    pub(crate) fn filled(width: usize, height: usize, color: [f32; 4]) -> Image {
+        Image { width, height, pixels: vec![color; width * height] }
+    }
+
+    pub(crate) fn map(&self, f: impl Fn([f32; 4]) -> [f32; 4]) -> Image {
+        Image { width: self.width, height: self.height, pixels: self.pixels.iter().map(|p| f(*p)).collect() }
+    }
+
+    /// Draw the triangles of `buffers` over the image, which covers `region`
+    /// at `resolution` pixels per unit.
+    pub(crate) fn rasterize(&mut self, buffers: &VertexBuffers, region: &Rect, resolution: f32) {
+        let to_pixels = |index: u32| {
+            let [x, y, _] = buffers.vertices[index as usize].position;
+            ((x - region.min_x()) * resolution, (y - region.min_y()) * resolution)
+        };
+        let edge = |(ax, ay): (f32, f32), (bx, by): (f32, f32), (px, py): (f32, f32)| {
+            (bx - ax) * (py - ay) - (by - ay) * (px - ax)
+        };
+
+        for triangle in buffers.indices.chunks_exact(3) {
+            let (a, b, c) = (to_pixels(triangle[0]), to_pixels(triangle[1]), to_pixels(triangle[2]));
+            let area = edge(a, b, c);
+            if area.abs() <= f32::EPSILON {
+                continue;
+            }
+
+            let color = buffers.vertices[triangle[0] as usize].color;
+            let x0 = a.0.min(b.0).min(c.0).floor().max(0.0) as usize;
+            let y0 = a.1.min(b.1).min(c.1).floor().max(0.0) as usize;
+            let x1 = (a.0.max(b.0).max(c.0).ceil().max(0.0) as usize).min(self.width);
+            let y1 = (a.1.max(b.1).max(c.1).ceil().max(0.0) as usize).min(self.height);
+
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let covered = SAMPLES.iter()
+                        .map(|(sx, sy)| (x as f32 + sx, y as f32 + sy))
+                        .filter(|&p| edge(b, c, p) / area >= 0.0 && edge(c, a, p) / area >= 0.0 && edge(a, b, p) / area >= 0.0)
+                        .count();
+                    if covered == 0 {
+                        continue;
+                    }
+
+                    let alpha = color[3] * covered as f32 / SAMPLES.len() as f32;
+                    let pixel = &mut self.pixels[y * self.width + x];
+                    for i in 0..3 {
+                        pixel[i] = color[i] * alpha + pixel[i] * (1.0 - alpha);
+                    }
+                    pixel[3] = alpha + pixel[3] * (1.0 - alpha);
+                }
+            }
+        }
+    }
+
+    /// Convert the image to a sRGB texture with straight alpha.
+    pub(crate) fn to_texture(&self) -> Texture {
+        let mut data = Vec::with_capacity(self.pixels.len() * 4);
+        for &[r, g, b, a] in self.pixels.iter() {
+            let straight = |c: f32| if a > 0.0 { (c / a).max(0.0).min(1.0) } else { 0.0 };
+            data.extend_from_slice(&[
+                (straight(r) * 255.0).round() as u8,
+                (straight(g) * 255.0).round() as u8,
+                (straight(b) * 255.0).round() as u8,
+                (a.max(0.0).min(1.0) * 255.0).round() as u8,
+            ]);
+        }
+        Texture::new(
+            Extent3d::new(self.width as u32, self.height as u32, 1),
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rgba8UnormSrgb,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lyon_tessellation::math::{point, size};
+
+    use crate::vertex_buffer::Vertex;
+
+    /// Two triangles covering a rectangle. The diagonal doesn't pass through
+    /// any sample position for the rectangles used below.
+    fn rectangle(x0: f32, y0: f32, x1: f32, y1: f32, color: [f32; 4]) -> VertexBuffers {
+        let mut buffers = VertexBuffers::new();
+        for &(x, y) in [(x0, y0), (x1, y0), (x1, y1), (x0, y1)].iter() {
+            buffers.vertices.push(Vertex { position: [x, y, 0.0], color, coverage: 1.0, uv: [0.0; 2] });
+        }
+        buffers.indices.extend_from_slice(&[0, 1, 2, 0, 2, 3]);
+        buffers
+    }
+
+    #[test]
+    fn rasterize_covers_pixels_within_the_region() {
+        let mut image = Image::new(8, 8);
+        let region = Rect::new(point(10.0, 10.0), size(4.0, 4.0));
+        image.rasterize(&rectangle(11.0, 11.0, 13.0, 12.5, [0.0, 1.0, 0.0, 1.0]), &region, 2.0);
+
+        for y in 0..8 {
+            for x in 0..8 {
+                let inside = (2..6).contains(&x) && (2..5).contains(&y);
+                let expected = if inside { [0.0, 1.0, 0.0, 1.0] } else { [0.0; 4] };
+                assert_eq!(image.pixels[y * 8 + x], expected, "pixel {}, {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn rasterize_anti_aliases_and_blends() {
+        let mut image = Image::filled(2, 1, [0.0, 0.0, 1.0, 1.0]);
+        let region = Rect::new(point(0.0, 0.0), size(2.0, 1.0));
+        image.rasterize(&rectangle(0.5, 0.0, 2.0, 1.0, [1.0, 0.0, 0.0, 0.5]), &region, 1.0);
+
+        // Half of the first pixel is covered by the half transparent red.
+        assert_eq!(image.pixels[0], [0.25, 0.0, 0.75, 1.0]);
+        assert_eq!(image.pixels[1], [0.5, 0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn texture_has_straight_alpha() {
+        let image = Image { width: 2, height: 1, pixels: vec![[0.5, 0.25, 0.0, 0.5], [0.0; 4]] };
+        assert_eq!(image.to_texture().data, vec![255, 128, 0, 128, 0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,198 @@
+//! Draw-on animation of strokes, as if they were being drawn by hand.
+
+use bevy::{
+    asset::{Assets, Handle},
+    ecs::{entity::Entity, query::{ChangeTrackers, Changed, Or}, system::{Commands, Query, ResMut}},
+    render::mesh::Mesh,
+};
+use lyon_tessellation::{FillTessellator, StrokeTessellator};
+
+use crate::{Convert, plugin::{TessellationOptions, mesh_order, tessellate_path_with}, svg::{DrawType, Svg}, vertex_buffer::{VertexBuffers, append}};
+
+/// Component which only draws the first `progress` fraction of the length of
+/// each stroked path of a [`Svg`]. Filled paths are always drawn completely.
+///
+/// Animate `progress` from `0.0` to `1.0` to reveal the strokes. Strokes of
+/// filtered groups and pattern-painted strokes are child entities with their
+/// own meshes, they are always drawn completely.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SvgStrokeReveal {
+    /// Fraction of each stroke that gets drawn, between `0.0` and `1.0`.
+    pub progress: f32,
+}
+
+impl SvgStrokeReveal {
+    /// Create a [`SvgStrokeReveal`] with the given progress.
+    pub fn new(progress: f32) -> SvgStrokeReveal {
+        SvgStrokeReveal { progress }
+    }
+}
+
+/// Fill geometry and stroke lengths of a revealed [`Svg`], so that only the
+/// strokes need to be tessellated again when the progress changes.
+pub(crate) struct StrokeRevealCache {
+    /// Tessellated fill or length of the stroke of each path, in the order
+    /// the paths get drawn.
+    parts: Vec<RevealPart>,
+}
+
+enum RevealPart {
+    Fill(VertexBuffers),
+    Stroke { index: usize, length: f32 },
+}
+
+/// Bevy system which re-tessellates the strokes of a [`Svg`] when the
+/// progress of its [`SvgStrokeReveal`] changes. The cache is built again
+/// when the [`Svg`] itself changes.
+pub(crate) fn svg_stroke_reveal(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut fill_tess: ResMut<FillTessellator>,
+    mut stroke_tess: ResMut<StrokeTessellator>,
+    query: Query<
+        (Entity, &Svg, ChangeTrackers<Svg>, &SvgStrokeReveal, &Handle<Mesh>, Option<&StrokeRevealCache>),
+        Or<(Changed<SvgStrokeReveal>, Changed<Svg>)>
+    >,
+) {
+    for (entity, svg, trackers, reveal, mesh, cache) in query.iter() {
+        let cache = cache.filter(|_| !trackers.is_changed());
+        let new_cache = match cache {
+            Some(_) => None,
+            None => Some(reveal_cache(&mut fill_tess, &mut stroke_tess, svg)),
+        };
+        let cache = match (cache, new_cache.as_ref()) {
+            (Some(cache), _) | (None, Some(cache)) => cache,
+            (None, None) => continue,
+        };
+
+        let progress = reveal.progress.max(0.0).min(1.0);
+        let mut buffers = VertexBuffers::new();
+        for part in cache.parts.iter() {
+            match *part {
+                RevealPart::Fill(ref fill) => append(&mut buffers, fill),
+                RevealPart::Stroke { index, length } => {
+                    let path = &svg.paths[index];
+                    let segments = path.segments_to_length(length * progress);
+                    tessellate_path_with(
+                        &mut fill_tess, &mut stroke_tess, path, segments, TessellationOptions::of(svg), &mut buffers,
+                    );
+                },
+            }
+        }
+        if let Some(ref nine_slice) = svg.nine_slice {
+            nine_slice.apply(&svg.view_box, &mut buffers);
+        }
+
+        if let Some(mesh) = meshes.get_mut(mesh) {
+            *mesh = buffers.convert();
+        }
+
+        if let Some(new_cache) = new_cache {
+            commands.entity(entity).insert(new_cache);
+        }
+    }
+}
+
+/// Tessellate the fills of a [`Svg`] and measure its strokes.
+fn reveal_cache(
+    fill_tess: &mut FillTessellator,
+    stroke_tess: &mut StrokeTessellator,
+    svg: &Svg,
+) -> StrokeRevealCache {
+    let parts = mesh_order(&svg.paths, svg.anti_alias.is_some()).into_iter()
+        .map(|index| (index, &svg.paths[index]))
+        .map(|(index, path)| match path.draw_type {
+            DrawType::Fill => {
+                let mut buffers = VertexBuffers::new();
+                tessellate_path_with(
+                    fill_tess, stroke_tess, path, path.segments.iter().cloned(),
+                    TessellationOptions::of(svg), &mut buffers,
+                );
+                RevealPart::Fill(buffers)
+            },
+            DrawType::Stroke(_) => RevealPart::Stroke { index, length: path.length() },
+        })
+        .collect();
+
+    StrokeRevealCache { parts }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        app::App,
+        asset::{AddAsset, AssetPlugin},
+        core::CorePlugin,
+        ecs::{schedule::{Stage, SystemStage}, system::IntoSystem, world::World},
+        render::pipeline::PrimitiveTopology,
+    };
+
+    use super::*;
+    use crate::test_util::{assert_near, load};
+
+    fn stroke_count(world: &World, entity: Entity) -> usize {
+        world.get::<StrokeRevealCache>(entity).unwrap().parts.iter()
+            .filter(|part| matches!(part, RevealPart::Stroke { .. }))
+            .count()
+    }
+
+    #[test]
+    fn cache_is_rebuilt_when_the_svg_changes() {
+        // The mesh assets need the asset server.
+        let mut app = App::build();
+        app.add_plugin(CorePlugin).add_plugin(AssetPlugin).add_asset::<Mesh>();
+        let world = &mut app.app.world;
+        world.insert_resource(FillTessellator::new());
+        world.insert_resource(StrokeTessellator::new());
+        let mesh = world.get_resource_mut::<Assets<Mesh>>().unwrap()
+            .add(Mesh::new(PrimitiveTopology::TriangleList));
+        let entity = world.spawn()
+            .insert(load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+                <path d="M0 5H8" fill="none" stroke="black"/>
+            </svg>"#))
+            .insert(SvgStrokeReveal::new(0.5))
+            .insert(mesh)
+            .id();
+
+        let mut stage = SystemStage::single(svg_stroke_reveal.system());
+        stage.run(world);
+        assert_eq!(stroke_count(world, entity), 1);
+
+        world.entity_mut(entity).insert(load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+            <path d="M0 5H8" fill="none" stroke="black"/>
+            <path d="M0 2H8" fill="none" stroke="black"/>
+        </svg>"#));
+        stage.run(world);
+        assert_eq!(stroke_count(world, entity), 2);
+    }
+
+    #[test]
+    fn cache_keeps_fills_and_measures_strokes() {
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+            <rect width="10" height="10" fill="red" stroke="black"/>
+            <path d="M0 5H8" fill="none" stroke="black"/>
+        </svg>"#);
+        let cache = reveal_cache(&mut FillTessellator::new(), &mut StrokeTessellator::new(), &svg);
+
+        // Drawn back to front, like the SVG mesh.
+        assert_eq!(cache.parts.len(), 3);
+        match cache.parts[0] {
+            RevealPart::Stroke { index, length } => {
+                assert_eq!(index, 2);
+                assert_near(length, 8.0, 1e-4);
+            },
+            _ => panic!("expected the stroke of the path"),
+        }
+        match cache.parts[1] {
+            RevealPart::Stroke { index, length } => {
+                assert_eq!(index, 1);
+                assert_near(length, 40.0, 1e-4);
+            },
+            _ => panic!("expected the stroke of the rectangle"),
+        }
+        match cache.parts[2] {
+            RevealPart::Fill(ref buffers) => assert!(!buffers.indices.is_empty()),
+            _ => panic!("expected the fill of the rectangle"),
+        }
+    }
+}
@@ -0,0 +1,349 @@
+//! Sampling of points on the shapes of a SVG, e.g. to emit particles.
+
+use std::cmp::Ordering;
+
+use bevy::{log::error, math::Vec3};
+use lyon_svg::path::{PathEvent, iterator::PathIterator};
+use lyon_tessellation::{
+    BuffersBuilder, FillOptions, FillTessellator, FillVertex, VertexBuffers,
+    math::{Point, Vector, vector},
+};
+use crate::{
+    geometry::contours,
+    svg::{DrawType, Svg},
+};
+
+/// Default maximum distance between a curve and its flattened outline.
+const DEFAULT_TOLERANCE: f32 = 0.05;
+
+/// A point sampled by a [`SvgShapeSampler`].
+///
+/// Positions and normals are in the coordinate space of the tessellated mesh,
+/// i.e. SVG user units with the y-axis pointing down, before the Bevy
+/// [`Transform`](bevy::transform::components::Transform) is applied. The z of
+/// the position is the z of the sampled path.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShapeSample {
+    /// Position of the point.
+    pub position: Vec3,
+    /// Unit normal at the point. Inside filled regions it is the normal of the
+    /// mesh, `(0, 0, 1)`. On the outline of a fill it points outwards, on a
+    /// stroke it points to the left of the stroke's direction.
+    pub normal: Vec3,
+}
+
+/// Samples points inside the filled regions and along the outlines of a
+/// [`Svg`], to feed a particle system.
+///
+/// The sampler flattens and tessellates the paths once, so build it once per
+/// SVG and keep it around. The random functions take a source of uniformly
+/// distributed numbers in `0.0..1.0`, e.g. from the `rand` crate.
+///
+/// ```ignore
+/// let sampler = SvgShapeSampler::new(&svg);
+/// let mut rng = rand::thread_rng();
+/// let dust = (0..100).filter_map(|_| sampler.sample_area(|| rng.gen()));
+/// let sparkles = sampler.outline_points(8.0);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SvgShapeSampler {
+    triangles: Vec<Triangle>,
+    /// Summed area of the triangles up to and including each triangle.
+    areas: Vec<f32>,
+    outlines: Vec<Outline>,
+    /// Summed length of the outlines up to and including each outline.
+    lengths: Vec<f32>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Triangle {
+    points: [Point; 3],
+    z: f32,
+}
+
+/// A flattened outline. Edge `i` goes from point `i` to point `i + 1`.
+#[derive(Clone, Debug)]
+struct Outline {
+    points: Vec<Point>,
+    normals: Vec<Vector>,
+    /// Distance along the outline of each point.
+    distances: Vec<f32>,
+    z: f32,
+}
+
+impl SvgShapeSampler {
+    /// Build a sampler for the paths of `svg`, including the ones of filtered
+    /// groups and the pattern-painted ones.
+    pub fn new(svg: &Svg) -> SvgShapeSampler {
+        SvgShapeSampler::with_tolerance(svg, DEFAULT_TOLERANCE)
+    }
+
+    /// Build a sampler for the paths of `svg`, flattening curves with the
+    /// given tolerance.
+    pub fn with_tolerance(svg: &Svg, tolerance: f32) -> SvgShapeSampler {
+        let mut sampler = SvgShapeSampler::default();
+        let mut fill_tess = FillTessellator::new();
+
+        for path in svg.drawn_paths() {
+            match path.draw_type {
+                DrawType::Fill => {
+                    let mut buffers: VertexBuffers<Point, u32> = VertexBuffers::new();
+                    if let Err(e) = fill_tess.tessellate(
+                        path.segments.iter().cloned(),
+                        &FillOptions::tolerance(tolerance),
+                        &mut BuffersBuilder::new(&mut buffers, |vertex: FillVertex| vertex.position()),
+                    ) {
+                        error!("FillTessellator error: {:?}", e);
+                    }
+                    for triangle in buffers.indices.chunks_exact(3) {
+                        let vertex = |i: usize| buffers.vertices[triangle[i] as usize];
+                        sampler.push_triangle(Triangle { points: [vertex(0), vertex(1), vertex(2)], z: path.z });
+                    }
+
+                    for contour in contours(&path.segments, tolerance) {
+                        let mut points = contour.points;
+                        points.push(points[0]);
+                        sampler.push_outline(points, contour.normals, path.z);
+                    }
+                },
+                DrawType::Stroke(_) => {
+                    for points in polylines(&path.segments, tolerance) {
+                        let normals = points.windows(2)
+                            .map(|edge| {
+                                let d = edge[1] - edge[0];
+                                vector(d.y, -d.x).normalize()
+                            })
+                            .collect();
+                        sampler.push_outline(points, normals, path.z);
+                    }
+                },
+            }
+        }
+
+        sampler
+    }
+
+    /// Total area of the filled regions.
+    pub fn area(&self) -> f32 {
+        self.areas.last().copied().unwrap_or(0.0)
+    }
+
+    /// Total length of the outlines of the fills and of the strokes.
+    pub fn outline_length(&self) -> f32 {
+        self.lengths.last().copied().unwrap_or(0.0)
+    }
+
+    /// A uniformly distributed random point inside the filled regions. Returns
+    /// `None` if the SVG has no filled area.
+    pub fn sample_area(&self, mut random: impl FnMut() -> f32) -> Option<ShapeSample> {
+        let total = self.area();
+        if total <= 0.0 {
+            return None;
+        }
+        let i = find(&self.areas, random() * total);
+        let Triangle { points: [a, b, c], z } = self.triangles[i];
+
+        // Folding the unit square onto the triangle keeps the distribution
+        // uniform.
+        let (mut u, mut v) = (random(), random());
+        if u + v > 1.0 {
+            u = 1.0 - u;
+            v = 1.0 - v;
+        }
+        let p = a + (b - a) * u + (c - a) * v;
+        Some(ShapeSample {
+            position: Vec3::new(p.x, p.y, z),
+            normal: Vec3::new(0.0, 0.0, 1.0),
+        })
+    }
+
+    /// A uniformly distributed random point on the outlines of the fills and
+    /// on the strokes. Returns `None` if the SVG has no outlines.
+    pub fn sample_outline(&self, mut random: impl FnMut() -> f32) -> Option<ShapeSample> {
+        let total = self.outline_length();
+        if total <= 0.0 {
+            return None;
+        }
+        let i = find(&self.lengths, random() * total);
+        let outline = &self.outlines[i];
+        Some(outline.sample(random() * outline.length()))
+    }
+
+    /// Points along the outlines of the fills and along the strokes, `spacing`
+    /// units apart. Each outline starts with a point at its beginning.
+    pub fn outline_points(&self, spacing: f32) -> Vec<ShapeSample> {
+        if spacing <= 0.0 {
+            return Vec::new();
+        }
+        let mut samples = Vec::new();
+        for outline in self.outlines.iter() {
+            let length = outline.length();
+            let mut distance = 0.0;
+            while distance <= length {
+                samples.push(outline.sample(distance));
+                distance += spacing;
+            }
+        }
+        samples
+    }
+
+    fn push_triangle(&mut self, triangle: Triangle) {
+        let [a, b, c] = triangle.points;
+        let area = (b - a).cross(c - a).abs() / 2.0;
+        if area <= 0.0 {
+            return;
+        }
+        self.areas.push(self.area() + area);
+        self.triangles.push(triangle);
+    }
+
+    fn push_outline(&mut self, points: Vec<Point>, normals: Vec<Vector>, z: f32) {
+        let mut distances = Vec::with_capacity(points.len());
+        let mut distance = 0.0;
+        distances.push(distance);
+        for edge in points.windows(2) {
+            distance += (edge[1] - edge[0]).length();
+            distances.push(distance);
+        }
+        if distance <= 0.0 {
+            return;
+        }
+        self.lengths.push(self.outline_length() + distance);
+        self.outlines.push(Outline { points, normals, distances, z });
+    }
+}
+
+impl Outline {
+    fn length(&self) -> f32 {
+        self.distances.last().copied().unwrap_or(0.0)
+    }
+
+    /// The point at the given distance along the outline.
+    fn sample(&self, distance: f32) -> ShapeSample {
+        let i = find(&self.distances[1..], distance).min(self.normals.len() - 1);
+        let (from, to) = (self.distances[i], self.distances[i + 1]);
+        let t = if to > from { ((distance - from) / (to - from)).max(0.0).min(1.0) } else { 0.0 };
+        let p = self.points[i].lerp(self.points[i + 1], t);
+        ShapeSample {
+            position: Vec3::new(p.x, p.y, self.z),
+            normal: Vec3::new(self.normals[i].x, self.normals[i].y, 0.0),
+        }
+    }
+}
+
+/// Index of the first value of the ascending `sums` greater than `value`,
+/// clamped to the last index.
+fn find(sums: &[f32], value: f32) -> usize {
+    let i = match sums.binary_search_by(|sum| sum.partial_cmp(&value).unwrap_or(Ordering::Less)) {
+        Ok(i) => i + 1,
+        Err(i) => i,
+    };
+    i.min(sums.len().saturating_sub(1))
+}
+
+/// Flatten the segments into polylines, one per sub-path. Closed sub-paths
+/// end with their first point.
+fn polylines(segments: &[PathEvent], tolerance: f32) -> Vec<Vec<Point>> {
+    let mut polylines = Vec::new();
+    let mut polyline: Vec<Point> = Vec::new();
+    for event in segments.iter().cloned().flattened(tolerance) {
+        match event {
+            PathEvent::Begin { at } => {
+                polyline.clear();
+                polyline.push(at);
+            },
+            PathEvent::Line { to, .. } => {
+                if polyline.last().map_or(true, |last| (*last - to).square_length() > f32::EPSILON) {
+                    polyline.push(to);
+                }
+            },
+            PathEvent::End { first, close, .. } => {
+                if close && polyline.last().map_or(false, |last| (*last - first).square_length() > f32::EPSILON) {
+                    polyline.push(first);
+                }
+                if polyline.len() >= 2 {
+                    polylines.push(std::mem::take(&mut polyline));
+                }
+            },
+            _ => {},
+        }
+    }
+    polylines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{assert_near, load};
+
+    /// A deterministic source of numbers in `0.0..1.0`.
+    fn sequence() -> impl FnMut() -> f32 {
+        let mut state = 0.0;
+        move || {
+            state = (state + 0.618_034) % 1.0;
+            state
+        }
+    }
+
+    #[test]
+    fn find_cumulative_index() {
+        let sums = [1.0, 3.0, 6.0];
+        assert_eq!(find(&sums, 0.5), 0);
+        assert_eq!(find(&sums, 1.0), 1);
+        assert_eq!(find(&sums, 5.9), 2);
+        assert_eq!(find(&sums, 10.0), 2);
+        assert_eq!(find(&[], 1.0), 0);
+    }
+
+    #[test]
+    fn square_area_and_outline() {
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+            <rect width="10" height="10"/>
+        </svg>"#);
+        let sampler = SvgShapeSampler::new(&svg);
+        assert_near(sampler.area(), 100.0, 1e-3);
+        assert_near(sampler.outline_length(), 40.0, 1e-3);
+
+        let mut random = sequence();
+        for _ in 0..100 {
+            let sample = sampler.sample_area(&mut random).unwrap();
+            assert!((0.0..=10.0).contains(&sample.position.x) && (0.0..=10.0).contains(&sample.position.y));
+            assert_eq!(sample.normal, Vec3::new(0.0, 0.0, 1.0));
+
+            let sample = sampler.sample_outline(&mut random).unwrap();
+            let (x, y) = (sample.position.x, sample.position.y);
+            assert!(x.abs() < 1e-3 || y.abs() < 1e-3 || (x - 10.0).abs() < 1e-3 || (y - 10.0).abs() < 1e-3);
+            // Normals point outwards.
+            let outward = Vec3::new(x - 5.0, y - 5.0, 0.0).dot(sample.normal);
+            assert!(outward > 0.0);
+        }
+    }
+
+    #[test]
+    fn evenly_spaced_outline_points() {
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+            <path d="M0 5H10" stroke="black" fill="none"/>
+        </svg>"#);
+        let sampler = SvgShapeSampler::new(&svg);
+        assert_eq!(sampler.area(), 0.0);
+        assert_eq!(sampler.sample_area(sequence()), None);
+
+        let points = sampler.outline_points(2.5);
+        let x: Vec<f32> = points.iter().map(|sample| sample.position.x).collect();
+        assert_eq!(x, vec![0.0, 2.5, 5.0, 7.5, 10.0]);
+        // Left of the stroke's direction, which is up with the y-axis pointing down.
+        assert!(points.iter().all(|sample| sample.normal == Vec3::new(0.0, -1.0, 0.0)));
+        assert!(sampler.outline_points(0.0).is_empty());
+    }
+
+    #[test]
+    fn includes_pattern_and_filtered_paths() {
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <pattern id="dots" width="2" height="2" patternUnits="userSpaceOnUse"><circle cx="1" cy="1" r="1"/></pattern>
+            <filter id="blur"><feGaussianBlur stdDeviation="1"/></filter>
+            <rect width="10" height="10" fill="url(#dots)"/>
+            <rect x="20" width="10" height="10" filter="url(#blur)"/>
+        </svg>"#);
+        assert_near(SvgShapeSampler::new(&svg).area(), 200.0, 1e-3);
+    }
+}
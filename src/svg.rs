@@ -1,13 +1,31 @@
-use std::{io::Read, path::PathBuf};
-use bevy::{math::{Vec2, Vec3}, prelude::{Color, Transform}};
+use std::{collections::HashMap, io::Read, path::PathBuf};
+use bevy::{math::{Vec2, Vec3}, prelude::{Color, Mesh, Transform}, reflect::TypeUuid};
+use flate2::read::GzDecoder;
 use lyon_svg::parser::ViewBox;
-use lyon_tessellation::math::Point;
+use lyon_tessellation::math::{Point, Transform as Transform2D};
 use usvg::NodeExt;
 
-use crate::bundle::SvgBundle;
+use crate::{
+    animation::{ElementTransform, SvgAnimation, element_transforms, local_transforms, parse_animations},
+    bundle::{SvgBundle, SvgUiBundle},
+    extrude::{SvgExtrusion, extrude},
+    filter::SvgFilter,
+    level::{LevelElements, SvgLayer, SvgObject, extend_bounds, parse_level},
+    limits::{SvgLimit, SvgLimits},
+    nine_slice::{NINE_SLICE_ATTRIBUTE, NineSlice},
+    pattern::{SvgPattern, find_pattern},
+    metadata::{ElementAttributes, custom_attributes, element_id, parse_xml, with_generated_ids},
+};
+#[cfg(feature = "collider")]
+use crate::collider::{ColliderKind, ColliderOptions, ColliderTarget};
 
 /// A loaded and deserialized SVG file.
-#[derive(Debug)]
+///
+/// Also a Bevy asset, loaded from `.svg` and `.svgz` files by the
+/// [`AssetServer`](bevy::asset::AssetServer) and spawned with a
+/// [`SvgAssetBundle`](crate::bundle::SvgAssetBundle).
+#[derive(Clone, Debug, TypeUuid)]
+#[uuid = "95bff4f0-e7e7-4eca-ad33-dd815b83943e"]
 pub struct Svg {
     /// The name of the file.
     pub name: String,
@@ -19,7 +37,46 @@ pub struct Svg {
     pub view_box: ViewBox,
     /// Origin of the coordinate system and as such the origin for the Bevy position.
     pub origin: Origin,
+    /// The paths of the SVG, in document order.
     pub paths: Vec<PathDescriptor>,
+    /// Inkscape layers, only imported with [`SvgBuilder::import_level`].
+    pub layers: Vec<SvgLayer>,
+    /// Marked elements, only imported with [`SvgBuilder::import_level`].
+    pub objects: Vec<SvgObject>,
+    /// SMIL animations of the SVG's elements, played back by a
+    /// [`SvgAnimationPlayer`](crate::animation::SvgAnimationPlayer).
+    pub animations: Vec<SvgAnimation>,
+    /// Static transforms of the elements targeted by the animations, by `id`.
+    pub(crate) element_transforms: HashMap<String, ElementTransform>,
+    /// Nine-slice scaling of the SVG, if any.
+    pub nine_slice: Option<NineSlice>,
+    /// Groups with a filter applied to them, drawn just above the z of their paths.
+    pub filters: Vec<SvgFilter>,
+    /// Paths painted with a pattern, drawn just above their z.
+    pub patterns: Vec<SvgPattern>,
+    /// Width of the anti-aliasing fringe around every shape, in SVG units, if
+    /// any.
+    pub anti_alias: Option<f32>,
+    /// How the UV coordinates of the mesh are computed.
+    pub uv_mode: UvMode,
+    /// Colliders to generate when the SVG gets spawned.
+    #[cfg(feature = "collider")]
+    pub colliders: Option<ColliderOptions>,
+}
+
+impl Svg {
+    /// All drawn paths, including the ones of filtered groups and the
+    /// pattern-painted ones, ordered by z. With a
+    /// [`SvgBuilder::z_range`] that is the document order, otherwise filtered
+    /// and pattern-painted paths come last.
+    pub(crate) fn drawn_paths(&self) -> Vec<&PathDescriptor> {
+        let mut paths: Vec<&PathDescriptor> = self.paths.iter()
+            .chain(self.filters.iter().flat_map(|filter| filter.paths.iter()))
+            .chain(self.patterns.iter().map(|pattern| &pattern.path))
+            .collect();
+        paths.sort_by(|a, b| a.z.partial_cmp(&b.z).unwrap_or(std::cmp::Ordering::Equal));
+        paths
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -37,6 +94,27 @@ impl Default for Origin {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+/// The rectangle that the UV coordinates of a SVG mesh are normalized to. The
+/// top left corner of the rectangle gets the UV `(0, 0)`.
+pub enum UvMode {
+    /// The view box of the SVG, so a texture spans the whole SVG. This is the
+    /// default.
+    ViewBox,
+    /// The bounding box of each path, including its stroke, so a texture
+    /// spans every path on its own.
+    PathBounds,
+}
+
+impl Default for UvMode {
+    fn default() -> Self {
+        UvMode::ViewBox
+    }
+}
+
+/// The first bytes of gzip-compressed data.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
 enum Data<'a> {
     Bytes(&'a [u8]),
     File(PathBuf),
@@ -52,40 +130,53 @@ pub struct SvgBuilder<'a> {
     origin: Origin,
     translation: Vec3,
     scale: Vec2,
+    layer_depth: Option<f32>,
+    z_range: Option<f32>,
+    limits: SvgLimits,
+    nine_slice: Option<NineSlice>,
+    anti_alias: Option<f32>,
+    uv_mode: UvMode,
+    raster_resolution: f32,
+    #[cfg(feature = "collider")]
+    colliders: Option<ColliderOptions>,
 }
 
 impl<'a> SvgBuilder<'a> {
     /// Create a [`SvgBuilder`] to load a SVG from a file.
     pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> SvgBuilder<'a> {
         let path = PathBuf::from(path.as_ref());
-        SvgBuilder {
-            name: path.file_name().unwrap().to_string_lossy().to_string(),
-            data: Data::File(path),
-            origin: Origin::default(),
-            translation: Vec3::default(),
-            scale: Vec2::new(1.0, 1.0),
-        }
+        SvgBuilder::new(
+            path.file_name().unwrap().to_string_lossy().to_string(),
+            Data::File(path),
+        )
     }
 
     /// Create a [`SvgBuilder`] from a reader.
     pub fn from_reader<R: 'static + std::io::Read>(reader: R, name: &str) -> SvgBuilder<'a> {
-        SvgBuilder {
-            name: name.to_string(),
-            data: Data::Reader(Box::new(reader)),
-            origin: Origin::default(),
-            translation: Vec3::default(),
-            scale: Vec2::new(1.0, 1.0),
-        }
+        SvgBuilder::new(name.to_string(), Data::Reader(Box::new(reader)))
     }
 
     /// Create a [`SvgBuilder`] from bytes.
     pub fn from_bytes(bytes: &'a [u8], name: &str) -> SvgBuilder<'a> {
+        SvgBuilder::new(name.to_string(), Data::Bytes(bytes))
+    }
+
+    fn new(name: String, data: Data<'a>) -> SvgBuilder<'a> {
         SvgBuilder {
-            name: name.to_string(),
-            data: Data::Bytes(bytes),
+            name,
+            data,
             origin: Origin::default(),
             translation: Vec3::default(),
             scale: Vec2::new(1.0, 1.0),
+            layer_depth: None,
+            z_range: None,
+            limits: SvgLimits::default(),
+            nine_slice: None,
+            anti_alias: None,
+            uv_mode: UvMode::default(),
+            raster_resolution: 1.0,
+            #[cfg(feature = "collider")]
+            colliders: None,
         }
     }
 
@@ -109,46 +200,262 @@ impl<'a> SvgBuilder<'a> {
         self
     }
 
+    /// Import Inkscape layers and elements marked with `data-*` or
+    /// `inkscape:label` attributes as [`SvgLayer`] and [`SvgObject`] child
+    /// entities. Each layer, together with its paths, is placed `layer_depth`
+    /// further along the z-axis than the previous one. Use the [`SvgObjectRegistry`](crate::level::SvgObjectRegistry)
+    /// resource to map attributes of objects to your own components.
+    pub fn import_level(mut self, layer_depth: f32) -> SvgBuilder<'a> {
+        self.layer_depth = Some(layer_depth);
+        self
+    }
+
+    /// Spread the paths evenly across `0.0..=range` along the z-axis, in
+    /// document order, instead of drawing them all at `z = 0.0`. Entities
+    /// placed in between, relative to the SVG entity, are drawn between the
+    /// paths, e.g. a character between the background and the foreground of a
+    /// scene. With [`SvgBuilder::import_level`], the depth of each path's layer
+    /// is added on top.
+    pub fn z_range(mut self, range: f32) -> SvgBuilder<'a> {
+        self.z_range = Some(range);
+        self
+    }
+
+    /// Refuse to load SVGs that exceed the given [`SvgLimits`], e.g. when
+    /// loading user-generated content. [`SvgBuilder::build`] then returns a
+    /// [`SvgLimitError`](crate::limits::SvgLimitError).
+    pub fn limits(mut self, limits: SvgLimits) -> SvgBuilder<'a> {
+        self.limits = limits;
+        self
+    }
+
+    /// Use nine-slice scaling with the given insets, in SVG units, instead of
+    /// scaling the whole SVG. The corners keep their size, while the edges and
+    /// the center get stretched to the scaled size. The insets are in CSS
+    /// order, `top right bottom left`, like in the `data-nine-slice` attribute
+    /// on the root `<svg>` element, which they override.
+    pub fn nine_slice(mut self, top: f32, right: f32, bottom: f32, left: f32) -> SvgBuilder<'a> {
+        self.nine_slice = Some(NineSlice::new(top, right, bottom, left));
+        self
+    }
+
+    /// Anti-alias the edges of the SVG with a fringe of the given width, in SVG
+    /// units, that fades the shapes out. About one pixel divided by the scale
+    /// of the SVG on screen gives smooth edges without enabling [`Msaa`](bevy::render::pass::Msaa),
+    /// e.g. on WebGL. Shapes grow by the width on every side.
+    pub fn anti_alias(mut self, fringe_width: f32) -> SvgBuilder<'a> {
+        self.anti_alias = Some(fringe_width);
+        self
+    }
+
+    /// Change how the UV coordinates of the mesh are computed, see [`UvMode`].
+    pub fn uv_mode(mut self, uv_mode: UvMode) -> SvgBuilder<'a> {
+        self.uv_mode = uv_mode;
+        self
+    }
+
+    /// Change the resolution at which groups with a `filter` and pattern tiles
+    /// get rasterized, in pixels per SVG unit. The default is `1.0`, increase it
+    /// for SVGs that are scaled up.
+    pub fn raster_resolution(mut self, pixels_per_unit: f32) -> SvgBuilder<'a> {
+        self.raster_resolution = pixels_per_unit;
+        self
+    }
+
+    /// Generate colliders of the given kind from the paths of the SVG when it
+    /// gets spawned. They are inserted as [`SvgCollider`](crate::collider::SvgCollider)
+    /// components on the SVG entity or on one child entity per path.
+    #[cfg(feature = "collider")]
+    pub fn colliders(mut self, kind: ColliderKind, target: ColliderTarget) -> SvgBuilder<'a> {
+        self.colliders = Some(ColliderOptions { kind, target });
+        self
+    }
+
     /// Load and finish the SVG content into a [`SvgBundle`], which then will be
     /// spawned by the [`SvgPlugin`].
     pub fn build<'s>(self) -> Result<SvgBundle, Box<dyn std::error::Error>> {
+        let (position, scale) = (self.translation, self.scale);
+        let mut svg = self.load()?;
+
+        let translation = match svg.origin {
+            Origin::Center => position + Vec3::new(
+                -svg.width as f32 * scale.x / 2.0,
+                svg.height as f32 * scale.y / 2.0,
+                0.0
+            ),
+            Origin::TopLeft => position,
+        };
+
+        // With nine-slice scaling the mesh gets the scaled size instead.
+        let scaled_size = Vec2::new(svg.view_box.w as f32 * scale.x, svg.view_box.h as f32 * scale.y);
+        if let Some(nine_slice) = svg.nine_slice.as_mut() {
+            nine_slice.size = scaled_size;
+            return Ok(SvgBundle::new(svg).at_position(translation));
+        }
+
+        Ok(SvgBundle::new(svg).at_position(translation).with_scale(scale))
+    }
+
+    /// Load and finish the SVG content into a [`SvgUiBundle`], to show it in
+    /// a Bevy UI node. The origin, position and scale are ignored, the SVG
+    /// gets scaled to the size of the node instead.
+    pub fn build_ui(self) -> Result<SvgUiBundle, Box<dyn std::error::Error>> {
+        Ok(SvgUiBundle::new(self.load()?))
+    }
+
+    /// Load the SVG and extrude its filled paths by `depth` into a closed 3D
+    /// [`Mesh`] with normals and UVs, e.g. for a `PbrBundle`. The origin and
+    /// scale are applied to the mesh, the position is ignored.
+    pub fn extrude(self, depth: f32) -> Result<Mesh, Box<dyn std::error::Error>> {
+        self.extrude_with(SvgExtrusion { depth, ..Default::default() })
+    }
+
+    /// Same as [`SvgBuilder::extrude`], with a bevel and tolerance from the
+    /// given [`SvgExtrusion`].
+    pub fn extrude_with(self, extrusion: SvgExtrusion) -> Result<Mesh, Box<dyn std::error::Error>> {
+        let scale = self.scale;
+        let svg = self.load()?;
+        Ok(extrude(&svg, &extrusion, scale))
+    }
+
+    /// Load and deserialize the SVG content. Gzip-compressed data, i.e. `.svgz`
+    /// files, gets decompressed first.
+    pub(crate) fn load(self) -> Result<Svg, Box<dyn std::error::Error>> {
         let mut opt = usvg::Options::default();
         opt.fontdb.load_system_fonts();
 
+        // Never read more than one byte past the limit, which is enough to
+        // know that it was exceeded.
+        let max_read = self.limits.max_bytes.map_or(u64::MAX, |max| max as u64 + 1);
+
         let mut svg_data = Vec::new();
         match self.data {
             Data::Bytes(bytes) => svg_data = bytes.to_vec(),
             Data::File(path) => {
-                let mut file = std::fs::File::open(path)?;
-                file.read_to_end(&mut svg_data)?;
+                let file = std::fs::File::open(path)?;
+                file.take(max_read).read_to_end(&mut svg_data)?;
             },
-            Data::Reader(mut reader) => { reader.read_to_end(&mut svg_data)?; },
+            Data::Reader(reader) => { reader.take(max_read).read_to_end(&mut svg_data)?; },
+        }
+        SvgLimits::check(self.limits.max_bytes, SvgLimit::Bytes, svg_data.len())?;
+
+        if svg_data.starts_with(&GZIP_MAGIC) {
+            let mut decompressed = Vec::new();
+            GzDecoder::new(svg_data.as_slice()).take(max_read).read_to_end(&mut decompressed)?;
+            SvgLimits::check(self.limits.max_bytes, SvgLimit::Bytes, decompressed.len())?;
+            svg_data = decompressed;
         }
 
-        let svg_tree = usvg::Tree::from_data(&svg_data, &opt)?;
+        // Groups with an `id` are kept, so that paths know the ids of their
+        // ancestor groups.
+        opt.keep_named_groups = true;
+
+        // usvg drops custom attributes and animations, so they are collected
+        // in a side pass. Elements with custom attributes but without an `id`
+        // get a generated one to find them in the usvg tree.
+        let (attributes, level, animations, locals, declared_nine_slice, usvg_data) = match std::str::from_utf8(&svg_data) {
+            Ok(text) => {
+                let xml = parse_xml(text)?;
+                self.limits.check_xml(&xml)?;
+                let attributes = custom_attributes(&xml);
+                let level = self.layer_depth.map(|depth| parse_level(&xml, &attributes, depth));
+                let animations = parse_animations(&xml);
+                let locals = local_transforms(&xml, &animations);
+                let nine_slice = xml.root_element().attribute(NINE_SLICE_ATTRIBUTE).and_then(NineSlice::parse);
+                let usvg_data = with_generated_ids(text, &xml).into_bytes();
+                (attributes, level, animations, locals, nine_slice, Some(usvg_data))
+            },
+            Err(_) => (ElementAttributes::default(), None, Vec::new(), HashMap::new(), None, None),
+        };
+
+        let svg_tree = usvg::Tree::from_data(usvg_data.as_deref().unwrap_or(&svg_data), &opt)?;
 
         let view_box = svg_tree.svg_node().view_box;
         let size = svg_tree.svg_node().size;
-
-        let translation = match self.origin {
-            Origin::Center => self.translation + Vec3::new(
-                -size.width() as f32 * self.scale.x / 2.0,
-                size.height() as f32 * self.scale.y / 2.0,
-                0.0
-            ),
-            Origin::TopLeft => self.translation,
+        let nine_slice = self.nine_slice.or(declared_nine_slice).map(|nine_slice| NineSlice {
+            size: Vec2::new(view_box.rect.width() as f32, view_box.rect.height() as f32),
+            ..nine_slice
+        });
+
+        // Every fill and stroke gets its z in document order, including the
+        // ones of filtered groups and the pattern-painted ones.
+        let count: usize = svg_tree.root().descendants()
+            .filter(|node| !in_defs(node))
+            .map(|node| match *node.borrow() {
+                usvg::NodeKind::Path(ref p) => p.fill.is_some() as usize + p.stroke.is_some() as usize,
+                _ => 0,
+            })
+            .sum();
+        let z_range = self.z_range;
+        let z_of = |index: usize, layer: Option<usize>| {
+            let layer_depth = layer
+                .and_then(|layer| level.as_ref()?.layers.get(layer))
+                .map_or(0.0, |layer| layer.depth);
+            let spread = match z_range {
+                Some(range) if count > 1 => range * index as f32 / (count - 1) as f32,
+                _ => 0.0,
+            };
+            layer_depth + spread
         };
+        let mut index = 0;
 
+        let mut counter = self.limits.counter();
         let mut descriptors = Vec::new();
-
-        for node in svg_tree.root().descendants() {
+        // Paths in groups with a filter are collected per outermost filtered group.
+        let mut filtered_groups: Vec<(usvg::Node, Vec<PathDescriptor>)> = Vec::new();
+        let mut patterns = Vec::new();
+        // Bounds of the elements containing paths by their usvg id, for the
+        // level's objects.
+        let mut element_bounds = HashMap::new();
+
+        for node in svg_tree.root().descendants().filter(|node| !in_defs(node)) {
             if let usvg::NodeKind::Path(ref p) = *node.borrow() {
+                SvgLimits::check(self.limits.max_path_segments, SvgLimit::PathSegments, p.data.len())?;
+
+                let filtered_group = node.ancestors().skip(1)
+                    .filter(|n| matches!(*n.borrow(), usvg::NodeKind::Group(ref g) if g.filter.is_some()))
+                    .last();
+                let target = match filtered_group {
+                    Some(group) => {
+                        let index = match filtered_groups.iter().position(|(g, _)| *g == group) {
+                            Some(index) => index,
+                            None => {
+                                filtered_groups.push((group, Vec::new()));
+                                filtered_groups.len() - 1
+                            },
+                        };
+                        &mut filtered_groups[index].1
+                    },
+                    None => &mut descriptors,
+                };
+
+                let group_ids: Vec<String> = node.ancestors().skip(1)
+                    .map(|n| element_id(n.borrow().id()).to_string())
+                    .filter(|id| !id.is_empty())
+                    .collect();
+                let layer = level.as_ref().and_then(|level| {
+                    group_ids.iter().find_map(|id| level.layers.iter().position(|layer| &layer.id == id))
+                });
+                let path_attributes = attributes.get(&p.id).cloned().unwrap_or_default();
+                let element_ids: Vec<String> = if level.is_some() {
+                    node.ancestors()
+                        .map(|n| n.borrow().id().to_string())
+                        .filter(|id| !id.is_empty())
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+
+                // The transforms of the element and its ancestors are applied to
+                // the segments. usvg instantiates markers as paths in transformed
+                // groups, so this also places them along their parent path.
                 let t = node.abs_transform();
+                let transform = Transform2D::new(t.a as f32, t.b as f32, t.c as f32, t.d as f32, t.e as f32, t.f as f32);
                 let mat = bevy::math::Mat4::from_cols(
                     [t.a as f32, t.b as f32, 0.0, 0.0].into(),
                     [t.c as f32, t.d as f32, 0.0, 0.0].into(),
-                    [t.e as f32, t.f as f32, 1.0, 0.0].into(),
-                    [0.0, 0.0, 0.0, 1.0].into()
+                    [0.0, 0.0, 1.0, 0.0].into(),
+                    [t.e as f32, t.f as f32, 0.0, 1.0].into()
                 );
 
                 if let Some(ref fill) = p.fill {
@@ -158,28 +465,80 @@ impl<'a> SvgBuilder<'a> {
                         _ => Color::default(),
                     };
 
-                    descriptors.push(PathDescriptor {
-                        segments: convert_path(p).collect(),
+                    let descriptor = PathDescriptor {
+                        segments: convert_path(p).map(|event| event.transformed(&transform)).collect(),
                         abs_transform: Transform::from_matrix(mat),
                         color,
                         draw_type: DrawType::Fill,
-                    });
+                        id: element_id(&p.id).to_string(),
+                        group_ids: group_ids.clone(),
+                        attributes: path_attributes.clone(),
+                        layer,
+                        z: z_of(index, layer),
+                    };
+                    index += 1;
+                    extend_bounds(&mut element_bounds, &element_ids, &descriptor);
+                    match find_pattern(&svg_tree, &fill.paint) {
+                        Some(pattern) => patterns.extend(SvgPattern::new(
+                            &pattern, descriptor, &transform, fill.opacity.value() as f32,
+                            self.raster_resolution, &mut counter,
+                        )?),
+                        None => target.push(descriptor),
+                    }
                 }
 
                 if let Some(ref stroke) = p.stroke {
                     let (color, stroke_opts) = convert_stroke(stroke);
+                    // Stroke widths scale with the average scale of the transform.
+                    let stroke_opts = stroke_opts.with_line_width(stroke_opts.line_width * transform.determinant().abs().sqrt());
 
-                    descriptors.push(PathDescriptor {
-                        segments: convert_path(p).collect(),
+                    let descriptor = PathDescriptor {
+                        segments: convert_path(p).map(|event| event.transformed(&transform)).collect(),
                         abs_transform: Transform::from_matrix(mat),
                         color,
                         draw_type: DrawType::Stroke(stroke_opts),
-                    });
+                        id: element_id(&p.id).to_string(),
+                        group_ids,
+                        attributes: path_attributes,
+                        layer,
+                        z: z_of(index, layer),
+                    };
+                    index += 1;
+                    extend_bounds(&mut element_bounds, &element_ids, &descriptor);
+                    match find_pattern(&svg_tree, &stroke.paint) {
+                        Some(pattern) => patterns.extend(SvgPattern::new(
+                            &pattern, descriptor, &transform, stroke.opacity.value() as f32,
+                            self.raster_resolution, &mut counter,
+                        )?),
+                        None => target.push(descriptor),
+                    }
                 }
             }
         }
 
-        let svg = Svg {
+        counter.add_paths(&descriptors)?;
+
+        let raster_resolution = self.raster_resolution;
+        let filters: Vec<SvgFilter> = filtered_groups.into_iter()
+            .filter_map(|(group, paths)| SvgFilter::new(&svg_tree, &group, paths, raster_resolution))
+            .collect();
+        for filter in filters.iter() {
+            counter.add_paths(&filter.paths)?;
+            let (width, height) = filter.image_size();
+            counter.add_image(width, height)?;
+        }
+
+        let (layers, objects) = match level {
+            Some(LevelElements { layers, mut objects }) => {
+                for object in objects.iter_mut() {
+                    object.bounds = element_bounds.get(&object.key).map(|b| b.to_rect()).unwrap_or_default();
+                }
+                (layers, objects)
+            },
+            None => (Vec::new(), Vec::new()),
+        };
+
+        Ok(Svg {
             name: self.name.to_string(),
             width: size.width(),
             height: size.height(),
@@ -191,28 +550,69 @@ impl<'a> SvgBuilder<'a> {
             },
             origin: self.origin,
             paths: descriptors,
-        };
-
-        Ok(SvgBundle::new(svg).at_position(translation).with_scale(self.scale))
+            layers,
+            objects,
+            animations,
+            element_transforms: element_transforms(&svg_tree, &locals),
+            nine_slice,
+            filters,
+            patterns,
+            anti_alias: self.anti_alias,
+            uv_mode: self.uv_mode,
+            #[cfg(feature = "collider")]
+            colliders: self.colliders,
+        })
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct PathDescriptor {
+    /// The segments of the path, in the coordinate system of the SVG.
     pub segments: Vec<lyon_svg::path::PathEvent>,
+    /// Transform from the element to the coordinate system of the SVG, which
+    /// is already applied to the segments. Skews can't be represented.
     pub abs_transform: Transform,
     pub color: Color,
     pub draw_type: DrawType,
+    /// The `id` of the SVG element, empty if it has none.
+    pub id: String,
+    /// The ids of the groups containing the element, innermost first. Groups
+    /// without an `id` are skipped.
+    pub group_ids: Vec<String>,
+    /// Custom attributes of the element, i.e. `class`, all `data-*` attributes
+    /// and all attributes in foreign namespaces like `inkscape:label`.
+    pub attributes: HashMap<String, String>,
+    /// Index of the [`SvgLayer`] containing the path, if any.
+    pub layer: Option<usize>,
+    /// Z coordinate of the path's vertices in the mesh. Paths with a higher z
+    /// are drawn in front, paths with the same z in document order.
+    pub z: f32,
 }
 
-#[derive(Debug)]
+impl PathDescriptor {
+    /// The path with `transform` applied to its segments and its stroke width.
+    pub(crate) fn transformed(&self, transform: &Transform2D) -> PathDescriptor {
+        let draw_type = match self.draw_type {
+            DrawType::Fill => DrawType::Fill,
+            DrawType::Stroke(opts) =>
+                DrawType::Stroke(opts.with_line_width(opts.line_width * transform.determinant().abs().sqrt())),
+        };
+        PathDescriptor {
+            segments: self.segments.iter().map(|event| event.transformed(transform)).collect(),
+            draw_type,
+            ..self.clone()
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub enum DrawType {
     Fill,
     Stroke(lyon_tessellation::StrokeOptions),
 }
 
 // Taken from https://github.com/nical/lyon/blob/74e6b137fea70d71d3b537babae22c6652f8843e/examples/wgpu_svg/src/main.rs
-struct PathConvIter<'a> {
+pub(crate) struct PathConvIter<'a> {
     iter: std::slice::Iter<'a, usvg::PathSegment>,
     prev: Point,
     first: Point,
@@ -306,7 +706,7 @@ fn point(x: &f64, y: &f64) -> Point {
     Point::new((*x) as f32, (*y) as f32)
 }
 
-fn convert_path<'a>(p: &'a usvg::Path) -> PathConvIter<'a> {
+pub(crate) fn convert_path<'a>(p: &'a usvg::Path) -> PathConvIter<'a> {
     PathConvIter {
         iter: p.data.iter(),
         first: Point::new(0.0, 0.0),
@@ -316,7 +716,29 @@ fn convert_path<'a>(p: &'a usvg::Path) -> PathConvIter<'a> {
     }
 }
 
-fn convert_stroke(s: &usvg::Stroke) -> (Color, lyon_tessellation::StrokeOptions) {
+/// Whether the node is inside `<defs>`, like the content of a pattern, which
+/// is only drawn where it is referenced.
+fn in_defs(node: &usvg::Node) -> bool {
+    node.ancestors().any(|n| matches!(*n.borrow(), usvg::NodeKind::Defs))
+}
+
+/// Parse the content of a `d` attribute, by letting `usvg` normalize a
+/// document containing only that path.
+pub(crate) fn parse_path_data(d: &str) -> Option<Vec<lyon_svg::path::PathEvent>> {
+    let text = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="1" height="1"><path d="{}"/></svg>"#,
+        d,
+    );
+    let tree = usvg::Tree::from_str(&text, &usvg::Options::default()).ok()?;
+    let node = tree.root().descendants().find(|node| matches!(*node.borrow(), usvg::NodeKind::Path(_)))?;
+    let segments = match *node.borrow() {
+        usvg::NodeKind::Path(ref p) => Some(convert_path(p).collect()),
+        _ => None,
+    };
+    segments
+}
+
+pub(crate) fn convert_stroke(s: &usvg::Stroke) -> (Color, lyon_tessellation::StrokeOptions) {
     let color = match s.paint {
         usvg::Paint::Color(c) =>
             Color::rgba_u8(c.red, c.green, c.blue, s.opacity.to_u8()),
@@ -341,3 +763,67 @@ fn convert_stroke(s: &usvg::Stroke) -> (Color, lyon_tessellation::StrokeOptions)
 
     (color, opt)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::assert_near;
+
+    #[test]
+    fn z_range_spreads_all_paths_in_document_order() {
+        let text = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <defs>
+                <pattern id="dots" width="10" height="10" patternUnits="userSpaceOnUse">
+                    <circle cx="5" cy="5" r="3"/>
+                </pattern>
+                <filter id="blur"><feGaussianBlur stdDeviation="2"/></filter>
+            </defs>
+            <rect width="100" height="100" fill="url(#dots)"/>
+            <g filter="url(#blur)"><rect x="10" y="10" width="20" height="20"/></g>
+            <rect x="50" y="50" width="10" height="10" stroke="black"/>
+        </svg>"#;
+        let svg = SvgBuilder::from_bytes(text.as_bytes(), "test.svg").z_range(3.0).load().unwrap();
+
+        assert_eq!(svg.patterns.len(), 1);
+        assert_near(svg.patterns[0].path.z, 0.0, 1e-6);
+        assert_eq!(svg.filters.len(), 1);
+        assert_near(svg.filters[0].paths[0].z, 1.0, 1e-6);
+        let z: Vec<f32> = svg.paths.iter().map(|path| path.z).collect();
+        assert_eq!(z, vec![2.0, 3.0]);
+    }
+
+    #[test]
+    fn no_z_without_range() {
+        let text = r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+            <rect width="5" height="5"/><rect x="5" width="5" height="5"/>
+        </svg>"#;
+        let svg = SvgBuilder::from_bytes(text.as_bytes(), "test.svg").load().unwrap();
+        assert!(svg.paths.iter().all(|path| path.z == 0.0));
+    }
+
+    #[test]
+    fn abs_transform_of_nested_elements() {
+        let text = r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+            <g transform="translate(10 20)"><rect transform="scale(2)" width="5" height="5" stroke="black"/></g>
+        </svg>"#;
+        let svg = SvgBuilder::from_bytes(text.as_bytes(), "test.svg").load().unwrap();
+        let path = &svg.paths[1];
+        let m = path.abs_transform.compute_matrix();
+        let transform = Transform2D::new(m.x_axis.x, m.x_axis.y, m.y_axis.x, m.y_axis.y, m.w_axis.x, m.w_axis.y);
+        let corner = transform.transform_point(Point::new(5.0, 5.0));
+        assert_near(corner.x, 20.0, 1e-5);
+        assert_near(corner.y, 30.0, 1e-5);
+
+        let local = path.transformed(&transform.inverse().unwrap());
+        let bounds = local.bounds().unwrap();
+        assert_near(bounds.max_x(), 5.0, 1e-4);
+        assert_near(bounds.max_y(), 5.0, 1e-4);
+        match (&path.draw_type, &local.draw_type) {
+            (DrawType::Stroke(abs), DrawType::Stroke(local)) => {
+                assert_near(abs.line_width, 2.0, 1e-5);
+                assert_near(local.line_width, 1.0, 1e-5);
+            },
+            _ => panic!("expected strokes"),
+        }
+    }
+}
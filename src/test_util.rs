@@ -0,0 +1,84 @@
+//! Helpers shared by the unit tests.
+
+use std::collections::HashMap;
+
+use bevy::{render::color::Color, transform::components::Transform};
+use lyon_svg::path::PathEvent;
+use lyon_tessellation::{
+    BuffersBuilder, FillOptions, FillTessellator, FillVertex, StrokeOptions, VertexBuffers,
+    math::{Point, point},
+};
+
+use crate::svg::{DrawType, PathDescriptor, Svg, SvgBuilder};
+
+/// The segments of a closed polygon.
+pub(crate) fn polygon(points: &[(f32, f32)]) -> Vec<PathEvent> {
+    let points: Vec<Point> = points.iter().map(|&(x, y)| point(x, y)).collect();
+    let mut segments = vec![PathEvent::Begin { at: points[0] }];
+    for edge in points.windows(2) {
+        segments.push(PathEvent::Line { from: edge[0], to: edge[1] });
+    }
+    segments.push(PathEvent::End { last: points[points.len() - 1], first: points[0], close: true });
+    segments
+}
+
+/// The segments of a closed axis-aligned rectangle.
+pub(crate) fn rect(x: f32, y: f32, width: f32, height: f32) -> Vec<PathEvent> {
+    polygon(&[(x, y), (x + width, y), (x + width, y + height), (x, y + height)])
+}
+
+/// A filled path without metadata.
+pub(crate) fn fill(segments: Vec<PathEvent>) -> PathDescriptor {
+    PathDescriptor {
+        segments,
+        abs_transform: Transform::identity(),
+        color: Color::BLACK,
+        draw_type: DrawType::Fill,
+        id: String::new(),
+        group_ids: Vec::new(),
+        attributes: HashMap::new(),
+        layer: None,
+        z: 0.0,
+    }
+}
+
+/// A stroked path without metadata.
+pub(crate) fn stroke(segments: Vec<PathEvent>, width: f32) -> PathDescriptor {
+    PathDescriptor {
+        draw_type: DrawType::Stroke(StrokeOptions::default().with_line_width(width)),
+        ..fill(segments)
+    }
+}
+
+/// Load a SVG document with the default builder settings.
+pub(crate) fn load(text: &str) -> Svg {
+    SvgBuilder::from_bytes(text.as_bytes(), "test.svg").load().unwrap()
+}
+
+/// Area enclosed by the segments according to the even-odd rule, measured by
+/// summing the triangles of the fill tessellation.
+pub(crate) fn fill_area(segments: &[PathEvent]) -> f32 {
+    let mut buffers: VertexBuffers<Point, u32> = VertexBuffers::new();
+    FillTessellator::new()
+        .tessellate(
+            segments.iter().cloned(),
+            &FillOptions::tolerance(0.01),
+            &mut BuffersBuilder::new(&mut buffers, |vertex: FillVertex| vertex.position()),
+        )
+        .unwrap();
+    buffers.indices.chunks_exact(3)
+        .map(|t| {
+            let (a, b, c) = (buffers.vertices[t[0] as usize], buffers.vertices[t[1] as usize], buffers.vertices[t[2] as usize]);
+            (b - a).cross(c - a).abs() / 2.0
+        })
+        .sum()
+}
+
+/// Assert that two floats are within `epsilon` of each other.
+#[track_caller]
+pub(crate) fn assert_near(actual: f32, expected: f32, epsilon: f32) {
+    assert!(
+        (actual - expected).abs() <= epsilon,
+        "expected {} to be within {} of {}", actual, epsilon, expected,
+    );
+}
@@ -0,0 +1,169 @@
+//! Rendering of SVGs inside Bevy UI nodes.
+//!
+//! Entities with a [`Node`] are drawn by the UI pass with the UI camera, so a
+//! [`SvgUiBundle`](crate::bundle::SvgUiBundle) is drawn in the same z-order as
+//! the surrounding UI. Bevy's UI has no clipping, so neither do SVGs in it.
+//!
+//! The SVG is scaled with the [`Transform`] of its node, and the y-axis is
+//! flipped. Child nodes inherit that scale, so give the SVG node no children
+//! and put it next to the other nodes instead. SVGs with a [`NineSlice`] keep
+//! a scale of 1 and get the size of the node as nine-slice size, so that their
+//! borders don't stretch.
+
+use bevy::{
+    ecs::{entity::Entity, system::{Commands, Query}},
+    math::Vec3,
+    transform::components::Transform,
+    ui::Node,
+};
+
+use crate::{nine_slice::NineSlice, svg::Svg};
+
+/// How a SVG in a UI node is scaled to the size of the node. SVGs with a
+/// [`NineSlice`] always fill the node.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SvgUiFit {
+    /// Scale uniformly so that the whole SVG fits into the node, centered.
+    Fit,
+    /// Stretch the SVG to fill the node.
+    Fill,
+}
+
+impl Default for SvgUiFit {
+    fn default() -> Self {
+        SvgUiFit::Fit
+    }
+}
+
+/// Translation of a SVG node as laid out by the UI and as moved to the SVG's
+/// top left corner.
+pub(crate) struct SvgUiPlacement {
+    layout: Vec3,
+    placed: Vec3,
+}
+
+/// Bevy system which scales and positions SVGs in UI nodes after the UI
+/// layout has been computed.
+///
+/// The layout moves the translation of every node to its center. The SVG is
+/// placed relative to the last translation written by the layout, so that it
+/// doesn't drift when the layout leaves the node alone.
+pub(crate) fn svg_ui_layout(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Svg, &Node, &SvgUiFit, &mut Transform, Option<&mut SvgUiPlacement>)>,
+) {
+    for (entity, mut svg, node, fit, mut transform, placement) in query.iter_mut() {
+        let (mut width, mut height) = (svg.view_box.w as f32, svg.view_box.h as f32);
+        if width <= 0.0 || height <= 0.0 {
+            continue;
+        }
+
+        let nine_slice = svg.nine_slice;
+        let (sx, sy) = match (nine_slice, fit) {
+            (Some(nine_slice), _) => {
+                // Only write a new size, every change tessellates the SVG again.
+                if nine_slice.size != node.size {
+                    svg.nine_slice = Some(NineSlice { size: node.size, ..nine_slice });
+                }
+                width = node.size.x;
+                height = node.size.y;
+                (1.0, 1.0)
+            },
+            (None, SvgUiFit::Fill) => (node.size.x / width, node.size.y / height),
+            (None, SvgUiFit::Fit) => {
+                let scale = (node.size.x / width).min(node.size.y / height);
+                (scale, scale)
+            },
+        };
+
+        // Any other translation than the one placed last time comes from the
+        // layout.
+        let layout = match placement {
+            Some(ref placement) if transform.translation == placement.placed => placement.layout,
+            _ => transform.translation,
+        };
+        let offset = Vec3::new(
+            -width * sx / 2.0 - svg.view_box.x as f32 * sx,
+            height * sy / 2.0 + svg.view_box.y as f32 * sy,
+            0.0,
+        );
+        let placed = layout + offset;
+
+        // SVG coordinates point down, UI coordinates point up.
+        let scale = Vec3::new(sx, -sy, 1.0);
+        if transform.scale != scale || transform.translation != placed {
+            transform.scale = scale;
+            transform.translation = placed;
+        }
+
+        match placement {
+            Some(mut placement) => *placement = SvgUiPlacement { layout, placed },
+            None => { commands.entity(entity).insert(SvgUiPlacement { layout, placed }); },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::{
+        ecs::{schedule::{Stage, SystemStage}, system::IntoSystem, world::World},
+        math::Vec2,
+    };
+
+    use crate::test_util::load;
+
+    fn spawn(world: &mut World, fit: SvgUiFit) -> Entity {
+        spawn_svg(world, fit, load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="50"/>"#))
+    }
+
+    fn spawn_svg(world: &mut World, fit: SvgUiFit, svg: Svg) -> Entity {
+        world.spawn()
+            .insert_bundle((svg, Node { size: Vec2::new(200.0, 200.0) }, fit, Transform::from_xyz(50.0, 60.0, 1.0)))
+            .id()
+    }
+
+    #[test]
+    fn placed_from_the_layout_translation() {
+        let mut world = World::default();
+        let entity = spawn(&mut world, SvgUiFit::Fit);
+        let mut stage = SystemStage::single(svg_ui_layout.system());
+
+        stage.run(&mut world);
+        let transform = *world.get::<Transform>(entity).unwrap();
+        assert_eq!(transform.scale, Vec3::new(2.0, -2.0, 1.0));
+        assert_eq!(transform.translation, Vec3::new(-50.0, 110.0, 1.0));
+
+        // Without a new layout the SVG stays in place.
+        stage.run(&mut world);
+        assert_eq!(world.get::<Transform>(entity).unwrap().translation, Vec3::new(-50.0, 110.0, 1.0));
+
+        // The layout moved the node.
+        world.get_mut::<Transform>(entity).unwrap().translation = Vec3::new(0.0, 0.0, 1.0);
+        stage.run(&mut world);
+        assert_eq!(world.get::<Transform>(entity).unwrap().translation, Vec3::new(-100.0, 50.0, 1.0));
+    }
+
+    #[test]
+    fn fill_stretches() {
+        let mut world = World::default();
+        let entity = spawn(&mut world, SvgUiFit::Fill);
+        SystemStage::single(svg_ui_layout.system()).run(&mut world);
+        assert_eq!(world.get::<Transform>(entity).unwrap().scale, Vec3::new(2.0, -4.0, 1.0));
+    }
+
+    #[test]
+    fn nine_slice_gets_the_node_size() {
+        let mut world = World::default();
+        let svg = load(r#"<svg xmlns="http://www.w3.org/2000/svg" width="100" height="50" data-nine-slice="10"/>"#);
+        let entity = spawn_svg(&mut world, SvgUiFit::Fit, svg);
+        SystemStage::single(svg_ui_layout.system()).run(&mut world);
+
+        let transform = *world.get::<Transform>(entity).unwrap();
+        assert_eq!(transform.scale, Vec3::new(1.0, -1.0, 1.0));
+        assert_eq!(transform.translation, Vec3::new(-50.0, 160.0, 1.0));
+        let nine_slice = world.get::<Svg>(entity).unwrap().nine_slice.unwrap();
+        assert_eq!(nine_slice.size, Vec2::new(200.0, 200.0));
+        assert_eq!(nine_slice.left, 10.0);
+    }
+}
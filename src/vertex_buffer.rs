@@ -9,10 +9,18 @@ use crate::Convert;
 /// [`Mesh`](bevy::render::mesh::Mesh).
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub(crate) struct Vertex {
-    position: [f32; 3],
-    color: [f32; 4],
+    pub(crate) position: [f32; 3],
+    pub(crate) color: [f32; 4],
+    /// Fraction of the pixel covered by the shape, which fades out the
+    /// anti-aliasing fringe.
+    pub(crate) coverage: f32,
+    /// Texture coordinates, see [`UvMode`](crate::svg::UvMode).
+    pub(crate) uv: [f32; 2],
 }
 
+/// Name of the coverage attribute of SVG meshes.
+pub(crate) const ATTRIBUTE_COVERAGE: &str = "Vertex_Coverage";
+
 /// The index type of a Bevy [`Mesh`](bevy::render::mesh::Mesh).
 pub(crate) type IndexType = u32;
 
@@ -23,10 +31,14 @@ impl Convert<Mesh> for VertexBuffers {
     fn convert(self) -> Mesh {
         let mut positions = Vec::with_capacity(self.vertices.len());
         let mut colors = Vec::with_capacity(self.vertices.len());
+        let mut coverages = Vec::with_capacity(self.vertices.len());
+        let mut uvs = Vec::with_capacity(self.vertices.len());
 
         self.vertices.iter().for_each(|v| {
             positions.push(v.position);
             colors.push(v.color);
+            coverages.push(v.coverage);
+            uvs.push(v.uv);
         });
 
         let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
@@ -39,6 +51,19 @@ impl Convert<Mesh> for VertexBuffers {
             Mesh::ATTRIBUTE_COLOR,
             colors
         );
+        mesh.set_attribute(
+            ATTRIBUTE_COVERAGE,
+            coverages
+        );
+        mesh.set_attribute(
+            Mesh::ATTRIBUTE_UV_0,
+            uvs
+        );
+        // SVG meshes are flat, so every normal points along the z-axis.
+        mesh.set_attribute(
+            Mesh::ATTRIBUTE_NORMAL,
+            vec![[0.0, 0.0, 1.0]; self.vertices.len()]
+        );
 
         mesh
     }
@@ -55,6 +80,8 @@ impl FillVertexConstructor<Vertex> for VertexConstructor {
         Vertex {
             position: [vertex.position().x, vertex.position().y, 0.0],
             color: [self.color.r(), self.color.g(), self.color.b(), self.color.a()],
+            coverage: 1.0,
+            uv: [0.0, 0.0],
         }
     }
 }
@@ -65,6 +92,56 @@ impl StrokeVertexConstructor<Vertex> for VertexConstructor {
         Vertex {
             position: [vertex.position().x, vertex.position().y, 0.0],
             color: [self.color.r(), self.color.g(), self.color.b(), self.color.a()],
+            coverage: 1.0,
+            uv: [0.0, 0.0],
+        }
+    }
+}
+
+/// Append the vertices and indices of `other` to `buffers`.
+pub(crate) fn append(buffers: &mut VertexBuffers, other: &VertexBuffers) {
+    let offset = buffers.vertices.len() as IndexType;
+    buffers.vertices.extend_from_slice(&other.vertices);
+    buffers.indices.extend(other.indices.iter().map(|i| i + offset));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::mesh::VertexAttributeValues;
+
+    fn triangle(x: f32) -> VertexBuffers {
+        let mut buffers = VertexBuffers::new();
+        for &(dx, dy) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)].iter() {
+            buffers.vertices.push(Vertex { position: [x + dx, dy, 0.0], color: [1.0; 4], coverage: 1.0, uv: [dx, dy] });
         }
+        buffers.indices.extend_from_slice(&[0, 1, 2]);
+        buffers
+    }
+
+    #[test]
+    fn append_offsets_indices() {
+        let mut buffers = triangle(0.0);
+        append(&mut buffers, &triangle(5.0));
+        assert_eq!(buffers.vertices.len(), 6);
+        assert_eq!(buffers.indices, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(buffers.vertices[3].position, [5.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn mesh_has_all_attributes() {
+        let mesh = triangle(0.0).convert();
+        assert_eq!(mesh.count_vertices(), 3);
+        assert!(matches!(mesh.indices(), Some(Indices::U32(indices)) if indices == &vec![0, 1, 2]));
+        assert!(matches!(mesh.attribute(ATTRIBUTE_COVERAGE), Some(VertexAttributeValues::Float(c)) if c == &vec![1.0; 3]));
+        assert!(matches!(
+            mesh.attribute(Mesh::ATTRIBUTE_UV_0),
+            Some(VertexAttributeValues::Float2(uvs)) if uvs[1] == [1.0, 0.0]
+        ));
+        assert!(matches!(
+            mesh.attribute(Mesh::ATTRIBUTE_NORMAL),
+            Some(VertexAttributeValues::Float3(normals)) if normals.iter().all(|n| *n == [0.0, 0.0, 1.0])
+        ));
+        assert!(mesh.attribute(Mesh::ATTRIBUTE_COLOR).is_some());
     }
 }